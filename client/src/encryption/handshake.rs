@@ -0,0 +1,169 @@
+use anyhow::anyhow;
+use log::{trace, warn};
+use packets::{
+    communication::{
+        handshake::{derive_session_key, generate_ephemeral, generate_nonce, get_ephemeral_public, sign_ephemeral, verify_ephemeral, HandshakeBinding, HandshakeRole},
+        key_reply::SymmKeyReplyMsg,
+        key_request::WantSymmKeyMsg,
+    },
+    encryption::ratchet::Ratchet,
+    types::ByteMessage,
+};
+use tokio_tungstenite::tungstenite::Message;
+use openssl::pkey::{PKey, Private};
+use uuid::Uuid;
+
+use crate::util::{
+    arcs::{get_curr_id, get_curr_keypair},
+    consts::{CHAT_RATCHETS, PENDING_HANDSHAKES},
+    msg::send_msg,
+};
+
+use super::{ratchet::has_ratchet, rsa::get_pubkey_from_rec};
+
+// Key exchange we started and wait for the reply of
+pub struct PendingHandshake {
+    pub ephemeral: PKey<Private>,
+    pub nonce: Vec<u8>,
+    pub rekey: bool,
+}
+
+// `rekey` replaces a session the other user may still have, without it the request is rejected in that case
+pub async fn start_handshake(user: &Uuid, rekey: bool) -> anyhow::Result<()> {
+    let curr_id = get_curr_id().await?;
+    let keypair = get_curr_keypair().await?;
+
+    let ephemeral = generate_ephemeral()?;
+    let raw = get_ephemeral_public(&ephemeral)?;
+    let nonce = generate_nonce()?;
+
+    let binding = HandshakeBinding {
+        role: HandshakeRole::Initiator,
+        from: curr_id,
+        to: user.clone(),
+        nonce: nonce.clone(),
+        rekey
+    };
+    let signature = sign_ephemeral(&raw, &binding, &keypair)?;
+
+    let mut state = PENDING_HANDSHAKES.write().await;
+    state.insert(user.clone(), PendingHandshake { ephemeral, nonce: nonce.clone(), rekey });
+
+    drop(state);
+
+    trace!("Starting key exchange with {} (rekey: {})", user, rekey);
+    send_msg(Message::Binary(
+        WantSymmKeyMsg {
+            user: user.clone(),
+            ephemeral: raw,
+            nonce,
+            rekey,
+            signature
+        }.serialize()
+    )).await?;
+
+    return Ok(());
+}
+
+pub async fn is_handshake_pending(user: &Uuid) -> bool {
+    let state = PENDING_HANDSHAKES.read().await;
+    let pending = state.contains_key(user);
+
+    drop(state);
+    return pending;
+}
+
+// We are the responder, `msg.user` initiated the key exchange
+pub async fn on_handshake_request(msg: WantSymmKeyMsg) -> anyhow::Result<()> {
+    let WantSymmKeyMsg { user: initiator, ephemeral: initiator_ephemeral, nonce, rekey, signature } = msg;
+
+    let curr_id = get_curr_id().await?;
+    let keypair = get_curr_keypair().await?;
+
+    let pubkey = get_pubkey_from_rec(&initiator).await?;
+    let binding = HandshakeBinding {
+        role: HandshakeRole::Initiator,
+        from: initiator.clone(),
+        to: curr_id.clone(),
+        nonce,
+        rekey
+    };
+    verify_ephemeral(&initiator_ephemeral, &signature, &binding, &pubkey)?;
+
+    if !rekey && has_ratchet(&initiator).await {
+        // The request may be replayed, so the session is only replaced by a fresh exchange of our own
+        warn!("Ignoring key exchange of {} without rekey, a session already exists. Rekeying instead.", initiator);
+        if !is_handshake_pending(&initiator).await {
+            start_handshake(&initiator, true).await?;
+        }
+
+        return Ok(());
+    }
+
+    let ephemeral = generate_ephemeral()?;
+    let raw = get_ephemeral_public(&ephemeral)?;
+
+    let key = derive_session_key(&ephemeral, &initiator_ephemeral, &initiator, &initiator_ephemeral, &curr_id, &raw, &binding.nonce)?;
+    // Our ephemeral key is the first ratchet key of the responder
+    let ratchet = Ratchet::new_responder(&key, ephemeral)?;
+
+    let mut state = CHAT_RATCHETS.write().await;
+    state.insert(initiator.clone(), ratchet);
+
+    drop(state);
+
+    let binding = HandshakeBinding {
+        role: HandshakeRole::Responder,
+        from: curr_id,
+        to: initiator.clone(),
+        ..binding
+    };
+    let signature = sign_ephemeral(&raw, &binding, &keypair)?;
+    send_msg(Message::Binary(
+        SymmKeyReplyMsg {
+            user: initiator,
+            ephemeral: raw,
+            signature
+        }.serialize()
+    )).await?;
+
+    return Ok(());
+}
+
+// We are the initiator, `msg.user` answered our key exchange
+pub async fn on_handshake_reply(msg: SymmKeyReplyMsg) -> anyhow::Result<()> {
+    let SymmKeyReplyMsg { user: responder, ephemeral: responder_ephemeral, signature } = msg;
+
+    let mut state = PENDING_HANDSHAKES.write().await;
+    let pending = state.remove(&responder);
+
+    drop(state);
+    if pending.is_none() {
+        return Err(anyhow!("Received key exchange reply of {} without requesting it.", responder));
+    }
+
+    let PendingHandshake { ephemeral, nonce, rekey } = pending.unwrap();
+    let curr_id = get_curr_id().await?;
+
+    let pubkey = get_pubkey_from_rec(&responder).await?;
+    let binding = HandshakeBinding {
+        role: HandshakeRole::Responder,
+        from: responder.clone(),
+        to: curr_id.clone(),
+        nonce,
+        rekey
+    };
+    verify_ephemeral(&responder_ephemeral, &signature, &binding, &pubkey)?;
+
+    let raw = get_ephemeral_public(&ephemeral)?;
+    let key = derive_session_key(&ephemeral, &responder_ephemeral, &curr_id, &raw, &responder, &responder_ephemeral, &binding.nonce)?;
+    drop(ephemeral);
+
+    let ratchet = Ratchet::new_initiator(&key, &responder_ephemeral)?;
+
+    let mut state = CHAT_RATCHETS.write().await;
+    state.insert(responder, ratchet);
+
+    drop(state);
+    return Ok(());
+}
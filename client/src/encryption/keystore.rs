@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use colored::Colorize;
+use inquire::Password;
+use log::trace;
+use openssl::{pkey::Private, rsa::Rsa, symm::Cipher};
+use tokio::{fs::{create_dir_all, read, OpenOptions}, io::AsyncWriteExt};
+
+use crate::util::consts::{IDENTITY_PASSPHRASE, IDENTITY_PATH};
+
+use super::rsa::generate;
+
+pub const IDENTITY_FILE: &str = "identity.pem";
+pub const PASSPHRASE_ENV: &str = "RSA_MSG_PASSPHRASE";
+
+pub fn get_config_dir() -> anyhow::Result<PathBuf> {
+    let home = dirs::home_dir();
+    if home.is_none() {
+        return Err(anyhow!("Could not get home directory of current user."));
+    }
+
+    let mut dir = home.unwrap();
+    dir.push(".rsa-msg");
+
+    return Ok(dir);
+}
+
+pub fn get_default_identity_path() -> anyhow::Result<PathBuf> {
+    let mut path = get_config_dir()?;
+    path.push(IDENTITY_FILE);
+
+    return Ok(path);
+}
+
+pub fn prompt_passphrase(msg: &str) -> anyhow::Result<String> {
+    let from_env = std::env::var(PASSPHRASE_ENV);
+    if from_env.is_ok() {
+        trace!("Using passphrase from {}", PASSPHRASE_ENV);
+        return Ok(from_env.unwrap());
+    }
+
+    let passphrase = Password::new(msg).prompt()?;
+    return Ok(passphrase);
+}
+
+pub async fn read_identity(path: &Path, passphrase: &str) -> anyhow::Result<Rsa<Private>> {
+    let pem = read(path).await?;
+    let keypair = Rsa::private_key_from_pem_passphrase(&pem, passphrase.as_bytes());
+
+    if keypair.is_err() {
+        trace!("Could not decrypt identity: {}", keypair.unwrap_err());
+        return Err(anyhow!("Could not decrypt identity at {:?}. Wrong passphrase?", path));
+    }
+
+    return Ok(keypair.unwrap());
+}
+
+pub async fn write_identity(path: &Path, keypair: &Rsa<Private>, passphrase: &str) -> anyhow::Result<()> {
+    if passphrase.is_empty() {
+        return Err(anyhow!("Passphrase of identity can not be empty."));
+    }
+
+    let parent = path.parent();
+    if parent.is_some() && !parent.unwrap().as_os_str().is_empty() {
+        create_dir_all(parent.unwrap()).await?;
+    }
+
+    let pem = keypair.private_key_to_pem_passphrase(Cipher::aes_256_cbc(), passphrase.as_bytes())?;
+    write_private(path, &pem).await?;
+
+    return Ok(());
+}
+
+// Creates the file readable by the owner only, so the key is never visible with the default umask
+async fn write_private(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path).await?;
+
+    // The mode only applies to new files, an existing one is restricted before anything is written
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600)).await?;
+    }
+
+    file.write_all(data).await?;
+    file.flush().await?;
+
+    return Ok(());
+}
+
+pub async fn load_or_generate(path: &Path) -> anyhow::Result<Rsa<Private>> {
+    let passphrase;
+    let keypair;
+
+    if path.is_file() {
+        println!("{}", format!("Loading identity from {}...", path.to_string_lossy().yellow()).green());
+        passphrase = prompt_passphrase("Passphrase of your identity:")?;
+        keypair = read_identity(path, &passphrase).await?;
+    } else {
+        println!("{}", format!("No identity found at {}. Generating RSA keypair...", path.to_string_lossy().yellow()).green());
+        passphrase = prompt_passphrase("Choose a passphrase for your new identity:")?;
+
+        keypair = generate();
+        write_identity(path, &keypair, &passphrase).await?;
+        println!("{}", format!("Identity stored at {}.", path.to_string_lossy().yellow()).green());
+    }
+
+    let mut state = IDENTITY_PATH.write().await;
+    *state = Some(path.to_path_buf());
+
+    drop(state);
+
+    let mut state = IDENTITY_PASSPHRASE.write().await;
+    *state = Some(passphrase);
+
+    drop(state);
+    return Ok(keypair);
+}
+
+pub async fn get_identity_path() -> anyhow::Result<PathBuf> {
+    let state = IDENTITY_PATH.read().await;
+    let path = state.clone();
+
+    drop(state);
+    if path.is_none() {
+        return Err(anyhow!("Identity has not been loaded."));
+    }
+
+    return Ok(path.unwrap());
+}
+
+pub async fn get_identity_passphrase() -> anyhow::Result<String> {
+    let state = IDENTITY_PASSPHRASE.read().await;
+    let passphrase = state.clone();
+
+    drop(state);
+    if passphrase.is_none() {
+        return Err(anyhow!("Identity has not been loaded."));
+    }
+
+    return Ok(passphrase.unwrap());
+}
+
+pub async fn export_identity(to: &Path) -> anyhow::Result<()> {
+    let from = get_identity_path().await?;
+    let pem = read(&from).await?;
+
+    write_private(to, &pem).await?;
+    return Ok(());
+}
+
+// Copies the current identity to `<identity>.old` before it is replaced
+async fn backup_identity(path: &Path) -> anyhow::Result<()> {
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    let mut backup = path.to_path_buf().into_os_string();
+    backup.push(".old");
+
+    tokio::fs::copy(path, &backup).await?;
+    trace!("Previous identity backed up to {:?}", backup);
+
+    return Ok(());
+}
+
+pub async fn import_identity(from: &Path, passphrase: &str) -> anyhow::Result<Rsa<Private>> {
+    let keypair = read_identity(from, passphrase).await?;
+
+    let path = get_identity_path().await?;
+    let curr_passphrase = get_identity_passphrase().await?;
+
+    backup_identity(&path).await?;
+    write_identity(&path, &keypair, &curr_passphrase).await?;
+    return Ok(keypair);
+}
+
+pub async fn rotate_identity() -> anyhow::Result<Rsa<Private>> {
+    let path = get_identity_path().await?;
+    let passphrase = get_identity_passphrase().await?;
+
+    backup_identity(&path).await?;
+
+    let keypair = generate();
+    write_identity(&path, &keypair, &passphrase).await?;
+
+    return Ok(keypair);
+}
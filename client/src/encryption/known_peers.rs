@@ -0,0 +1,199 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::anyhow;
+use colored::Colorize;
+use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::encryption::fingerprint::{format_fingerprint, get_fingerprint};
+use tokio::fs::{create_dir_all, read_to_string, write};
+use uuid::Uuid;
+
+use crate::util::consts::{KNOWN_PEERS, WARNED_PEERS};
+
+use super::keystore::get_config_dir;
+
+pub const KNOWN_PEERS_FILE: &str = "known_peers";
+
+pub fn get_known_peers_path() -> anyhow::Result<PathBuf> {
+    let mut path = get_config_dir()?;
+    path.push(KNOWN_PEERS_FILE);
+
+    return Ok(path);
+}
+
+// Every line is "<name> <hex fingerprint>", just like known_hosts of ssh.
+// Pins are looked up by fingerprint, the name is only the label the key was pinned with.
+async fn load_known_peers() -> anyhow::Result<HashMap<Vec<u8>, String>> {
+    let path = get_known_peers_path()?;
+    let mut peers = HashMap::new();
+
+    if !path.is_file() {
+        return Ok(peers);
+    }
+
+    let content = read_to_string(&path).await?;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+
+        let split = line.rsplit_once(" ");
+        if split.is_none() {
+            trace!("Invalid line in known peers: {}", line);
+            continue;
+        }
+
+        let (name, fingerprint) = split.unwrap();
+        let fingerprint = hex::decode(fingerprint);
+        if fingerprint.is_err() {
+            trace!("Invalid fingerprint in known peers: {}", line);
+            continue;
+        }
+
+        peers.insert(fingerprint.unwrap(), name.to_string());
+    }
+
+    return Ok(peers);
+}
+
+async fn save_known_peers(peers: &HashMap<Vec<u8>, String>) -> anyhow::Result<()> {
+    let path = get_known_peers_path()?;
+    let parent = path.parent();
+    if parent.is_some() {
+        create_dir_all(parent.unwrap()).await?;
+    }
+
+    let mut entries: Vec<(&Vec<u8>, &String)> = peers.iter().collect();
+    entries.sort_by(|a, b| a.1.cmp(b.1).then(a.0.cmp(b.0)));
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(fingerprint, name)| format!("{} {}", name, hex::encode(fingerprint)))
+        .collect();
+
+    write(&path, lines.join("\n") + "\n").await?;
+    return Ok(());
+}
+
+// Name the key has been pinned with
+pub async fn get_known_name(fingerprint: &Vec<u8>) -> anyhow::Result<Option<String>> {
+    let mut state = KNOWN_PEERS.write().await;
+    if state.is_none() {
+        *state = Some(load_known_peers().await?);
+    }
+
+    let name = state.as_ref().unwrap().get(fingerprint).cloned();
+    drop(state);
+
+    return Ok(name);
+}
+
+pub async fn get_known_fingerprint(name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut state = KNOWN_PEERS.write().await;
+    if state.is_none() {
+        *state = Some(load_known_peers().await?);
+    }
+
+    let fingerprint = state.as_ref().unwrap().iter()
+        .find(|(_, e)| e.as_str() == name)
+        .map(|(fingerprint, _)| fingerprint.clone());
+
+    drop(state);
+    return Ok(fingerprint);
+}
+
+// Pins the key with `name`, `None` forgets every key pinned with that name
+pub async fn set_known_fingerprint(name: &str, fingerprint: Option<Vec<u8>>) -> anyhow::Result<()> {
+    let mut state = KNOWN_PEERS.write().await;
+    if state.is_none() {
+        *state = Some(load_known_peers().await?);
+    }
+
+    let peers = state.as_mut().unwrap();
+    if fingerprint.is_some() {
+        peers.insert(fingerprint.unwrap(), name.to_string());
+    } else {
+        peers.retain(|_, e| e.as_str() != name);
+    }
+
+    let res = save_known_peers(peers).await;
+    drop(state);
+
+    return res;
+}
+
+// True the first time a renamed peer with this key is warned about, names are looked up for every message
+async fn should_warn(fingerprint: &Vec<u8>) -> bool {
+    let mut state = WARNED_PEERS.write().await;
+    let is_new = state.insert(fingerprint.clone());
+
+    drop(state);
+    return is_new;
+}
+
+// Names come from the server, so a pinned key is recognized by its fingerprint whatever the server calls it.
+// The name only tells which pinned key a new one replaces.
+pub async fn verify_peer_key(uuid: &Uuid, name: &Option<String>, pubkey: &Rsa<Public>) -> anyhow::Result<()> {
+    let fingerprint = get_fingerprint(pubkey)?;
+    let known_name = get_known_name(&fingerprint).await?;
+    if known_name.is_some() {
+        let known_name = known_name.unwrap();
+        let is_renamed = name.is_some() && name.as_ref().unwrap() != &known_name;
+        if is_renamed && should_warn(&fingerprint).await {
+            println!(
+                "{}",
+                format!("'{}' has the key you pinned for '{}'.", name.as_ref().unwrap(), known_name.yellow()).yellow()
+            );
+        }
+
+        return Ok(());
+    }
+
+    // Without a name there is nothing to pin the key with, a server could hand out any key that way
+    if name.is_none() {
+        return Err(anyhow!(
+            "'{}' has no name, so its key (fingerprint {}) can not be trusted on first use. Compare it with /verify and pin it with /verify trust <name>.",
+            uuid,
+            format_fingerprint(&fingerprint)
+        ));
+    }
+
+    let name = name.clone().unwrap();
+    let known = get_known_fingerprint(&name).await?;
+
+    if known.is_none() {
+        println!(
+            "{}",
+            format!(
+                "Trusting new key of '{}' on first use (fingerprint {}). Use /verify to compare it out of band.",
+                name.yellow(),
+                format_fingerprint(&fingerprint)
+            ).bright_black()
+        );
+
+        set_known_fingerprint(&name, Some(fingerprint)).await?;
+        return Ok(());
+    }
+
+    let known = known.unwrap();
+    if known != fingerprint {
+        eprintln!("{}", "@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@".on_red());
+        eprintln!("{}", "@    WARNING: PEER IDENTIFICATION HAS CHANGED!    @".on_red());
+        eprintln!("{}", "@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@".on_red());
+        eprintln!(
+            "{}",
+            format!(
+                "The key of '{}' changed. Someone (e.g. the server) could be doing a man-in-the-middle attack.\nKnown fingerprint:    {}\nReceived fingerprint: {}\nIf the change is legitimate, verify it out of band and run /verify forget {}",
+                name,
+                format_fingerprint(&known),
+                format_fingerprint(&fingerprint),
+                name
+            ).red()
+        );
+
+        return Err(anyhow!("Public key of '{}' does not match the known fingerprint.", name));
+    }
+
+    return Ok(());
+}
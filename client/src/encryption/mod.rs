@@ -1 +1,6 @@
 pub mod rsa;
+pub mod keystore;
+pub mod known_peers;
+pub mod handshake;
+pub mod ratchet;
+pub mod offline;
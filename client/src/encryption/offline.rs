@@ -0,0 +1,136 @@
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+use anyhow::anyhow;
+use colored::Colorize;
+use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::{communication::to_offline::ToOfflineMsg, encryption::{fingerprint::{format_fingerprint, get_fingerprint}, sealed::{get_unix_secs, is_sealed_expired, open_from_identity, seal_to_identity, SealedContent}}, types::ByteMessage, util::compression::{compress_msg, decompress_msg}};
+use tokio::fs::{read_to_string, write};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::util::{arcs::get_curr_keypair, consts::{CHAT_RATCHETS, LEFT_PEERS, PEER_KEYS, SEEN_MESSAGES}, msg::send_msg};
+
+use super::{keystore::get_identity_path, known_peers::get_known_name};
+
+pub const SEEN_MESSAGES_EXT: &str = "seen";
+
+pub async fn has_left(user: &Uuid) -> bool {
+    let state = LEFT_PEERS.read().await;
+    let left = state.contains(user);
+
+    drop(state);
+    return left;
+}
+
+// The ratchet is bound to the connection of the user, so it is useless from now on
+pub async fn mark_left(user: &Uuid) {
+    LEFT_PEERS.write().await.insert(user.clone());
+    CHAT_RATCHETS.write().await.remove(user);
+}
+
+// The ratchet of an offline user is gone with its connection, so the message is sealed to its identity key
+pub async fn send_offline(receiver: &Uuid, data: &[u8]) -> anyhow::Result<()> {
+    let state = PEER_KEYS.read().await;
+    let pubkey = state.get(receiver).cloned();
+
+    drop(state);
+    if pubkey.is_none() {
+        return Err(anyhow!("The receiver is offline and its key is unknown, the message can not be queued."));
+    }
+
+    let keypair = get_curr_keypair().await?;
+    let data = compress_msg(data)?;
+    let sealed = seal_to_identity(&data, &keypair, &pubkey.unwrap())?;
+
+    let to_send = ToOfflineMsg {
+        msg: sealed,
+        receiver: receiver.clone(),
+    }
+    .serialize();
+
+    send_msg(Message::Binary(to_send)).await?;
+    return Ok(());
+}
+
+// Plaintext and the name the key of the sender has been pinned with, its fingerprint if it is not pinned
+pub async fn open_offline(sender_key: &Rsa<Public>, data: &[u8]) -> anyhow::Result<(Vec<u8>, String)> {
+    let keypair = get_curr_keypair().await?;
+    let content = open_from_identity(data, &keypair, sender_key)?;
+
+    if content.is_expired(get_unix_secs()) {
+        return Err(anyhow!("Message is too old or its time is in the future."));
+    }
+
+    if !mark_seen(&content).await? {
+        return Err(anyhow!("Message has been received before, the server sent it again."));
+    }
+
+    let fingerprint = get_fingerprint(sender_key)?;
+    let name = get_known_name(&fingerprint).await?;
+    let name = name.unwrap_or_else(|| format_fingerprint(&fingerprint).yellow().to_string());
+
+    return Ok((decompress_msg(&content.data)?, name));
+}
+
+// Kept next to the identity, the ids only matter for messages sealed to that key
+async fn get_seen_path() -> anyhow::Result<PathBuf> {
+    let path = get_identity_path().await?;
+    return Ok(path.with_extension(SEEN_MESSAGES_EXT));
+}
+
+// Every line is "<message id> <sent at>"
+async fn load_seen() -> anyhow::Result<HashMap<Uuid, u64>> {
+    let path = get_seen_path().await?;
+    let mut seen = HashMap::new();
+
+    if !path.is_file() {
+        return Ok(seen);
+    }
+
+    let content = read_to_string(&path).await?;
+    for line in content.lines() {
+        let split = line.trim().split_once(" ");
+        if split.is_none() {
+            continue;
+        }
+
+        let (id, sent_at) = split.unwrap();
+        let id = Uuid::from_str(id);
+        let sent_at = sent_at.parse::<u64>();
+        if id.is_err() || sent_at.is_err() {
+            trace!("Invalid line in seen messages: {}", line);
+            continue;
+        }
+
+        seen.insert(id.unwrap(), sent_at.unwrap());
+    }
+
+    return Ok(seen);
+}
+
+// False if the message has been seen already. Expired ids are dropped, those messages are rejected by their age.
+async fn mark_seen(content: &SealedContent) -> anyhow::Result<bool> {
+    let mut state = SEEN_MESSAGES.write().await;
+    if state.is_none() {
+        *state = Some(load_seen().await?);
+    }
+
+    let seen = state.as_mut().unwrap();
+    if seen.contains_key(&content.id) {
+        drop(state);
+        return Ok(false);
+    }
+
+    let now = get_unix_secs();
+    seen.retain(|_, sent_at| !is_sealed_expired(*sent_at, now));
+    seen.insert(content.id, content.sent_at);
+
+    let lines: Vec<String> = seen.iter().map(|(id, sent_at)| format!("{} {}", id, sent_at)).collect();
+    let res = write(get_seen_path().await?, lines.join("\n") + "\n").await;
+
+    drop(state);
+    res?;
+
+    return Ok(true);
+}
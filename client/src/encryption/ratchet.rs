@@ -0,0 +1,96 @@
+use anyhow::anyhow;
+use log::trace;
+use packets::{communication::to::ToMsg, encryption::aead::get_chat_aad, types::ByteMessage, util::compression::{compress_msg, decompress_msg}};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::util::{arcs::get_curr_id, consts::CHAT_RATCHETS, msg::send_msg};
+
+pub async fn has_ratchet(user: &Uuid) -> bool {
+    let state = CHAT_RATCHETS.read().await;
+    let exists = state.contains_key(user);
+
+    drop(state);
+    return exists;
+}
+
+pub async fn encrypt_chat(receiver: &Uuid, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let curr_id = get_curr_id().await?;
+    let aad = get_chat_aad(&curr_id, receiver);
+    // Compressed before encrypting, the ciphertext would not compress anymore
+    let data = compress_msg(data)?;
+
+    let mut state = CHAT_RATCHETS.write().await;
+    let ratchet = state.get_mut(receiver);
+    if ratchet.is_none() {
+        return Err(anyhow!("Key exchange with {} has not finished yet.", receiver));
+    }
+
+    let encrypted = ratchet.unwrap().encrypt(&data, &aad);
+    drop(state);
+
+    return encrypted;
+}
+
+pub async fn decrypt_chat(sender: &Uuid, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let curr_id = get_curr_id().await?;
+    let aad = get_chat_aad(sender, &curr_id);
+
+    let mut state = CHAT_RATCHETS.write().await;
+    let ratchet = state.get_mut(sender);
+    if ratchet.is_none() {
+        return Err(anyhow!("No key exchange with {} has happened.", sender));
+    }
+
+    let ratchet = ratchet.unwrap();
+    let decrypted = ratchet.decrypt(data, &aad);
+    let needs_heartbeat = ratchet.needs_heartbeat();
+
+    drop(state);
+    if decrypted.is_ok() && needs_heartbeat {
+        send_heartbeat(sender).await?;
+    }
+
+    return decompress_msg(&decrypted?);
+}
+
+// Empty message, only carries our current ratchet key so the peer can do a DH step
+pub async fn send_heartbeat(receiver: &Uuid) -> anyhow::Result<()> {
+    trace!("Sending ratchet heartbeat to {}", receiver);
+    let encrypted = encrypt_chat(receiver, &Vec::new()).await?;
+
+    let to_send = ToMsg {
+        msg: encrypted,
+        receiver: receiver.clone(),
+    }
+    .serialize();
+
+    send_msg(Message::Binary(to_send)).await?;
+    return Ok(());
+}
+
+pub async fn authenticate_control(receiver: &Uuid, data: &[u8]) -> anyhow::Result<(u64, Vec<u8>)> {
+    let mut state = CHAT_RATCHETS.write().await;
+    let ratchet = state.get_mut(receiver);
+    if ratchet.is_none() {
+        return Err(anyhow!("Key exchange with {} has not finished yet.", receiver));
+    }
+
+    let res = ratchet.unwrap().authenticate(data);
+    drop(state);
+
+    return res;
+}
+
+pub async fn verify_control(sender: &Uuid, seq: u64, mac: &[u8], data: &[u8]) -> anyhow::Result<()> {
+    let mut state = CHAT_RATCHETS.write().await;
+    let ratchet = state.get_mut(sender);
+    if ratchet.is_none() {
+        return Err(anyhow!("No key exchange with {} has happened.", sender));
+    }
+
+    let res = ratchet.unwrap().verify(seq, mac, data);
+    drop(state);
+
+    return res;
+}
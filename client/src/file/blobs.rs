@@ -0,0 +1,77 @@
+use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::{
+    encryption::sign::get_signature,
+    file::chunk::index::{ChunkHeader, ChunkLink},
+    other::key_iv::KeyIVPair,
+    util::compression::Compression,
+};
+use uuid::Uuid;
+
+use crate::{file::tools::ChunkUpload, util::{arcs::{get_base_url, get_curr_keypair}, consts::UPLOADED_BLOBS}, web::prefix::get_web_protocol};
+
+// A chunk this client uploaded before. The server stores its encrypted data once,
+// so the same chunk can be sent to other receivers by wrapping the key for them.
+#[derive(Debug, Clone)]
+pub struct UploadedBlob {
+    pub key: KeyIVPair,
+    pub compression: Compression,
+    pub data_size: u64,
+    pub encrypted_size: u64,
+    pub blob_hash: Vec<u8>
+}
+
+pub async fn add_uploaded_blob(chunk_hash: &Vec<u8>, file_compression: Compression, blob: UploadedBlob) {
+    let mut state = UPLOADED_BLOBS.write().await;
+    state.insert((chunk_hash.clone(), file_compression), blob);
+
+    drop(state);
+}
+
+// Links the chunk to a blob uploaded before, None if the chunk has to be uploaded
+pub async fn try_link_chunk(uuid: &Uuid, chunk_index: u64, chunk_hash: &Vec<u8>, file_compression: Compression, receiver: &Uuid, receiver_key: &Rsa<Public>) -> anyhow::Result<Option<ChunkUpload>> {
+    let state = UPLOADED_BLOBS.read().await;
+    let blob = state.get(&(chunk_hash.clone(), file_compression)).cloned();
+
+    drop(state);
+    if blob.is_none() {
+        return Ok(None);
+    }
+
+    let blob = blob.unwrap();
+    let header = ChunkHeader {
+        uuid: uuid.clone(),
+        chunk_index,
+        compression: blob.compression,
+        key: blob.key.clone(),
+        encrypted_size: blob.encrypted_size
+    }.serialize(receiver_key)?;
+
+    let mut link = ChunkLink { header, blob_hash: blob.blob_hash.clone(), signature: Vec::new() };
+    link.signature = get_signature(&link.get_auth_data(), &get_curr_keypair().await?)?;
+
+    let base_url = get_base_url().await;
+    let http_protocol = get_web_protocol().await;
+
+    let url = format!("{}//{}/file/link?receiver={}", http_protocol, base_url, receiver);
+    trace!("Linking chunk {} to blob {}...", chunk_index, hex::encode(&blob.blob_hash));
+
+    let res = surf::post(url).body(link.serialize()?).await;
+    if res.is_err() {
+        return Err(anyhow::anyhow!(res.unwrap_err()));
+    }
+
+    let status = res.unwrap().status();
+    if status == 410 {
+        return Ok(Some(ChunkUpload::Gone));
+    }
+
+    if status != 200 {
+        // The server removed the blob, it has to be uploaded again
+        trace!("Could not link chunk {} ({}), uploading it", chunk_index, status);
+        UPLOADED_BLOBS.write().await.remove(&(chunk_hash.clone(), file_compression));
+        return Ok(None);
+    }
+
+    return Ok(Some(ChunkUpload::Done(blob.data_size)));
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, fmt::Write, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use anyhow::anyhow;
 use colored::Colorize;
 use futures_util::future::select_all;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use indicatif::ProgressBar;
 use log::{trace, warn};
 use openssl::{pkey::Public, rsa::Rsa};
 use packets::file::{processing::tools::get_max_chunks, types::FileInfo};
@@ -18,7 +18,8 @@ use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 use uuid::Uuid;
 
 use crate::{
-    file::tools::{get_hash_progress, WorkerProgress},
+    file::{tools::{get_hash_progress, WorkerProgress}, journal::remove_journal, manifest::apply_pending_permissions},
+    transfer::{state::{Direction, TransferState}, tools::{get_compression_suffix, print_transfer, register_transfer, set_transfer_state}},
     util::tools::get_avg,
 };
 
@@ -94,7 +95,7 @@ impl Downloader {
         let file_lock = self.file_lock.clone();
         let worker_tx = self.worker_tx.clone();
 
-        let to_spawn = get_max_chunks(info.size).min(max_chunks);
+        let to_spawn = get_max_chunks(info.size, info.chunk_size).min(max_chunks);
         self.threads = Some(to_spawn);
 
         if info.path.is_none() {
@@ -130,6 +131,16 @@ impl Downloader {
         Ok(())
     }
 
+    // Chunks of a resumed transfer which are already written to the file
+    pub async fn mark_completed(&self, chunks: &Vec<u64>) {
+        let mut state = self.progress.write().await;
+        for chunk in chunks {
+            state.insert(chunk.clone(), 1 as f32);
+        }
+
+        drop(state);
+    }
+
     pub async fn start_downloading(&self, chunk: u64) -> anyhow::Result<()> {
         let prog = self.progress.read().await;
         if prog.contains_key(&chunk) {
@@ -190,15 +201,14 @@ impl Downloader {
         let prog_arc = self.progress.clone();
         let file_arc = Arc::new(RwLock::new(self.info.clone()));
         let max_size = self.info.size;
+        let chunk_size = self.info.chunk_size;
         let worker_rx_arc = self.worker_rx.clone();
+        let uuid = self.uuid.clone();
+
+        let info = self.info.clone();
 
         let e = tokio::spawn(async move {
-            let pb = ProgressBar::new(max_size);
-            pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})")
-            .unwrap()
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("#>-"));
-            pb.enable_steady_tick(Duration::from_millis(250));
+            let pb = register_transfer(&uuid, Direction::Download, &info).await;
 
             let mut worker_rx = worker_rx_arc.write().await;
             let mut downloader_done = false;
@@ -211,27 +221,24 @@ impl Downloader {
                 state.insert(chunk, progress);
                 if progress >= 1.0 && old_prog < 1.0 as f32 {
                     trace!("Downloader worker {} finished.", chunk);
-                    let e = Downloader::on_worker_done(&state, &file_arc, &pb).await;
+                    let e = Downloader::on_worker_done(&uuid, &state, &file_arc, &pb).await;
                     if e.is_err() {
                         let err = e.unwrap_err();
-                        println!(
-                            "{}",
-                            format!("Could not send on worker_done update: {}", err).red()
-                        );
+                        print_transfer(format!("Could not send on worker_done update: {}", err).red().to_string());
                     } else {
                         downloader_done = e.unwrap();
                     }
                 }
 
                 if !downloader_done {
-                    Downloader::print_update(&pb, &state, max_size);
+                    Downloader::print_update(&pb, &state, max_size, chunk_size);
                 }
                 drop(state);
             }
 
             if !downloader_done {
-                pb.println(format!("Listening for updates stopped and worker is not done. Proably aborted."));
-                pb.finish();
+                trace!("Listening for updates of {} stopped and downloader is not done. Probably aborted.", uuid);
+                set_transfer_state(&uuid, TransferState::Cancelled).await;
             }
             drop(worker_rx);
         });
@@ -239,9 +246,9 @@ impl Downloader {
         return Ok(e);
     }
 
-    fn print_update(pb: &ProgressBar, progress: &ProgressMap, max_size: u64) {
+    fn print_update(pb: &ProgressBar, progress: &ProgressMap, max_size: u64, chunk_size: u64) {
         let mut percentages: Vec<f32> = progress.iter().map(|e| e.1.clone()).collect();
-        let max_chunks = get_max_chunks(max_size);
+        let max_chunks = get_max_chunks(max_size, chunk_size);
 
         let left = max_chunks - progress.len() as u64;
         for _ in 0..left {
@@ -268,7 +275,7 @@ impl Downloader {
         return done.len();
     }
 
-    async fn get_hash_progress(file: &FileInfo) -> anyhow::Result<Vec<u8>> {
+    async fn get_hash_progress(file: &FileInfo) -> anyhow::Result<(Vec<u8>, Vec<Vec<u8>>)> {
         let path = file.path.clone();
         if path.is_none() {
             return Err(anyhow!(
@@ -279,18 +286,19 @@ impl Downloader {
         let path = path.unwrap();
         let path = path.to_str().unwrap();
 
-        let hash = get_hash_progress(path.to_owned()).await?;
+        let hashes = get_hash_progress(path.to_owned(), file.chunk_size).await?;
 
-        return Ok(hash);
+        return Ok(hashes);
     }
 
     async fn on_worker_done(
+        uuid: &Uuid,
         map: &ProgressMap,
         file_arc: &Arc<RwLock<FileInfo>>,
         pb: &ProgressBar,
     ) -> anyhow::Result<bool> {
         let s = file_arc.read().await;
-        let max_chunks = get_max_chunks(s.size) as usize;
+        let max_chunks = get_max_chunks(s.size, s.chunk_size) as usize;
         let curr_completed = Downloader::get_chunks_completed(map).await;
 
         if curr_completed < max_chunks {
@@ -300,51 +308,88 @@ impl Downloader {
 
         pb.disable_steady_tick();
         pb.finish_and_clear();
-        println!(
-            "{}",
-            format!("Calculating hash for downloaded file...").yellow()
-        );
-        let curr_hash = Downloader::get_hash_progress(&s).await?;
+        remove_journal(uuid).await?;
+
+        print_transfer(format!("Calculating hash for downloaded file...").yellow().to_string());
+        let (curr_hash, curr_chunk_hashes) = Downloader::get_hash_progress(&s).await?;
 
         let expected = (&s.hash).clone();
 
         let is_valid = curr_hash == expected;
         if is_valid {
-            println!("{}",
-                    format!("Hashes {} and {} match.", hex::encode(expected), hex::encode(curr_hash))
-                    .green()
-            );
-            println!(
-                "{}",
-                format!(
-                    "File '{}' has been downloaded successfully.",
-                    s.filename.yellow()
-                )
-                .green()
-            );
+            print_transfer(format!("Hashes {} and {} match.", hex::encode(expected), hex::encode(curr_hash)).green().to_string());
+            if s.path.is_some() {
+                let res = apply_pending_permissions(uuid, s.path.as_ref().unwrap()).await;
+                if res.is_err() {
+                    print_transfer(format!("Could not set permissions of file: {}", res.unwrap_err()));
+                }
+            }
+
+            set_transfer_state(uuid, TransferState::Finished).await;
+            let compression = get_compression_suffix(uuid).await;
+            print_transfer(format!("File '{}' has been downloaded successfully{}.", s.filename.yellow(), compression).green().to_string());
         } else {
-            println!(
-                "{}",
+            set_transfer_state(uuid, TransferState::Failed).await;
+            print_transfer(
                 format!(
                     "Could not download file as hashes did not match (expected {} got {})",
                     hex::encode(expected),
                     hex::encode(curr_hash)
                 )
                 .red()
-            )
+                .to_string()
+            );
+
+            let bad_chunks: Vec<String> = s.chunk_hashes.iter()
+                .enumerate()
+                .filter(|(i, e)| curr_chunk_hashes.get(*i) != Some(e))
+                .map(|(i, _)| i.to_string())
+                .collect();
+
+            if !bad_chunks.is_empty() {
+                print_transfer(format!("Corrupt chunks: {}", bad_chunks.join(", ")).red().to_string());
+            }
+
+            let quarantined = Downloader::quarantine(&s).await;
+            if quarantined.is_err() {
+                let err = quarantined.unwrap_err();
+                print_transfer(format!("Could not quarantine file: {}", err));
+            } else {
+                let path = quarantined.unwrap();
+                print_transfer(format!("The file has been moved to '{}'.", path.display()).yellow().to_string());
+            }
         }
 
         drop(s);
         return Ok(true);
     }
 
+    // Moves a corrupt file out of the way, so it is not mistaken for the real one
+    async fn quarantine(file: &FileInfo) -> anyhow::Result<PathBuf> {
+        let path = file.path.clone();
+        if path.is_none() {
+            return Err(anyhow!("Local path of file is none."));
+        }
+
+        let path = path.unwrap();
+        let mut quarantined = path.clone().into_os_string();
+        quarantined.push(".quarantine");
+
+        let quarantined = PathBuf::from(quarantined);
+        tokio::fs::rename(&path, &quarantined).await?;
+
+        return Ok(quarantined);
+    }
+
     pub async fn abort(&self) {
         self.worker_rx.write().await.close();
         let mut s = self.aborted.write().await;
         *s = true;
 
         drop(s);
+        set_transfer_state(&self.uuid, TransferState::Cancelled).await;
+
         let name = self.info.filename.clone();
-        println!("{}", format!("Download of file '{}' has been stopped as a error either on sender or receiver side ocurred.", name.yellow()).red());
+        print_transfer(format!("Download of file '{}' has been stopped as a error either on sender or receiver side ocurred.", name.yellow()).red().to_string());
     }
 }
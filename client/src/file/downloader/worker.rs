@@ -8,16 +8,17 @@ use anyhow::anyhow;
 use colored::Colorize;
 use indicatif::HumanBytes;
 use log::{debug, trace, warn};
-use openssl::{pkey::Public, rsa::Rsa};
+use openssl::{hash::Hasher, pkey::{PKey, Public}, rsa::Rsa};
 use packets::{
-    consts::CHUNK_SIZE_I64,
+    consts::{MSG_DIGEST, STREAM_PIECE_SIZE, U64_SIZE, UUID_SIZE},
     encryption::sign::get_signature,
     file::{
-        chunk::index::ChunkMsg,
-        processing::{downloaded::ChunkDownloadedMsg, tools::get_max_chunks},
+        chunk::{index::{ChunkDecryptor, ChunkHeader, is_valid_encrypted_size}, compression::ChunkDecompressor},
+        processing::{downloaded::ChunkDownloadedMsg, tools::{get_max_chunks, get_chunk_size}},
         types::FileInfo,
     },
     types::ByteMessage,
+    util::{compression::Compression, tools::vec_to_usize},
 };
 use tokio::{
     fs::OpenOptions,
@@ -31,9 +32,10 @@ use uuid::Uuid;
 use crate::{
     util::{
         arcs::{get_base_url, get_curr_keypair},
-        msg::send_msg, consts::MAX_RETRIES
+        msg::send_msg, consts::{MAX_RETRIES, DOWNLOAD_LIMIT}
     },
-    web::{prefix::get_web_protocol, progress::download_file}, file::tools::WorkerProgress,
+    web::{prefix::get_web_protocol, progress::ChunkDownload, throttle::get_piece_size}, file::{tools::WorkerProgress, journal::update_journal},
+    transfer::tools::{acquire_worker, add_transfer_bytes, print_transfer},
 };
 
 pub type ProgressTX = UnboundedSender<WorkerProgress>;
@@ -127,12 +129,15 @@ impl DownloadWorker {
 
         let out_path = file.path.clone().unwrap();
         let size = file.size;
+        let chunk_size = file.chunk_size;
+        let file_compression = file.compression;
+        let expected_hash = file.chunk_hashes.get(i as usize).cloned();
         let sender_key = self.sender_key.clone();
         let file_lock_arc = self.file_lock.clone();
 
         let handle: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
             let to_run = || async {
-                let max_threads = get_max_chunks(size);
+                let max_threads = get_max_chunks(size, chunk_size);
 
                 if max_threads <= 0 {
                     warn!("Max Threads is 0 in index {}", i);
@@ -145,7 +150,6 @@ impl DownloadWorker {
                     max_threads
                 );
 
-                let file_lock = file_lock_arc.lock().await;
                 let keypair = get_curr_keypair().await?;
 
                 let uuid_signature = get_signature(&uuid.as_bytes().to_vec(), &keypair)?;
@@ -163,24 +167,33 @@ impl DownloadWorker {
                 );
 
                 let tx_state = tx.read().await;
-                let response = download_file(url, &tx_state, i).await;
+                let progress_tx = tx_state.clone();
                 drop(tx_state);
 
-                let response = response?;
-                // Signature is validated in deserialize, so its fine
-                let deserialized = ChunkMsg::deserialize(&response, &sender_key, &keypair);
-                if deserialized.is_err() {
-                    let e = deserialized.unwrap_err();
-                    eprintln!("Deserialize err: {:?}", e);
-                    return Err(e);
+                let mut download = ChunkDownload::new(url, progress_tx, i).await?;
+
+                // uuid | chunk index | compression | key size | key | iv size | iv | encrypted size
+                let mut b_header = download.read_vec(UUID_SIZE + U64_SIZE + 1).await?;
+                for _ in 0..2 {
+                    let mut b_size = download.read_vec(U64_SIZE).await?;
+                    let field_size = vec_to_usize(&mut b_size.clone())?;
+
+                    b_header.append(&mut b_size);
+                    b_header.append(&mut download.read_vec(field_size).await?);
                 }
-                let deserialized = deserialized.unwrap();
-                let encrypted = &deserialized.encrypted;
 
-                let decrypted = deserialized.key.decrypt(encrypted)?;
+                b_header.append(&mut download.read_vec(U64_SIZE).await?);
+                let header = ChunkHeader::deserialize(&b_header, &keypair)?;
+
+                let data_size = get_chunk_size(i, size, chunk_size)?;
+                let valid_compression = header.compression == Compression::None || header.compression == file_compression;
+                if header.uuid != uuid || header.chunk_index != i || !valid_compression || !is_valid_encrypted_size(header.encrypted_size, data_size, header.compression) {
+                    return Err(anyhow!("Chunk {} of file {} has an invalid header.", i, uuid));
+                }
 
-                let offset = CHUNK_SIZE_I64 * i as i64;
+                let offset = i64::try_from(chunk_size * i)?;
 
+                let file_lock = file_lock_arc.lock().await;
                 let path = Path::new(&out_path);
                 let mut f = OpenOptions::new()
                     .write(true)
@@ -188,10 +201,62 @@ impl DownloadWorker {
                     .open(&path)
                     .await?;
 
+                drop(file_lock);
                 f.seek(SeekFrom::Current(offset)).await?;
+
+                // Pieces are written before the signature is checked at the end,
+                // an invalid chunk is an error so the retry loop downloads and overwrites it again
+                let sender_pkey = PKey::from_rsa(sender_key.clone())?;
+                let mut decryptor = ChunkDecryptor::new(&header.key, &sender_pkey)?;
+                let mut decompressor = ChunkDecompressor::new(header.compression, data_size)?;
+                let mut hasher = Hasher::new(*MSG_DIGEST)?;
+
+                let piece_size = get_piece_size(&DOWNLOAD_LIMIT, STREAM_PIECE_SIZE).await as u64;
+                let mut left = header.encrypted_size;
+                // Size of the data before decompressing, for the compression ratio
+                let mut compressed = 0 as u64;
+                while left > 0 {
+                    let to_read = piece_size.min(left);
+                    let piece = download.read_vec(to_read as usize).await?;
+                    left -= to_read;
+
+                    let decrypted = decryptor.update(&piece)?;
+                    compressed += decrypted.len() as u64;
+
+                    let decrypted = decompressor.update(&decrypted)?;
+                    hasher.update(&decrypted)?;
+                    f.write_all(&decrypted).await?;
+                }
+
+                let mut b_signature_size = download.read_vec(U64_SIZE).await?;
+                let signature_size = vec_to_usize(&mut b_signature_size)?;
+                let signature = download.read_vec(signature_size).await?;
+
+                let decrypted = decryptor.finalize(&signature)?;
+                compressed += decrypted.len() as u64;
+
+                let decrypted = decompressor.update(&decrypted)?;
+                hasher.update(&decrypted)?;
                 f.write_all(&decrypted).await?;
+                decompressor.finalize()?;
+                download.finish()?;
+
+                // A corrupt chunk is an error, so it is downloaded again by the retry loop
+                let chunk_hash = hasher.finish()?.to_vec();
+                if expected_hash.is_none() || expected_hash.as_ref().unwrap() != &chunk_hash {
+                    warn!("Chunk {} of file {} does not match its hash", i, uuid);
+                    return Err(anyhow!("Chunk {} does not match its hash", i));
+                }
 
-                drop(file_lock);
+                // Chunk has to be on disk before it is journaled as downloaded
+                f.sync_data().await?;
+
+                let res = update_journal(&uuid, |j| j.receivers.iter_mut().for_each(|e| { e.downloaded.insert(i); })).await;
+                if res.is_err() {
+                    trace!("Could not journal downloaded chunk {}: {}", i, res.unwrap_err());
+                }
+
+                add_transfer_bytes(&uuid, data_size, compressed).await;
 
                 send_msg(Message::Binary(
                     ChunkDownloadedMsg {
@@ -205,6 +270,9 @@ impl DownloadWorker {
                 Ok(())
             };
 
+            // Waits until the global worker budget allows another download
+            let permit = acquire_worker(&uuid).await?;
+
             let mut retry_count = 0 as u64;
             let mut res;
             loop {
@@ -214,8 +282,9 @@ impl DownloadWorker {
                 }
 
                 retry_count += 1;
-                eprintln!("{}", format!("An error occurred while running a worker. Retrying ({} / {}).", retry_count, MAX_RETRIES).on_yellow());
+                print_transfer(format!("An error occurred while running a worker. Retrying ({} / {}).", retry_count, MAX_RETRIES).on_yellow().to_string());
             }
+            drop(permit);
             drop(tx);
 
             if res.is_err() {
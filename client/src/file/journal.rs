@@ -0,0 +1,138 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::anyhow;
+use log::trace;
+use packets::{encryption::fingerprint::get_fingerprint, file::{journal::{JournalReceiver, TransferJournal}, types::FileInfo}, initialize::pubkey::PubkeyMsg};
+use tokio::fs::{create_dir_all, read, read_dir, remove_file, write};
+use uuid::Uuid;
+
+use crate::{encryption::keystore::get_config_dir, util::{arcs::get_curr_keypair, consts::TRANSFER_JOURNALS}};
+
+pub const JOURNAL_DIR: &str = "transfers";
+
+fn get_journal_dir() -> anyhow::Result<PathBuf> {
+    let mut path = get_config_dir()?;
+    path.push(JOURNAL_DIR);
+
+    return Ok(path);
+}
+
+fn get_journal_file(uuid: &Uuid) -> anyhow::Result<PathBuf> {
+    let mut path = get_journal_dir()?;
+    path.push(format!("{}.bin", uuid.to_string()));
+
+    return Ok(path);
+}
+
+async fn load_journals() -> anyhow::Result<HashMap<Uuid, TransferJournal>> {
+    let dir = get_journal_dir()?;
+    let mut journals = HashMap::new();
+
+    if !dir.is_dir() {
+        return Ok(journals);
+    }
+
+    let mut files = read_dir(&dir).await?;
+    while let Some(file) = files.next_entry().await? {
+        let journal = TransferJournal::deserialize(&read(file.path()).await?);
+        if journal.is_err() {
+            trace!("Invalid transfer journal {:?}: {}", file.path(), journal.unwrap_err());
+            continue;
+        }
+
+        let journal = journal.unwrap();
+        journals.insert(journal.uuid, journal);
+    }
+
+    return Ok(journals);
+}
+
+async fn save_journal(journal: &TransferJournal) -> anyhow::Result<()> {
+    let dir = get_journal_dir()?;
+    create_dir_all(&dir).await?;
+
+    write(get_journal_file(&journal.uuid)?, journal.serialize()).await?;
+    return Ok(());
+}
+
+pub async fn get_own_fingerprint() -> anyhow::Result<Vec<u8>> {
+    let keypair = get_curr_keypair().await?;
+    let pubkey = PubkeyMsg::from_private(keypair)?.pubkey;
+
+    return get_fingerprint(&pubkey);
+}
+
+pub async fn get_journals() -> anyhow::Result<Vec<TransferJournal>> {
+    let mut state = TRANSFER_JOURNALS.write().await;
+    if state.is_none() {
+        *state = Some(load_journals().await?);
+    }
+
+    let journals = state.as_ref().unwrap().values().cloned().collect();
+    drop(state);
+
+    return Ok(journals);
+}
+
+pub async fn get_journal(uuid: &Uuid) -> anyhow::Result<Option<TransferJournal>> {
+    let journals = get_journals().await?;
+    return Ok(journals.into_iter().find(|e| e.uuid == *uuid));
+}
+
+pub async fn create_journal(uuid: &Uuid, info: &FileInfo, sender: Vec<u8>, receivers: Vec<JournalReceiver>) -> anyhow::Result<()> {
+    let journal = TransferJournal::new(uuid, info, sender, receivers);
+
+    let mut state = TRANSFER_JOURNALS.write().await;
+    if state.is_none() {
+        *state = Some(load_journals().await?);
+    }
+
+    let res = save_journal(&journal).await;
+    state.as_mut().unwrap().insert(uuid.clone(), journal);
+
+    drop(state);
+    return res;
+}
+
+// Applies `f` to the journal of the transfer, persists and returns it
+pub async fn update_journal<F>(uuid: &Uuid, f: F) -> anyhow::Result<TransferJournal>
+where
+    F: FnOnce(&mut TransferJournal),
+{
+    let mut state = TRANSFER_JOURNALS.write().await;
+    if state.is_none() {
+        *state = Some(load_journals().await?);
+    }
+
+    let journal = state.as_mut().unwrap().get_mut(uuid);
+    if journal.is_none() {
+        drop(state);
+        return Err(anyhow!("No journal for transfer {}.", uuid));
+    }
+
+    let journal = journal.unwrap();
+    f(journal);
+
+    let journal = journal.clone();
+    let res = save_journal(&journal).await;
+
+    drop(state);
+    res?;
+
+    return Ok(journal);
+}
+
+pub async fn remove_journal(uuid: &Uuid) -> anyhow::Result<()> {
+    let mut state = TRANSFER_JOURNALS.write().await;
+    if state.is_some() {
+        state.as_mut().unwrap().remove(uuid);
+    }
+
+    let path = get_journal_file(uuid)?;
+    if path.is_file() {
+        remove_file(path).await?;
+    }
+
+    drop(state);
+    return Ok(());
+}
@@ -0,0 +1,154 @@
+use std::{fs::Metadata, path::{Path, PathBuf}};
+
+use anyhow::anyhow;
+use log::{trace, warn};
+use packets::file::manifest::index::ManifestEntry;
+use uuid::Uuid;
+
+use crate::util::consts::FILE_PERMISSIONS;
+
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+// Resolves the arguments of `/send` to existing paths, globs are expanded
+pub fn resolve_send_paths(args: &str) -> anyhow::Result<Vec<PathBuf>> {
+    // Paths containing spaces are given as is
+    let whole = PathBuf::from(args);
+    if whole.exists() {
+        return Ok(vec![whole]);
+    }
+
+    let mut paths = Vec::new();
+    for arg in args.split_whitespace() {
+        if !arg.contains(&GLOB_CHARS[..]) {
+            let path = PathBuf::from(arg);
+            if !path.exists() {
+                return Err(anyhow!("File '{}' does not exist.", arg));
+            }
+
+            paths.push(path);
+            continue;
+        }
+
+        let matches: Vec<PathBuf> = glob::glob(arg)?.filter_map(|e| e.ok()).collect();
+        if matches.is_empty() {
+            return Err(anyhow!("No files match '{}'.", arg));
+        }
+
+        paths.extend(matches);
+    }
+
+    if paths.is_empty() {
+        return Err(anyhow!("No files given."));
+    }
+
+    return Ok(paths);
+}
+
+// Local path and relative path (with `/` as separator) of every file to send
+pub fn collect_files(paths: &Vec<PathBuf>) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let name = path.file_name().and_then(|e| e.to_str());
+        if name.is_none() {
+            return Err(anyhow!("Could not get filename of path {:?}", path.as_os_str()));
+        }
+
+        collect_path(path, name.unwrap().to_string(), &mut files)?;
+    }
+
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    let duplicate = files.windows(2).find(|e| e[0].1 == e[1].1);
+    if duplicate.is_some() {
+        return Err(anyhow!("'{}' would be sent twice.", duplicate.unwrap()[0].1));
+    }
+
+    return Ok(files);
+}
+
+fn collect_path(path: &Path, relative: String, files: &mut Vec<(PathBuf, String)>) -> anyhow::Result<()> {
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_symlink() {
+        warn!("Skipping symlink {:?}", path);
+        return Ok(());
+    }
+
+    if meta.is_file() {
+        files.push((path.to_path_buf(), relative));
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_str();
+        if name.is_none() {
+            return Err(anyhow!("Filename of {:?} is not valid utf8.", entry.path()));
+        }
+
+        collect_path(&entry.path(), format!("{}/{}", relative, name.unwrap()), files)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(unix)]
+pub fn get_permissions(meta: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    return meta.permissions().mode() & 0o777;
+}
+
+#[cfg(not(unix))]
+pub fn get_permissions(meta: &Metadata) -> u32 {
+    return if meta.permissions().readonly() { 0o444 } else { 0o644 };
+}
+
+// Where an entry of a manifest is stored, rejects anything leaving `base`
+pub fn get_entry_path(base: &Path, entry: &ManifestEntry) -> anyhow::Result<PathBuf> {
+    if !entry.is_valid_path() {
+        return Err(anyhow!("Invalid path '{}' in manifest.", entry.path));
+    }
+
+    let mut path = base.to_path_buf();
+    for part in entry.path.split('/') {
+        path.push(part);
+    }
+
+    return Ok(path);
+}
+
+// Permissions are set when the download is done, read-only files could not be written to otherwise
+pub async fn set_pending_permissions(uuid: &Uuid, permissions: u32) {
+    let mut state = FILE_PERMISSIONS.write().await;
+    state.insert(uuid.clone(), permissions);
+
+    drop(state);
+}
+
+pub async fn apply_pending_permissions(uuid: &Uuid, path: &Path) -> anyhow::Result<()> {
+    let mut state = FILE_PERMISSIONS.write().await;
+    let permissions = state.remove(uuid);
+
+    drop(state);
+    if permissions.is_none() {
+        return Ok(());
+    }
+
+    // Only the rwx bits, a peer must not be able to set setuid / setgid / sticky bits
+    let permissions = permissions.unwrap() & 0o777;
+    trace!("Setting permissions of {:?} to {:o}", path, permissions);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(permissions)).await?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        let mut perms = tokio::fs::metadata(path).await?.permissions();
+        perms.set_readonly(permissions & 0o200 == 0);
+        tokio::fs::set_permissions(path, perms).await?;
+    }
+
+    return Ok(());
+}
@@ -1,3 +1,6 @@
 pub mod uploader;
 pub mod downloader;
-pub mod tools;
\ No newline at end of file
+pub mod tools;
+pub mod journal;
+pub mod manifest;
+pub mod blobs;
\ No newline at end of file
@@ -1,13 +1,16 @@
-use std::{fmt::Write, time::Duration};
+use std::{fmt::Write, path::Path, time::Duration};
 
 use anyhow::anyhow;
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use openssl::hash::Hasher;
-use packets::{consts::{MSG_DIGEST, ONE_MB_SIZE}, file::types::FileInfo};
+use packets::{consts::{MSG_DIGEST, ONE_MB_SIZE}, file::types::FileInfo, util::compression::{is_worth_compressing, Compression}};
 use tokio::{fs::File, io::AsyncReadExt};
 use uuid::Uuid;
 
-use crate::util::consts::PENDING_FILES;
+use crate::util::{consts::{MULTI_PROGRESS, PENDING_FILES}, arcs::get_compression};
+
+// Bytes of a file that are compressed to guess whether compressing the whole file is worth it
+const COMPRESSION_SAMPLE_SIZE: usize = 256 * 1000;
 
 pub async fn get_pending_file(uuid: Uuid) -> anyhow::Result<FileInfo> {
     let state = PENDING_FILES.read().await;
@@ -19,13 +22,17 @@ pub async fn get_pending_file(uuid: Uuid) -> anyhow::Result<FileInfo> {
     return Ok(temp.unwrap().to_owned());
 }
 
-pub async fn get_hash_progress(file_path: String) -> anyhow::Result<Vec<u8>> {
+// Returns the hash of the whole file and the hash of every chunk
+pub async fn get_hash_progress(file_path: String, chunk_size: u64) -> anyhow::Result<(Vec<u8>, Vec<Vec<u8>>)> {
     let mut file = File::open(file_path).await?;
     let size = file.metadata().await?.len();
 
     let mut hasher = Hasher::new(*MSG_DIGEST)?;
+    let mut chunk_hasher = Hasher::new(*MSG_DIGEST)?;
+    let mut chunk_hashes = Vec::new();
+    let mut chunk_left = chunk_size as usize;
 
-    let pb = ProgressBar::new(size);
+    let pb = MULTI_PROGRESS.add(ProgressBar::new(size));
     pb.set_style(ProgressStyle::with_template("{spinner:.yellow} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})")
     .unwrap()
     .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
@@ -40,11 +47,48 @@ pub async fn get_hash_progress(file_path: String) -> anyhow::Result<Vec<u8>> {
         pb.inc(e.try_into().unwrap());
         hasher.update(&chunk)?;
         if e == 0 { break; }
+
+        // Reads may cross chunk borders, so split them up
+        let mut data = chunk.as_slice();
+        while !data.is_empty() {
+            let len = data.len().min(chunk_left);
+            chunk_hasher.update(&data[..len])?;
+
+            data = &data[len..];
+            chunk_left -= len;
+            if chunk_left == 0 {
+                // finish resets the hasher
+                chunk_hashes.push(chunk_hasher.finish()?.to_vec());
+                chunk_left = chunk_size as usize;
+            }
+        }
+    }
+
+    if chunk_left != chunk_size as usize {
+        chunk_hashes.push(chunk_hasher.finish()?.to_vec());
     }
 
     pb.disable_steady_tick();
     pb.finish();
-    return Ok(hasher.finish()?.to_vec());
+    return Ok((hasher.finish()?.to_vec(), chunk_hashes));
+}
+
+// Compression offered for the file, none if it is compressed already or does not shrink
+pub async fn get_file_compression(file_path: &Path, filename: &str) -> anyhow::Result<Compression> {
+    let compression = get_compression().await;
+    if compression == Compression::None {
+        return Ok(compression);
+    }
+
+    let file = File::open(file_path).await?;
+    let mut sample = Vec::with_capacity(COMPRESSION_SAMPLE_SIZE);
+    file.take(COMPRESSION_SAMPLE_SIZE as u64).read_to_end(&mut sample).await?;
+
+    if !is_worth_compressing(filename, &sample)? {
+        return Ok(Compression::None);
+    }
+
+    return Ok(compression);
 }
 
 #[derive(Debug, Clone)]
@@ -52,3 +96,13 @@ pub struct WorkerProgress {
     pub chunk: u64,
     pub progress: f32,
 }
+
+// Result of uploading a chunk for one receiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkUpload {
+    // Size of the (compressed) data
+    Done(u64),
+    Failed,
+    // The receiver is no longer part of the transfer
+    Gone
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, fmt::Write, sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::anyhow;
 use colored::Colorize;
 use futures_util::future::select_all;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use indicatif::ProgressBar;
 use log::{trace, warn};
 use openssl::{pkey::Public, rsa::Rsa};
 use packets::file::{processing::tools::get_max_chunks, types::FileInfo};
@@ -19,10 +19,11 @@ use uuid::Uuid;
 
 use crate::{
     file::tools::WorkerProgress,
+    transfer::{state::{Direction, TransferState}, tools::{print_transfer, register_transfer, set_transfer_state}},
     util::tools::get_avg,
 };
 
-use super::worker::UploadWorker;
+use super::worker::{ReceiverKeys, UploadWorker};
 type WorkersType = Arc<RwLock<Vec<UploadWorker>>>;
 type ProgressMap = HashMap<u64, f32>;
 type ProgressType = Arc<RwLock<ProgressMap>>;
@@ -38,7 +39,7 @@ pub struct Uploader {
     workers: WorkersType,
     progress: ProgressType,
 
-    receiver_key: Rsa<Public>,
+    receivers: ReceiverKeys,
 
     update_thread: UpdateThreadType,
 
@@ -48,7 +49,7 @@ pub struct Uploader {
 }
 
 impl Uploader {
-    pub fn new(uuid: &Uuid, receiver_key: Rsa<Public>, file: &FileInfo) -> Self {
+    pub fn new(uuid: &Uuid, receivers: Vec<(Uuid, Rsa<Public>)>, file: &FileInfo) -> Self {
         let (worker_tx, worker_rx) = mpsc::unbounded_channel();
 
         let worker_rx = UnboundedReceiverStream::new(worker_rx);
@@ -58,7 +59,7 @@ impl Uploader {
             threads: None,
             workers: Arc::new(RwLock::new(Vec::new())),
             progress: Arc::new(RwLock::new(HashMap::new())),
-            receiver_key,
+            receivers: Arc::new(RwLock::new(receivers)),
             update_thread: Arc::new(Mutex::new(None)),
             worker_rx: Arc::new(RwLock::new(worker_rx)),
             worker_tx: Arc::new(RwLock::new(worker_tx)),
@@ -88,7 +89,8 @@ impl Uploader {
         *state = Some(handle);
 
         drop(state);
-        let to_spawn = self.get_max_chunks().min(max_threads);
+        let missing = self.get_missing_chunks().await;
+        let to_spawn = (missing.len() as u64).min(max_threads);
 
         self.threads = Some(to_spawn);
         trace!("Spawning {} workers max: {}", to_spawn, max_threads);
@@ -96,16 +98,17 @@ impl Uploader {
         let mut state_prog = self.progress.write().await;
 
         for i in 0..to_spawn {
-            trace!("Spawning upload worker with Thread_Index {}", i);
+            let chunk = missing[i as usize];
+            trace!("Spawning upload worker with Thread_Index {} for chunk {}", i, chunk);
             let mut worker = UploadWorker::new(
                 i,
                 self.uuid,
-                self.receiver_key.clone(),
+                self.receivers.clone(),
                 self.info.clone(),
                 self.worker_tx.clone(),
             )?;
 
-            let e = worker.start(i).await;
+            let e = worker.start(chunk).await;
             if e.is_err() {
                 drop(state);
                 drop(state_prog);
@@ -113,7 +116,7 @@ impl Uploader {
             }
 
             state.push(worker);
-            state_prog.insert(i, 0 as f32);
+            state_prog.insert(chunk, 0 as f32);
         }
         trace!("Dropping state workers...");
         drop(state);
@@ -121,8 +124,8 @@ impl Uploader {
         Ok(())
     }
 
-    fn print_update(pb: &ProgressBar, progress: &ProgressMap, max_size: u64) {
-        let max_chunks = get_max_chunks(max_size);
+    fn print_update(pb: &ProgressBar, progress: &ProgressMap, max_size: u64, chunk_size: u64) {
+        let max_chunks = get_max_chunks(max_size, chunk_size);
         let mut percentages: Vec<f32> = progress.values().map(|e| e.clone()).collect();
 
         let prog_len: u64 = progress.len().try_into().unwrap();
@@ -152,14 +155,12 @@ impl Uploader {
         let worker_rx_arc = self.worker_rx.clone();
 
         let max_size = self.info.size;
+        let chunk_size = self.info.chunk_size;
+        let uuid = self.uuid.clone();
+        let info = self.info.clone();
 
         let e = tokio::spawn(async move {
-            let pb = ProgressBar::new(max_size);
-            pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})")
-            .unwrap()
-            .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("#>-"));
-            pb.enable_steady_tick(Duration::from_millis(250));
+            let pb = register_transfer(&uuid, Direction::Upload, &info).await;
 
             let mut worker_rx = worker_rx_arc.write().await;
             let mut uploader_done = false;
@@ -173,18 +174,22 @@ impl Uploader {
 
                 if progress >= 1.0 && old_prog < 1.0 {
                     trace!("Upload worker {} finished.", chunk);
+                }
+
+                let completed = state.values().filter(|e| **e >= 1.0).count() as u64;
+                if completed >= get_max_chunks(max_size, chunk_size) {
                     pb.disable_steady_tick();
                     pb.finish();
                     uploader_done = true;
                 }
 
-                Uploader::print_update(&pb, &state, max_size);
+                Uploader::print_update(&pb, &state, max_size, chunk_size);
                 drop(state);
             }
 
             if !uploader_done {
-                pb.println(format!("Listening for updates stopped and worker is not done. Proably aborted."));
-                pb.finish();
+                trace!("Listening for updates of {} stopped and uploader is not done. Probably aborted.", uuid);
+                set_transfer_state(&uuid, TransferState::Cancelled).await;
             }
             drop(worker_rx);
         });
@@ -192,6 +197,26 @@ impl Uploader {
         return Ok(e);
     }
 
+    // Chunks of a resumed transfer which don't have to be uploaded again
+    pub async fn mark_completed(&self, chunks: &Vec<u64>) {
+        let mut state = self.progress.write().await;
+        for chunk in chunks {
+            state.insert(chunk.clone(), 1 as f32);
+        }
+
+        drop(state);
+    }
+
+    async fn get_missing_chunks(&self) -> Vec<u64> {
+        let state = self.progress.read().await;
+        let missing = (0..self.get_max_chunks())
+            .filter(|e| !state.contains_key(e))
+            .collect();
+
+        drop(state);
+        return missing;
+    }
+
     pub async fn on_next(&self) -> anyhow::Result<()> {
         let mut chunks_left = None;
         let state = self.progress.read().await;
@@ -257,13 +282,22 @@ impl Uploader {
     }
 
     pub fn get_max_chunks(&self) -> u64 {
-        return get_max_chunks(self.info.size) as u64;
+        return get_max_chunks(self.info.size, self.info.chunk_size) as u64;
     }
 
     pub fn get_file_info(&self) -> FileInfo {
         return self.info.clone();
     }
 
+    // Receivers which are still part of the transfer
+    pub async fn get_receivers(&self) -> Vec<Uuid> {
+        let state = self.receivers.read().await;
+        let receivers = state.iter().map(|e| e.0).collect();
+
+        drop(state);
+        return receivers;
+    }
+
     pub async fn is_done(&self) -> bool {
         let max_chunks = self.get_max_chunks();
         let completed = self.get_chunks_completed().await;
@@ -312,8 +346,10 @@ impl Uploader {
         let mut s = self.aborted.write().await;
         *s = true;
 
-        let name = self.info.filename.clone();
-        println!("{}", format!("Download of file '{}' has been stopped as a error either on sender or receiver side ocurred.", name.yellow()).red());
         drop(s);
+        set_transfer_state(&self.uuid, TransferState::Cancelled).await;
+
+        let name = self.info.filename.clone();
+        print_transfer(format!("Upload of file '{}' has been stopped as a error either on sender or receiver side ocurred.", name.yellow()).red().to_string());
     }
 }
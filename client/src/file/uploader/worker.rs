@@ -1,12 +1,12 @@
 use std::{io::SeekFrom, path::Path, sync::Arc};
 
 use anyhow::anyhow;
-use bytes::{BytesMut, Buf};
 use log::{debug, trace, warn};
-use openssl::{pkey::Public, rsa::Rsa};
+use openssl::{hash::Hasher, pkey::{PKey, Public}, rsa::Rsa};
 use packets::{
-    consts::{CHUNK_SIZE, ONE_MB_SIZE},
-    file::{processing::tools::get_max_chunks, types::FileInfo, chunk::index::ChunkMsg}, encryption::sign::get_signature, other::key_iv::KeyIVPair
+    consts::{MSG_DIGEST, STREAM_PIECE_SIZE},
+    file::{processing::tools::{get_max_chunks, get_chunk_size}, types::FileInfo, chunk::{index::{ChunkEncryptor, ChunkHeader, get_encrypted_size}, compression::ChunkCompressor}}, other::key_iv::KeyIVPair,
+    util::compression::Compression
 };
 use tokio::{
     fs::File,
@@ -16,10 +16,203 @@ use tokio::{
 };
 use uuid::Uuid;
 
-use crate::{util::arcs::{get_curr_keypair, get_base_url}, web::{prefix::get_web_protocol, progress::upload_file}, file::tools::WorkerProgress};
+use crate::{util::{arcs::{get_curr_keypair, get_base_url}, consts::UPLOAD_LIMIT}, web::{prefix::get_web_protocol, progress::upload_file, throttle::get_piece_size}, file::{tools::{WorkerProgress, ChunkUpload}, journal::update_journal, blobs::{add_uploaded_blob, try_link_chunk, UploadedBlob}}, transfer::tools::{acquire_worker, add_transfer_bytes}};
 
 pub type ProgressTX = UnboundedSender<WorkerProgress>;
 pub type ArcProgressTX = Arc<RwLock<ProgressTX>>;
+// Receivers the chunks are uploaded for, shared by all workers of a transfer
+pub type ReceiverKeys = Arc<RwLock<Vec<(Uuid, Rsa<Public>)>>>;
+
+// Size of the next `chunk_size` bytes of `buf` after compressing them, the compressed data is discarded
+async fn get_compressed_size(buf: &mut BufReader<File>, chunk_size: usize, piece_size: usize, compression: Compression) -> anyhow::Result<usize> {
+    let mut compressor = ChunkCompressor::new(compression)?;
+    let mut piece = vec![0 as u8; piece_size];
+    let mut left = chunk_size;
+    let mut compressed = 0;
+    while left > 0 {
+        let to_read = piece_size.min(left);
+        let read = buf.read(&mut piece[..to_read]).await?;
+        if read == 0 {
+            return Err(anyhow!("File ended before chunk was read completely."));
+        }
+
+        left -= read;
+        compressed += compressor.update(&piece[..read])?.len();
+    }
+
+    compressed += compressor.finalize()?.len();
+    return Ok(compressed);
+}
+
+async fn on_chunk_uploaded(uuid: &Uuid, chunk_index: u64, chunk_size: u64, data_size: u64) {
+    let res = update_journal(uuid, |j| j.receivers.iter_mut().for_each(|e| { e.uploaded.insert(chunk_index); })).await;
+    if res.is_err() {
+        trace!("Could not journal uploaded chunk {}: {}", chunk_index, res.unwrap_err());
+    }
+
+    add_transfer_bytes(uuid, chunk_size, data_size).await;
+}
+
+async fn send_done(tx: &ArcProgressTX, chunk_index: u64) -> anyhow::Result<()> {
+    let tx = tx.read().await;
+    let e = tx.send(WorkerProgress {
+        progress: 1 as f32,
+        chunk: chunk_index
+    });
+
+    drop(tx);
+    e?;
+
+    return Ok(());
+}
+
+// Uploads chunk `i` for one receiver. Later receivers of the same chunk only link to the blob of the first upload.
+async fn upload_chunk(uuid: Uuid, i: u64, file: &FileInfo, receiver: &Uuid, receiver_key: &Rsa<Public>, tx: &ArcProgressTX) -> anyhow::Result<ChunkUpload> {
+    let path = file.path.clone().unwrap();
+    let size = file.size;
+    let file_chunk_size = file.chunk_size;
+    let file_compression = file.compression;
+    let chunk_hash = file.chunk_hashes.get(i as usize).cloned();
+
+    let f = File::open(&path).await?;
+    let mut buf = BufReader::new(f);
+    let seek_to = i64::try_from(file_chunk_size * i)?;
+
+    if (seek_to as u64) > size {
+        trace!("Invalid upload error with index {}", i);
+        return Err(anyhow!(format!("Can not seek to {} as file size is only {}", seek_to, size)));
+    }
+
+    trace!("Seeek {}", seek_to);
+    buf.seek(SeekFrom::Current(seek_to)).await?;
+
+    let chunk_size = usize::try_from(get_chunk_size(i, size, file_chunk_size)?)?;
+
+    // Chunks sent to someone else before are not uploaded again
+    let linked = if chunk_hash.is_some() {
+        try_link_chunk(&uuid, i, chunk_hash.as_ref().unwrap(), file_compression, receiver, receiver_key).await?
+    } else { None };
+
+    if linked.is_some() {
+        return Ok(linked.unwrap());
+    }
+
+    let piece_size = get_piece_size(&UPLOAD_LIMIT, STREAM_PIECE_SIZE).await;
+
+    // The size has to be known before uploading, so the chunk is compressed twice instead of being kept in memory.
+    // Chunks that do not get smaller are sent uncompressed.
+    let mut compression = file_compression;
+    let mut data_size = chunk_size;
+    if compression != Compression::None {
+        let compressed_size = get_compressed_size(&mut buf, chunk_size, piece_size, compression).await?;
+        if compressed_size < chunk_size {
+            data_size = compressed_size;
+        } else {
+            compression = Compression::None;
+        }
+
+        buf.seek(SeekFrom::Start(seek_to as u64)).await?;
+    }
+
+    let key = KeyIVPair::generate()?;
+    let encrypted_size = get_encrypted_size(data_size as u64);
+    let header = ChunkHeader {
+        uuid,
+        chunk_index: i,
+        compression,
+        key: key.clone(),
+        encrypted_size
+    }.serialize(receiver_key)?;
+
+    let keypair = PKey::from_rsa(get_curr_keypair().await?)?;
+    let body_size = header.len() + usize::try_from(encrypted_size)? + ChunkEncryptor::get_trailer_size(&keypair);
+
+    // zstd contexts are not Sync, but the body of surf has to be
+    let compressor = std::sync::Mutex::new(ChunkCompressor::new(compression)?);
+    // Hash of everything after the header, the server stores it under that
+    let blob_hash = Arc::new(std::sync::Mutex::new(None));
+    let blob_hash_stream = blob_hash.clone();
+    let stream_key = key.clone();
+
+    // Only one piece of the chunk is in memory at a time
+    let body = async_stream::try_stream! {
+        yield header;
+
+        let mut hasher = Hasher::new(*MSG_DIGEST)?;
+        let mut encryptor = ChunkEncryptor::new(&stream_key, &keypair)?;
+        let mut piece = vec![0 as u8; piece_size];
+        let mut left = chunk_size;
+        let mut compressed = 0;
+        while left > 0 {
+            // Never read into the next chunk, chunk sizes don't have to be a multiple of the piece size
+            let to_read = piece_size.min(left);
+            let read = buf.read(&mut piece[..to_read]).await?;
+            if read == 0 {
+                Err(anyhow!("File ended before chunk {} was read completely.", i))?;
+            }
+
+            left -= read;
+            // `?` yields the error, so the guard has to be dropped first
+            let data = compressor.lock().unwrap().update(&piece[..read]);
+            let data = data?;
+            compressed += data.len();
+
+            let encrypted = encryptor.update(&data)?;
+            hasher.update(&encrypted)?;
+            yield encrypted;
+        }
+
+        let data = compressor.into_inner().unwrap().finalize();
+        let data = data?;
+        compressed += data.len();
+
+        // The file changed since the first pass
+        if compressed != data_size {
+            Err(anyhow!("Chunk {} has {} bytes after compressing, expected {}.", i, compressed, data_size))?;
+        }
+
+        let mut last = encryptor.update(&data)?;
+        last.append(&mut encryptor.finalize()?);
+        hasher.update(&last)?;
+
+        let hash = hasher.finish()?.to_vec();
+        blob_hash_stream.lock().unwrap().replace(hash);
+        yield last;
+    };
+
+    let base_url = get_base_url().await;
+    let http_protocol = get_web_protocol().await;
+
+    let url = format!("{}//{}/file/upload?receiver={}", http_protocol, base_url, receiver);
+    trace!("Uploading chunk {} to {} with size {}...", i, url, body_size);
+
+    let res = upload_file(url, body, body_size, tx.clone(), i).await;
+    let mut res = res?;
+
+    let status = res.status();
+    let e = res.body_string().await;
+    if status == 410 {
+        return Ok(ChunkUpload::Gone);
+    }
+
+    if status != 200 {
+        eprintln!("Error uploading file: {}", e.unwrap_or("unknown err".to_string()));
+        return Ok(ChunkUpload::Failed);
+    }
+
+    let blob_hash = blob_hash.lock().unwrap().take();
+    if chunk_hash.is_some() && blob_hash.is_some() {
+        add_uploaded_blob(chunk_hash.as_ref().unwrap(), file_compression, UploadedBlob {
+            key,
+            compression,
+            data_size: data_size as u64,
+            encrypted_size,
+            blob_hash: blob_hash.unwrap()
+        }).await;
+    }
+
+    return Ok(ChunkUpload::Done(data_size as u64));
+}
 
 #[derive(Debug)]
 pub struct UploadWorker {
@@ -29,7 +222,7 @@ pub struct UploadWorker {
     thread: Option<JoinHandle<anyhow::Result<()>>>,
     running: bool,
     tx: ArcProgressTX,
-    receiver_key: Rsa<Public>,
+    receivers: ReceiverKeys,
     pub curr_chunk: Arc<RwLock<Option<u64>>>,
     aborted: Arc<RwLock<bool>>
 }
@@ -38,7 +231,7 @@ impl UploadWorker {
     pub fn new(
         worker_id: u64,
         uuid: Uuid,
-        receiver_key: Rsa<Public>,
+        receivers: ReceiverKeys,
         file: FileInfo,
         progress_channel: ArcProgressTX,
     ) -> anyhow::Result<UploadWorker> {
@@ -72,7 +265,7 @@ impl UploadWorker {
             thread: None,
             tx: progress_channel,
             running: false,
-            receiver_key,
+            receivers,
             curr_chunk: Arc::new(RwLock::new(None)),
             aborted: Arc::new(RwLock::new(false))
         });
@@ -96,9 +289,9 @@ impl UploadWorker {
 
         let i = chunk_index;
 
-        let path = file.path.unwrap();
         let size = file.size;
-        let receiver_key = self.receiver_key.clone();
+        let file_chunk_size = file.chunk_size;
+        let receivers_arc = self.receivers.clone();
 
         let mut state = self.curr_chunk.write().await;
         state.replace(chunk_index);
@@ -107,7 +300,7 @@ impl UploadWorker {
 
         let handle: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
             let to_run = || async {
-                let max_chunks = get_max_chunks(size);
+                let max_chunks = get_max_chunks(size, file_chunk_size);
 
                 if max_chunks <= 0 {
                     warn!("Max Threads is 0 in index {}", i);
@@ -120,92 +313,34 @@ impl UploadWorker {
                     max_chunks
                 );
 
-                let f = File::open(&path).await?;
-                let mut buf = BufReader::new(f);
-                let seek_to = i64::try_from(CHUNK_SIZE * i)?;
-
-                if (seek_to as u64) > size {
-                    trace!("Invalid upload error with index {}", i);
-                    return Err(anyhow!(format!("Can not seek to {} as file size is only {}", seek_to, size)));
+                let chunk_size = get_chunk_size(i, size, file_chunk_size)?;
+                let receivers = receivers_arc.read().await.clone();
+
+                let mut data_size = None;
+                for (receiver, receiver_key) in receivers {
+                    let res = upload_chunk(uuid, i, &file, &receiver, &receiver_key, &tx).await?;
+                    match res {
+                        ChunkUpload::Done(uploaded) => { data_size.get_or_insert(uploaded); },
+                        ChunkUpload::Failed => {},
+                        ChunkUpload::Gone => {
+                            trace!("Receiver {} left the transfer {}", receiver, uuid);
+                            receivers_arc.write().await.retain(|e| e.0 != receiver);
+                        }
+                    }
                 }
 
-                trace!("Seeek {}", seek_to);
-                buf.seek(SeekFrom::Current(seek_to)).await?;
-
-                let is_last_chunk = (i + max_chunks) >= max_chunks;
-
-                let chunk_size_u64 = if is_last_chunk {
-                    std::cmp::min(CHUNK_SIZE, size)
-                } else {
-                    CHUNK_SIZE
-                };
-                let chunk_size = usize::try_from(chunk_size_u64)?;
-
-                let mut chunk = Vec::with_capacity(chunk_size);
-
-                let mut bytes_read = 0;
-                trace!("while loop {}", seek_to);
-                while bytes_read < chunk_size {
-                    let to_read = std::cmp::min(ONE_MB_SIZE, chunk_size_u64);
-                    let to_read = usize::try_from(to_read)?;
-                    let mut small_chunk = BytesMut::with_capacity(to_read);
-
-                    buf.read_buf(&mut small_chunk).await?;
-                    chunk.append(&mut small_chunk.chunk().to_vec());
-
-                    let progress = (bytes_read as f32) / (chunk_size as f32) * 0.5;
-                    let tx = tx.read().await;
-                    let e = tx.send(WorkerProgress {
-                        progress,
-                        chunk: i
-                    });
-                    drop(tx);
-                    e?;
-
-                    bytes_read += to_read;
-                }
-
-                let key = KeyIVPair::generate()?;
-                let encrypted = key.encrypt(&chunk.to_vec())?;
-
-                let keypair = get_curr_keypair().await?;
-                let signature = get_signature(&encrypted.clone(), &keypair)?;
-
-                let base_url = get_base_url().await;
-                let http_protocol = get_web_protocol().await;
-
-                let url = format!("{}//{}/file/upload", http_protocol, base_url);
-
-                let body = ChunkMsg {
-                    signature,
-                    encrypted,
-                    uuid,
-                    chunk_index: i,
-                    key
-                }.serialize(&receiver_key)?;
-                trace!("Uploading chunk {} to {} with size {}...", i, url, body.len());
-
-                let res = upload_file(url, body,  tx.clone(), 0.5, chunk_index).await;
-                let mut res = res?;
-
-                let status = res.status();
-                let e = res.body_string().await;
-                if status != 200 {
-                    eprintln!("Error uploading file: {}", e.unwrap_or("unknown err".to_string()));
+                if data_size.is_some() {
+                    on_chunk_uploaded(&uuid, i, chunk_size, data_size.unwrap()).await;
                 }
 
-                let tx = tx.read().await;
-                let e = tx.send(WorkerProgress {
-                    progress: 1 as f32,
-                    chunk: i
-                });
-                drop(tx);
-                e?;
                 debug!("Worker {} of file {} done.", i, uuid);
-                Ok(())
+                send_done(&tx, i).await
             };
 
+            // Waits until the global worker budget allows another upload
+            let permit = acquire_worker(&uuid).await?;
             let res = to_run().await;
+            drop(permit);
 
             let mut state = curr_chunk_arc.write().await;
             state.take();
@@ -12,18 +12,22 @@ use tokio_tungstenite::tungstenite::Message;
 use util::consts::{RECEIVE_RX, RECEIVE_TX};
 use log::trace;
 
-use crate::encryption::rsa::generate;
+use crate::encryption::keystore::{export_identity, get_default_identity_path, import_identity, load_or_generate, rotate_identity};
+use crate::msg::hello::negotiate_protocol;
 use crate::msg::receive::index::receive_msgs;
 use crate::msg::send::index::send_msgs;
-use crate::util::consts::{BASE_URL, CONCURRENT_THREADS, KEYPAIR, TX_CHANNEL, USE_TLS};
+use crate::util::consts::{BASE_URL, CHUNK_BOUNDS, CHUNK_SIZE, COMPRESSION, CONCURRENT_THREADS, DOWNLOAD_LIMIT, FEATURES, KEYPAIR, TX_CHANNEL, UPLOAD_LIMIT, USE_TLS, WORKER_PERMITS};
 use crate::util::msg::send_msg;
 use crate::util::types::Args;
 use crate::web::prefix::get_ws_protocol;
+use crate::web::throttle::set_rate;
 
 mod encryption;
 mod file;
 mod input;
 mod msg;
+mod room;
+mod transfer;
 mod util;
 mod web;
 
@@ -82,10 +86,50 @@ async fn _async_main() -> anyhow::Result<()> {
     let mut state = CONCURRENT_THREADS.write().await;
     *state = args.threads.unwrap_or(64 as usize) as u64;
 
+    WORKER_PERMITS.add_permits(*state as usize);
     drop(state);
 
-    println!("{}", format!("Generating RSA keypair...").green());
-    let keypair = generate();
+    set_rate(&UPLOAD_LIMIT, args.max_upload_rate.unwrap_or(0)).await;
+    set_rate(&DOWNLOAD_LIMIT, args.max_download_rate.unwrap_or(0)).await;
+
+    if args.chunk_size.is_some() {
+        let mut state = CHUNK_SIZE.write().await;
+        *state = args.chunk_size.unwrap();
+
+        drop(state);
+    }
+
+    let mut state = COMPRESSION.write().await;
+    *state = args.compression;
+
+    drop(state);
+
+    let identity_path = match args.identity {
+        Some(e) => e,
+        None => get_default_identity_path()?
+    };
+
+    let mut keypair = load_or_generate(&identity_path).await?;
+    if args.export_identity.is_some() {
+        let to = args.export_identity.unwrap();
+        export_identity(&to).await?;
+
+        println!("{}", format!("Identity exported to {}.", to.to_string_lossy().yellow()).green());
+        return Ok(());
+    }
+
+    if args.import_identity.is_some() {
+        let from = args.import_identity.unwrap();
+        let passphrase = inquire::Password::new("Passphrase of the identity to import:").prompt()?;
+
+        keypair = import_identity(&from, &passphrase).await?;
+        println!("{}", format!("Identity imported from {}.", from.to_string_lossy().yellow()).green());
+    }
+
+    if args.rotate_identity {
+        keypair = rotate_identity().await?;
+        println!("{}", format!("Identity has been rotated. Your peers will see a new key.").green());
+    }
 
     let mut state = KEYPAIR.write().await;
     *state = Some(keypair.clone());
@@ -102,10 +146,21 @@ async fn _async_main() -> anyhow::Result<()> {
 
     let (ws_stream, _) = connect_async(ws_url.to_string()).await?;
 
-    let (tx, rx) = ws_stream.split();
+    let (tx, mut rx) = ws_stream.split();
     let mut state = TX_CHANNEL.lock().await;
     *state = Some(tx);
 
+    drop(state);
+    let ack = negotiate_protocol(&mut rx).await?;
+
+    let mut state = FEATURES.write().await;
+    *state = ack.features;
+
+    drop(state);
+
+    let mut state = CHUNK_BOUNDS.write().await;
+    *state = (ack.min_chunk_size, ack.max_chunk_size);
+
     drop(state);
 
     if args.name.is_some() {
@@ -0,0 +1,39 @@
+use anyhow::anyhow;
+use colored::Colorize;
+use futures_util::StreamExt;
+use log::debug;
+use packets::{initialize::hello::{HelloAckMsg, HelloMsg}, packet::index::Packet, types::ByteMessage};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::util::{msg::send_msg, types::RXChannel};
+
+// Has to happen before any other packet is sent
+pub async fn negotiate_protocol(rx: &mut RXChannel) -> anyhow::Result<HelloAckMsg> {
+    let hello = HelloMsg::current();
+    send_msg(Message::binary(hello.serialize())).await?;
+
+    let reply = rx.next().await;
+    if reply.is_none() {
+        return Err(anyhow!("Server closed the connection before answering hello."));
+    }
+
+    let reply = reply.unwrap()?;
+    let packet = Packet::decode(&reply.into_data());
+    if packet.is_err() {
+        return Err(anyhow!("Could not read answer of server to hello ({}). The server is probably outdated.", packet.err().unwrap()));
+    }
+
+    let ack = match packet.unwrap() {
+        Packet::HelloAck(ack) => ack,
+        Packet::Error(err) => {
+            eprintln!("{}", format!("Server refused connection: {}", err.error).red());
+            return Err(anyhow!("Incompatible server."));
+        }
+        _ => return Err(anyhow!("Expected hello answer of server. The server is probably outdated.")),
+    };
+
+    ack.verify(&hello)?;
+    debug!("Negotiated protocol version {} (features {:#b}, {:?})", ack.version, ack.features, ack.cipher_suite);
+
+    return Ok(ack);
+}
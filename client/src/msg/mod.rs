@@ -1,2 +1,3 @@
 pub mod receive;
-pub mod send;
\ No newline at end of file
+pub mod send;
+pub mod hello;
\ No newline at end of file
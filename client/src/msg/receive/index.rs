@@ -1,10 +1,7 @@
-use std::collections::VecDeque;
-
 use anyhow::anyhow;
 use colored::Colorize;
 use futures_util::StreamExt;
-use packets::util::modes::Modes;
-use packets::util::vec::decque_to_vec;
+use packets::packet::index::Packet;
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::util::types::*;
@@ -15,10 +12,13 @@ use super::packets::file::chunk::downloaded::on_chunk_downloaded;
 use super::packets::file::chunk::ready::on_chunk_ready;
 use super::packets::file::question::index::on_file_question;
 use super::packets::file::question::reply::on_file_question_reply;
+use super::packets::file::question::manifest::on_file_manifest;
 use super::packets::file::start_processing::on_start_processing;
+use super::packets::file::resume::on_file_resume;
 use super::packets::symm_key::on_symm_key;
 use super::packets::want_symm_key::on_want_symm_key;
-use super::packets::{from::on_from, uid::on_uid};
+use super::packets::{from::on_from, from_offline::on_from_offline, left::on_user_left, uid::on_uid};
+use super::packets::room::{key::on_room_key, list::on_room_list, msg::on_room_msg, roster::on_room_roster};
 
 pub async fn receive_msgs(mut rx: RXChannel) -> anyhow::Result<()> {
     while let Some(msg) = rx.next().await {
@@ -45,73 +45,40 @@ pub async fn receive_msgs(mut rx: RXChannel) -> anyhow::Result<()> {
 
 pub async fn handle(msg: Message) -> anyhow::Result<()> {
     let data = msg.into_data();
-    let mut decque: VecDeque<u8> = VecDeque::new();
-    for i in data {
-        decque.push_back(i);
-    }
-
-    let mode = decque.pop_front();
-    if mode.is_none() {
-        return Ok(());
-    }
-
-    let mode = mode.unwrap();
-    let mut data = decque_to_vec(decque);
-
-    if Modes::From.is_indicator(&mode) {
-        on_from(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::UidReply.is_indicator(&mode) {
-        on_uid(&mut data).await?;
+    if data.is_empty() {
         return Ok(());
     }
 
-    if Modes::SendFileQuestion.is_indicator(&mode) {
-        on_file_question(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::SendFileQuestionReply.is_indicator(&mode) {
-        on_file_question_reply(&mut data).await?;
-        return Ok(());
+    let packet = Packet::decode(&data)?;
+    match packet {
+        Packet::From(msg) => on_from(msg).await,
+        Packet::FromOffline(msg) => on_from_offline(msg).await,
+        Packet::UserLeft(msg) => on_user_left(msg).await,
+        Packet::UidReply(msg) => on_uid(msg).await,
+        Packet::SendFileQuestion(msg) => on_file_question(msg).await,
+        Packet::SendFileQuestionReply(msg) => on_file_question_reply(msg).await,
+        Packet::SendFileManifest(msg) => on_file_manifest(msg).await,
+        Packet::Error(msg) => on_error(msg).await,
+        Packet::SendFileStartProcessing(msg) => on_start_processing(msg).await,
+        Packet::SendFileChunkReady(msg) => on_chunk_ready(msg).await,
+        Packet::SendFileChunkDownloaded(msg) => on_chunk_downloaded(msg).await,
+        Packet::SendFileAbort(msg) => on_chunk_abort(msg).await,
+        Packet::SendFileResume(msg) => on_file_resume(msg).await,
+        Packet::SymmKey(msg) => on_symm_key(msg).await,
+        Packet::WantSymmKey(msg) => on_want_symm_key(msg).await,
+        Packet::RoomRoster(msg) => on_room_roster(msg).await,
+        Packet::RoomKey(msg) => on_room_key(msg).await,
+        Packet::RoomMsg(msg) => on_room_msg(msg).await,
+        Packet::RoomList(msg) => on_room_list(msg).await,
+        Packet::HelloAck(_) => Err(anyhow!("Received hello answer twice.")),
+
+        // Only sent by clients
+        Packet::SetPubkey(_)
+        | Packet::To(_)
+        | Packet::ToOffline(_)
+        | Packet::Name(_)
+        | Packet::WantUid
+        | Packet::Hello(_)
+        | Packet::RoomAction(_) => Err(anyhow!("Invalid packet received.")),
     }
-
-    if Modes::Error.is_indicator(&mode) {
-        on_error(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::SendFileStartProcessing.is_indicator(&mode) {
-        on_start_processing(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::SendFileChunkReady.is_indicator(&mode) {
-        on_chunk_ready(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::SendFileChunkDownloaded.is_indicator(&mode) {
-        on_chunk_downloaded(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::SendFileAbort.is_indicator(&mode) {
-        on_chunk_abort(&mut data).await?;
-        return Ok(())
-    }
-
-    if Modes::SymmKey.is_indicator(&mode) {
-        on_symm_key(&mut data).await?;
-        return Ok(());
-    }
-
-    if Modes::WantSymmKey.is_indicator(&mode) {
-        on_want_symm_key(&mut data).await?;
-        return Ok(());
-    }
-
-    return Err(anyhow!("Invalid packet received."));
 }
@@ -1,8 +1,13 @@
 use colored::Colorize;
-use packets::{communication::error::ErrorMsg, types::ByteMessage};
+use packets::communication::error::{ErrorCode, ErrorMsg};
 
-pub async fn on_error(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let ErrorMsg { error } = ErrorMsg::deserialize(data)?;
+pub async fn on_error(msg: ErrorMsg) -> anyhow::Result<()> {
+    let ErrorMsg { code, error } = msg;
+
+    if code != ErrorCode::Generic {
+        eprintln!("{}", format!("Server rejected the file: {}", error).red());
+        return Ok(());
+    }
 
     eprintln!("{}", format!("Server returned error: {}", error).red());
     Ok(())
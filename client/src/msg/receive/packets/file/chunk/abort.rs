@@ -1,12 +1,10 @@
 use anyhow::anyhow;
-use packets::{
-    file:: processing::abort::ChunkAbortMsg,
-    types::ByteMessage,
-};
-use crate::util::consts::{FILE_DOWNLOADS, FILE_UPLOADS};
+use packets::file::processing::abort::ChunkAbortMsg;
+use colored::Colorize;
+use crate::{util::consts::{FILE_DOWNLOADS, FILE_UPLOADS, PENDING_FILES}, file::journal::remove_journal};
 
-pub async fn on_chunk_abort(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let msg = ChunkAbortMsg::deserialize(data)?;
+pub async fn on_chunk_abort(msg: ChunkAbortMsg) -> anyhow::Result<()> {
+    remove_journal(&msg.uuid).await?;
 
     let state = FILE_DOWNLOADS.write().await;
     let downloader = state.get(&msg.uuid);
@@ -30,5 +28,17 @@ pub async fn on_chunk_abort(data: &mut Vec<u8>) -> anyhow::Result<()> {
         return Ok(());
     }
 
+    drop(state);
+
+    // The offer has been cancelled or has not been answered in time
+    let mut state = PENDING_FILES.write().await;
+    let pending = state.remove(&msg.uuid);
+
+    drop(state);
+    if pending.is_some() {
+        println!("{}", format!("The offer of '{}' has been cancelled.", pending.unwrap().filename.yellow()).red());
+        return Ok(());
+    }
+
     Err(anyhow!("Received abort msg but could not find file"))
 }
@@ -4,11 +4,9 @@ use packets::{
     types::ByteMessage,
 };
 use tokio_tungstenite::tungstenite::Message;
-use crate::util::{consts::FILE_UPLOADS, msg::send_msg, tools::uuid_to_name};
-
-pub async fn on_chunk_downloaded(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let msg = ChunkDownloadedMsg::deserialize(data)?;
+use crate::{util::{consts::FILE_UPLOADS, msg::send_msg, tools::uuid_to_name}, file::journal::{remove_journal, update_journal}, transfer::{state::TransferState, tools::{get_compression_suffix, print_transfer, set_transfer_state}}};
 
+pub async fn on_chunk_downloaded(msg: ChunkDownloadedMsg) -> anyhow::Result<()> {
     let state = FILE_UPLOADS.read().await;
     let uploader = state.get(&msg.uuid);
 
@@ -24,12 +22,24 @@ pub async fn on_chunk_downloaded(data: &mut Vec<u8>) -> anyhow::Result<()> {
     let uploader = uploader.unwrap();
     let is_done = uploader.is_done().await;
 
+    // The server only sends this once every receiver has the chunk
+    let journal = update_journal(&msg.uuid, |j| j.receivers.iter_mut().for_each(|e| { e.downloaded.insert(msg.chunk_index); })).await;
+    if journal.is_ok() && journal.unwrap().is_done() {
+        remove_journal(&msg.uuid).await?;
+    }
 
     if is_done {
-        let FileInfo {filename, receiver, ..} = uploader.get_file_info();
-        let receiver_name = uuid_to_name(receiver).await?;
+        let FileInfo {filename, ..} = uploader.get_file_info();
+        let mut receiver_names = Vec::new();
+        for receiver in uploader.get_receivers().await {
+            receiver_names.push(uuid_to_name(receiver).await?);
+        }
+
+        let receiver_name = receiver_names.join(", ");
 
-        println!("{}", format!("File '{}' was successfully sent to {}.", filename.yellow(), receiver_name.blue().bold()).green());
+        set_transfer_state(&msg.uuid, TransferState::Finished).await;
+        let compression = get_compression_suffix(&msg.uuid).await;
+        print_transfer(format!("File '{}' was successfully sent to {}{}.", filename.yellow(), receiver_name.blue().bold(), compression).green().to_string());
         return Ok(());
     }
 
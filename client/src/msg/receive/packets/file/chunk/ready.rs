@@ -6,15 +6,20 @@ use packets::{
 use tokio_tungstenite::tungstenite::Message;
 use log::trace;
 
-use crate::util::{consts::FILE_DOWNLOADS, msg::send_msg};
-pub async fn on_chunk_ready(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let msg = ChunkReadyMsg::deserialize(data)?;
+use crate::{util::{consts::FILE_DOWNLOADS, msg::send_msg}, file::journal::get_journal};
+pub async fn on_chunk_ready(msg: ChunkReadyMsg) -> anyhow::Result<()> {
     trace!("Received {:?}", msg);
 
     let state = FILE_DOWNLOADS.read().await;
     let downloader = state.get(&msg.uuid);
 
     if downloader.is_none() {
+        drop(state);
+        if get_journal(&msg.uuid).await?.is_some() {
+            trace!("Transfer {} has not been resumed yet, ignoring ready chunk {}", msg.uuid, msg.chunk_index);
+            return Ok(());
+        }
+
         send_msg(Message::binary(ChunkAbortMsg {
             uuid: msg.uuid.clone()
         }.serialize())).await?;
@@ -1,3 +1,4 @@
 pub mod start_processing;
 pub mod question;
-pub mod chunk;
\ No newline at end of file
+pub mod chunk;
+pub mod resume;
\ No newline at end of file
@@ -4,31 +4,43 @@ use anyhow::anyhow;
 use colored::Colorize;
 use indicatif::HumanBytes;
 use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
 use packets::{
     file::{
         question::{ index::FileQuestionMsg, reply::FileQuestionReplyMsg },
         types::FileInfo,
         processing::tools::get_max_chunks,
+        journal::JournalReceiver,
     },
     types::ByteMessage,
+    encryption::fingerprint::get_fingerprint,
 };
-use tokio::{ fs::{File, remove_file}, io::AsyncWriteExt };
+use tokio::{ fs::{File, OpenOptions, remove_file}, io::AsyncWriteExt };
 use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
 
 use crate::{
     util::{
         consts::{ PENDING_FILES, FILE_DOWNLOADS },
         msg::{ send_msg, get_input },
-        tools::{ uuid_to_name, wait_confirm }, arcs::get_concurrent_threads,
+        tools::{ uuid_to_name, wait_confirm }, arcs::{get_concurrent_threads, get_curr_id},
     },
-    file::downloader::index::Downloader,
+    encryption::ratchet::verify_control,
+    file::{downloader::index::Downloader, journal::{create_journal, get_own_fingerprint}},
     web::user_info::get_user_info,
 };
 
-pub async fn on_file_question(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let msg = FileQuestionMsg::deserialize(data)?;
+pub async fn on_file_question(msg: FileQuestionMsg) -> anyhow::Result<()> {
     let sender_name = uuid_to_name(msg.sender).await?;
 
+    let curr_id = get_curr_id().await?;
+    let valid = verify_control(&msg.sender, msg.seq, &msg.mac, &msg.get_auth_data()).await;
+    if msg.receiver != curr_id || valid.is_err() {
+        let reason = valid.err().map(|e| e.to_string()).unwrap_or("File request is not addressed to you.".to_string());
+        eprintln!("{}", format!("Rejected file request from '{}': {}", sender_name.yellow(), reason).red());
+        return Ok(());
+    }
+
     let size_str = format!("{}", HumanBytes(msg.size));
     let confirm_msg = format!(
         "{} wants to send you the file '{}' of size {}. Accept? (y/n)",
@@ -50,7 +62,7 @@ pub async fn on_file_question(data: &mut Vec<u8>) -> anyhow::Result<()> {
 }
 
 pub async fn check_accepted(msg: FileQuestionMsg) -> anyhow::Result<bool> {
-    let FileQuestionMsg { filename, receiver, size, sender, uuid, hash } = msg;
+    let FileQuestionMsg { filename, receiver, size, chunk_size, compression, sender, uuid, hash, chunk_hashes, .. } = msg;
 
     let accepted = wait_confirm().await?;
     if !accepted {
@@ -138,16 +150,13 @@ pub async fn check_accepted(msg: FileQuestionMsg) -> anyhow::Result<bool> {
         receiver,
         sender,
         size,
+        chunk_size,
+        compression,
         path: Some(path),
-        hash
+        hash,
+        chunk_hashes
     };
 
-    trace!("Waiting for pending files...");
-    let mut state = PENDING_FILES.write().await;
-    state.insert(uuid, info.clone());
-
-    drop(state);
-
     trace!("Getting user info...");
     let user = get_user_info(&sender).await?;
     let key = user.public_key;
@@ -156,11 +165,38 @@ pub async fn check_accepted(msg: FileQuestionMsg) -> anyhow::Result<bool> {
         return Err(anyhow!("Sender does not have a public key"));
     }
 
-    let key = key.unwrap();
+    prepare_download(&uuid, &info, &key.unwrap()).await?;
+    return Ok(true);
+}
+
+// Registers the downloader, chunks are downloaded as soon as the server says they are ready
+pub async fn prepare_download(uuid: &Uuid, info: &FileInfo, key: &Rsa<Public>) -> anyhow::Result<()> {
+    let uuid = uuid.clone();
+
+    trace!("Waiting for pending files...");
+    let mut state = PENDING_FILES.write().await;
+    state.insert(uuid, info.clone());
+
+    drop(state);
+
+    let receivers = vec![JournalReceiver::new(0, get_own_fingerprint().await?)];
+    let res = create_journal(&uuid, info, get_fingerprint(key)?, receivers).await;
+    if res.is_err() {
+        eprintln!("{}", format!("Could not create transfer journal, the download can not be resumed: {}", res.unwrap_err()).yellow());
+    }
+
+    // Workers write their chunks at an offset, so bytes of an older file after the end would be kept
+    let f = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(info.path.as_ref().unwrap())
+        .await?;
+
+    f.set_len(info.size).await?;
 
     trace!("Initializing downloader...");
-    let mut downloader = Downloader::new(&uuid, key, &info);
-    downloader.initialize(get_max_chunks(info.size)).await?;
+    let mut downloader = Downloader::new(&uuid, key.clone(), info);
+    downloader.initialize(get_max_chunks(info.size, info.chunk_size)).await?;
 
     trace!("Aquiring lock on file_downloads...");
     let mut state = FILE_DOWNLOADS.write().await;
@@ -170,5 +206,5 @@ pub async fn check_accepted(msg: FileQuestionMsg) -> anyhow::Result<bool> {
 
     drop(state);
     trace!("Done.");
-    return Ok(true);
+    return Ok(());
 }
\ No newline at end of file
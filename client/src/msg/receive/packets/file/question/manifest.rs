@@ -0,0 +1,198 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::anyhow;
+use colored::Colorize;
+use indicatif::HumanBytes;
+use log::trace;
+use packets::{
+    file::{manifest::index::FileManifestMsg, question::reply::FileQuestionReplyMsg, types::FileInfo},
+    types::ByteMessage,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    encryption::ratchet::verify_control,
+    file::manifest::{get_entry_path, set_pending_permissions},
+    util::{arcs::get_curr_id, msg::{get_input, send_msg}, tools::{uuid_to_name, wait_confirm}},
+    web::user_info::get_user_info,
+};
+
+use super::index::prepare_download;
+
+// Amount of entries listed when asking the user
+const SHOWN_ENTRIES: usize = 10;
+
+pub async fn on_file_manifest(msg: FileManifestMsg) -> anyhow::Result<()> {
+    let sender_name = uuid_to_name(msg.sender).await?;
+
+    let curr_id = get_curr_id().await?;
+    let valid = verify_control(&msg.sender, msg.seq, &msg.mac, &msg.get_auth_data()).await;
+    if msg.receiver != curr_id || valid.is_err() {
+        let reason = valid.err().map(|e| e.to_string()).unwrap_or("File request is not addressed to you.".to_string());
+        eprintln!("{}", format!("Rejected file request from '{}': {}", sender_name.yellow(), reason).red());
+        return Ok(());
+    }
+
+    let invalid = msg.entries.iter().find(|e| !e.is_valid_path());
+    if invalid.is_some() {
+        let path = invalid.unwrap().path.clone();
+        eprintln!("{}", format!("Rejected file request from '{}': invalid path '{}'", sender_name.yellow(), path).red());
+
+        return reply_all(&msg, false).await;
+    }
+
+    let size_str = format!("{}", HumanBytes(msg.get_total_size()));
+    println!(
+        "{} wants to send you {} files of size {}:",
+        sender_name.green(),
+        msg.entries.len(),
+        size_str.purple()
+    );
+
+    for entry in msg.entries.iter().take(SHOWN_ENTRIES) {
+        println!("  {} ({})", entry.path.yellow(), HumanBytes(entry.size));
+    }
+
+    if msg.entries.len() > SHOWN_ENTRIES {
+        println!("  {}", format!("and {} more", msg.entries.len() - SHOWN_ENTRIES).bright_black());
+    }
+
+    println!("Accept? (y/n)");
+
+    let accepted = check_manifest_accepted(&msg).await;
+    if accepted.is_err() {
+        reply_all(&msg, false).await?;
+        return Err(accepted.unwrap_err());
+    }
+
+    return reply_all(&msg, accepted.unwrap()).await;
+}
+
+async fn reply_all(msg: &FileManifestMsg, accepted: bool) -> anyhow::Result<()> {
+    for entry in &msg.entries {
+        let to_send = (FileQuestionReplyMsg {
+            accepted,
+            uuid: entry.uuid.clone(),
+        }).serialize();
+
+        send_msg(Message::binary(to_send)).await?;
+    }
+
+    return Ok(());
+}
+
+async fn check_manifest_accepted(msg: &FileManifestMsg) -> anyhow::Result<bool> {
+    let accepted = wait_confirm().await?;
+    if !accepted {
+        println!("{}", "You denied the file request.".red());
+        return Ok(false);
+    }
+
+    let base: PathBuf;
+    loop {
+        println!(
+            "{}",
+            "Where do you want to save these files (default is in current directory)?".yellow()
+        );
+
+        let raw_path = get_input().await?;
+        let raw_path = if raw_path.trim().is_empty() { ".".to_string() } else { raw_path };
+
+        let try_path = PathBuf::from(&raw_path);
+        let res = tokio::fs::create_dir_all(&try_path).await;
+        if res.is_err() {
+            let err = res.unwrap_err();
+            eprintln!("{}", format!("Could not create directory at given path: {:?}. Please enter a valid path.", err).red());
+            continue;
+        }
+
+        base = tokio::fs::canonicalize(&try_path).await?;
+        break;
+    }
+
+    let mut paths = Vec::new();
+    for entry in &msg.entries {
+        paths.push(get_entry_path(&base, entry)?);
+    }
+
+    let existing = paths.iter().filter(|e| e.exists()).count();
+    if existing > 0 {
+        println!(
+            "{}",
+            format!(
+                "{} of these files exist already. Overwrite? ({}/{})",
+                existing,
+                "y".green(),
+                "n".red()
+            ).yellow()
+        );
+
+        let overwrite = wait_confirm().await?;
+        if !overwrite {
+            println!("{}", "You denied the file request.".red());
+            return Ok(false);
+        }
+    }
+
+    for path in &paths {
+        create_parent(&base, path).await?;
+    }
+
+    trace!("Getting user info...");
+    let user = get_user_info(&msg.sender).await?;
+    let key = user.public_key;
+
+    if key.is_none() {
+        return Err(anyhow!("Sender does not have a public key"));
+    }
+
+    let key = key.unwrap();
+    for (entry, path) in msg.entries.iter().zip(paths) {
+        let info = FileInfo {
+            filename: entry.path.clone(),
+            receiver: msg.receiver,
+            sender: msg.sender,
+            size: entry.size,
+            chunk_size: msg.chunk_size,
+            compression: entry.compression,
+            path: Some(path),
+            hash: entry.hash.clone(),
+            chunk_hashes: entry.chunk_hashes.clone()
+        };
+
+        set_pending_permissions(&entry.uuid, entry.permissions).await;
+        prepare_download(&entry.uuid, &info, &key).await?;
+    }
+
+    println!("{}", format!("Receiving {} files into '{}'...", msg.entries.len(), base.display()).green());
+    return Ok(true);
+}
+
+// Creates the directories of `path` one by one, each existing one is checked before going deeper
+// so neither `..` nor symlinks can create anything outside of `base`
+async fn create_parent(base: &Path, path: &Path) -> anyhow::Result<()> {
+    let parent = path.parent().unwrap_or(base);
+    let relative = parent.strip_prefix(base);
+    if relative.is_err() || !relative.as_ref().unwrap().components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(anyhow!("Path {:?} is outside of {:?}.", path, base));
+    }
+
+    let mut curr = base.to_path_buf();
+    for part in relative.unwrap().components() {
+        curr.push(part);
+        if !curr.exists() && !curr.is_symlink() {
+            tokio::fs::create_dir(&curr).await?;
+        }
+
+        let resolved = tokio::fs::canonicalize(&curr).await?;
+        if !resolved.starts_with(base) || !resolved.is_dir() {
+            return Err(anyhow!("Path {:?} is outside of {:?}.", path, base));
+        }
+    }
+
+    if path.is_symlink() || path.is_dir() {
+        return Err(anyhow!("Can not write to {:?}, it is a directory or symlink.", path));
+    }
+
+    return Ok(());
+}
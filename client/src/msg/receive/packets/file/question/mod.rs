@@ -1,2 +1,3 @@
 pub mod index;
-pub mod reply;
\ No newline at end of file
+pub mod reply;
+pub mod manifest;
\ No newline at end of file
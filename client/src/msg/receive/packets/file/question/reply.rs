@@ -1,10 +1,10 @@
 use colored::Colorize;
-use packets::{file::{question::reply::FileQuestionReplyMsg, types::FileInfo}, types::ByteMessage};
+use packets::file::{question::reply::FileQuestionReplyMsg, types::FileInfo};
 
 use crate::{util::tools::uuid_to_name, file::tools::get_pending_file};
 
-pub async fn on_file_question_reply(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let FileQuestionReplyMsg { accepted, uuid} = FileQuestionReplyMsg::deserialize(data)?;
+pub async fn on_file_question_reply(msg: FileQuestionReplyMsg) -> anyhow::Result<()> {
+    let FileQuestionReplyMsg { accepted, uuid} = msg;
     let file = get_pending_file(uuid).await;
 
     if file.is_err() {
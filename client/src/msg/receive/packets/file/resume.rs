@@ -0,0 +1,114 @@
+use colored::Colorize;
+use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::{encryption::fingerprint::get_fingerprint, file::{processing::resume::FileResumeMsg, journal::TransferJournal, types::FileInfo}};
+use uuid::Uuid;
+
+use crate::{
+    encryption::rsa::get_pubkey_from_rec,
+    file::{downloader::index::Downloader, journal::{get_journal, get_own_fingerprint}, uploader::index::Uploader},
+    util::{arcs::get_concurrent_threads, consts::{FILE_DOWNLOADS, FILE_UPLOADS}, tools::uuid_to_name},
+};
+
+pub async fn on_file_resume(msg: FileResumeMsg) -> anyhow::Result<()> {
+    let journal = get_journal(&msg.uuid).await?;
+    if journal.is_none() {
+        eprintln!("{}", format!("Could not resume transfer {}, it is not in the journal.", msg.uuid).red());
+        return Ok(());
+    }
+
+    let journal = journal.unwrap();
+    let is_sender = get_own_fingerprint().await? == journal.sender;
+    if is_sender {
+        return resume_upload(&msg, &journal).await;
+    }
+
+    if msg.sender.is_nil() {
+        println!("{}", format!("Transfer of '{}' will continue as soon as the other side is online and runs /resume.", journal.filename.yellow()).bright_black());
+        return Ok(());
+    }
+
+    // The peer has a new connection id, make sure it still is the same key
+    let sender_key = get_pubkey_from_rec(&msg.sender).await?;
+    if get_fingerprint(&sender_key)? != journal.sender {
+        eprintln!("{}", format!("Could not resume transfer of '{}', the key of the other side changed.", journal.filename.yellow()).red());
+        return Ok(());
+    }
+
+    let sender_name = uuid_to_name(msg.sender).await?;
+    let receiver = msg.receivers.first().cloned().unwrap_or(Uuid::nil());
+    let info = journal.to_file_info(&msg.sender, &receiver);
+
+    println!("{}", format!("Resuming download of '{}' from {} ({}/{} chunks done).", info.filename.yellow(), sender_name.blue(), msg.done.len(), journal.get_max_chunks()).green());
+    return resume_download(&msg, &journal, info, sender_key).await;
+}
+
+// Uploads the chunks the connected receivers are missing, the others get theirs once they resume
+async fn resume_upload(msg: &FileResumeMsg, journal: &TransferJournal) -> anyhow::Result<()> {
+    let mut keys = Vec::new();
+    let mut receiver_names = Vec::new();
+    for receiver in &msg.receivers {
+        // The receiver has a new connection id, make sure it still is the same key
+        let receiver_key = get_pubkey_from_rec(receiver).await?;
+        let name = uuid_to_name(receiver.clone()).await?;
+        if journal.get_receiver(&get_fingerprint(&receiver_key)?).is_none() {
+            eprintln!("{}", format!("Could not resume transfer of '{}' to {}, the key of the receiver changed.", journal.filename.yellow(), name.blue()).red());
+            continue;
+        }
+
+        keys.push((receiver.clone(), receiver_key));
+        receiver_names.push(name);
+    }
+
+    if keys.is_empty() {
+        println!("{}", format!("Transfer of '{}' will continue as soon as the other side is online and runs /resume.", journal.filename.yellow()).bright_black());
+        return Ok(());
+    }
+
+    let info = journal.to_file_info(&msg.sender, &keys[0].0);
+    println!("{}", format!("Resuming upload of '{}' to {} ({}/{} chunks done).", info.filename.yellow(), receiver_names.join(", ").blue(), msg.done.len(), journal.get_max_chunks()).green());
+
+    let threads = get_concurrent_threads().await;
+
+    let mut state = FILE_UPLOADS.write().await;
+    // Workers of the interrupted upload stop on their own
+    state.remove(&msg.uuid);
+
+    let mut uploader = Uploader::new(&msg.uuid, keys, &info);
+    uploader.mark_completed(&msg.done).await;
+
+    let res = uploader.start(threads).await;
+    if res.is_err() {
+        drop(state);
+        return res;
+    }
+
+    state.insert(msg.uuid, uploader);
+    drop(state);
+
+    return Ok(());
+}
+
+async fn resume_download(msg: &FileResumeMsg, journal: &TransferJournal, info: FileInfo, sender_key: Rsa<Public>) -> anyhow::Result<()> {
+    let uuid = msg.uuid;
+
+    let mut downloader = Downloader::new(&uuid, sender_key, &info);
+    downloader.initialize(journal.get_max_chunks()).await?;
+    downloader.mark_completed(&msg.done).await;
+
+    let mut state = FILE_DOWNLOADS.write().await;
+    state.insert(uuid, downloader);
+
+    drop(state);
+
+    let state = FILE_DOWNLOADS.read().await;
+    let downloader = state.get(&uuid).unwrap();
+
+    for chunk in &msg.ready {
+        trace!("Downloading chunk {} of resumed transfer {}", chunk, uuid);
+        downloader.start_downloading(chunk.clone()).await?;
+    }
+
+    drop(state);
+    return Ok(());
+}
@@ -1,24 +1,44 @@
+use anyhow::anyhow;
 use colored::Colorize;
 use log::trace;
-use packets::{file::processing::start::FileStartProcessing, types::ByteMessage};
+use packets::{encryption::fingerprint::get_fingerprint, file::{journal::JournalReceiver, processing::start::FileStartProcessing}};
 
-use crate::{util::{tools::uuid_to_name, consts::FILE_UPLOADS, arcs::get_concurrent_threads}, file::{tools::get_pending_file, uploader::index::Uploader}, encryption::rsa::get_pubkey_from_rec};
+use crate::{util::{tools::uuid_to_name, consts::FILE_UPLOADS, arcs::get_concurrent_threads}, file::{tools::get_pending_file, uploader::index::Uploader, journal::{create_journal, get_own_fingerprint}}, encryption::rsa::get_pubkey_from_rec};
 
-pub async fn on_start_processing(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let FileStartProcessing { uuid,.. } = FileStartProcessing::deserialize(data)?;
+pub async fn on_start_processing(msg: FileStartProcessing) -> anyhow::Result<()> {
+    let FileStartProcessing { uuid, chunk_size, receivers } = msg;
 
     let file = get_pending_file(uuid).await?;
-    let receiver = file.receiver.clone();
-    let receiver_name = uuid_to_name(receiver).await?;
+    if file.chunk_size != chunk_size {
+        return Err(anyhow!("Server started upload of '{}' with chunk size {}, expected {}.", file.filename, chunk_size, file.chunk_size));
+    }
+
+    // Only receivers which accepted the file
+    let mut keys = Vec::new();
+    let mut receiver_names = Vec::new();
+    for receiver in &receivers {
+        keys.push((receiver.clone(), get_pubkey_from_rec(receiver).await?));
+        receiver_names.push(uuid_to_name(receiver.clone()).await?);
+    }
 
     let threads = get_concurrent_threads().await;
     let plural = if threads > 1 { "s" } else { "" };
 
-    println!("{}", format!("{} file '{}' to user '{}' ({} thread{})", "Starting to upload".green(), file.filename.yellow(), receiver_name.yellow(), threads, plural));
+    println!("{}", format!("{} file '{}' to user '{}' ({} thread{})", "Starting to upload".green(), file.filename.yellow(), receiver_names.join(", ").yellow(), threads, plural));
+
+    // Positions are only known to the server, the journal of the sender needs the fingerprints
+    let mut journal_receivers = Vec::new();
+    for (position, (_, key)) in keys.iter().enumerate() {
+        journal_receivers.push(JournalReceiver::new(position, get_fingerprint(key)?));
+    }
+
+    let res = create_journal(&uuid, &file, get_own_fingerprint().await?, journal_receivers).await;
+    if res.is_err() {
+        eprintln!("{}", format!("Could not create transfer journal, the upload can not be resumed: {}", res.unwrap_err()).yellow());
+    }
 
-    let key = get_pubkey_from_rec(&receiver).await?;
     let mut state = FILE_UPLOADS.write().await;
-    let mut uploader = Uploader::new(&uuid, key, &file);
+    let mut uploader = Uploader::new(&uuid, keys, &file);
 
     let res = uploader.start(threads).await;
     if res.is_err() {
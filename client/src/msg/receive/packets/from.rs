@@ -1,24 +1,32 @@
 use colored::Colorize;
-use packets::{communication::{from::FromMsg, key_request::WantSymmKeyMsg}, types::ByteMessage};
-use tokio_tungstenite::tungstenite::Message;
+use packets::communication::from::FromMsg;
 
-use crate::{util::{arcs::get_symm_key, msg::{print_from_msg, send_msg}}, web::user_info::get_user_info};
+use crate::{util::msg::print_from_msg, web::user_info::get_user_info, encryption::{handshake::start_handshake, ratchet::{decrypt_chat, has_ratchet}}};
 
-pub async fn on_from(data: &mut Vec<u8>) -> anyhow::Result<()> {
-    let FromMsg { msg, sender } =  FromMsg::deserialize(data)?;
+pub async fn on_from(msg: FromMsg) -> anyhow::Result<()> {
+    let FromMsg { msg, sender } = msg;
 
-    let key = get_symm_key(&sender).await;
-    if key.is_err() {
-        println!("{}", format!("Could not get symmetric key pair for user '{}'. Sending packet again...", sender.to_string().yellow()).red());
-        let packet = WantSymmKeyMsg { user: sender}.serialize();
-        send_msg(Message::binary(packet)).await?;
+    if !has_ratchet(&sender).await {
+        println!("{}", format!("Could not get symmetric key pair for user '{}'. Starting key exchange again...", sender.to_string().yellow()).red());
+        // The sender still has a session with us, so it has to be replaced
+        start_handshake(&sender, true).await?;
 
         return Ok(());
     }
 
-    let key = key.unwrap();
+    let decrypted = decrypt_chat(&sender, &msg).await;
+    if decrypted.is_err() {
+        let err = decrypted.unwrap_err();
+        eprintln!("{}", format!("Dropped message from '{}': {}", sender.to_string().yellow(), err).red());
+        return Ok(());
+    }
+
+    let decrypted = decrypted.unwrap();
+    if decrypted.is_empty() {
+        // Ratchet heartbeat
+        return Ok(());
+    }
 
-    let decrypted = key.decrypt(&msg)?;
     let msg = String::from_utf8(decrypted);
 
     if msg.is_err() {
@@ -0,0 +1,25 @@
+use colored::Colorize;
+use packets::communication::from_offline::FromOfflineMsg;
+
+use crate::{util::msg::print_from_msg, encryption::offline::open_offline};
+
+pub async fn on_from_offline(msg: FromOfflineMsg) -> anyhow::Result<()> {
+    let FromOfflineMsg { msg, sender_key } = msg;
+
+    let opened = open_offline(&sender_key, &msg).await;
+    if opened.is_err() {
+        let err = opened.unwrap_err();
+        eprintln!("{}", format!("Dropped queued message: {}", err).red());
+        return Ok(());
+    }
+
+    let (decrypted, name) = opened.unwrap();
+    let msg = String::from_utf8(decrypted);
+
+    if msg.is_err() {
+        return Ok(());
+    }
+
+    print_from_msg(&format!("{} {}", name, "(queued)".bright_black()), &msg.unwrap());
+    Ok(())
+}
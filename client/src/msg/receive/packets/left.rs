@@ -0,0 +1,16 @@
+use colored::Colorize;
+use packets::communication::left::UserLeftMsg;
+
+use crate::{util::consts::RECEIVER, encryption::offline::mark_left};
+
+pub async fn on_user_left(msg: UserLeftMsg) -> anyhow::Result<()> {
+    let UserLeftMsg { user } = msg;
+    mark_left(&user).await;
+
+    let receiver = RECEIVER.read().await.clone();
+    if receiver == Some(user) {
+        println!("{}", "Your receiver went offline, messages are queued until it reconnects.".yellow());
+    }
+
+    return Ok(());
+}
@@ -1,6 +1,9 @@
 pub mod from;
+pub mod from_offline;
+pub mod left;
 pub mod uid;
 pub mod file;
 pub mod error;
 pub mod symm_key;
-pub mod want_symm_key;
\ No newline at end of file
+pub mod want_symm_key;
+pub mod room;
@@ -0,0 +1,32 @@
+use colored::Colorize;
+use log::trace;
+use packets::room::key::RoomKeyMsg;
+
+use crate::{room::tools::store_key, util::consts::ROOMS};
+
+pub async fn on_room_key(msg: RoomKeyMsg) -> anyhow::Result<()> {
+    let mut state = ROOMS.write().await;
+    let room_state = state.entry(msg.room.clone()).or_default();
+
+    // Packets are handled concurrently, so the key may overtake the roster of its epoch
+    if !room_state.is_joined() || msg.epoch > room_state.epoch {
+        trace!("Key for epoch {} of room '{}' arrived before its roster", msg.epoch, msg.room);
+        room_state.add_pending_key(msg);
+        return Ok(());
+    }
+
+    if msg.epoch < room_state.epoch {
+        trace!("Ignoring outdated key for epoch {} of room '{}'", msg.epoch, msg.room);
+        return Ok(());
+    }
+
+    drop(state);
+
+    let room = msg.room.clone();
+    let res = store_key(msg).await;
+    if res.is_err() {
+        eprintln!("{}", format!("Dropped key for room '{}': {}", room.yellow(), res.unwrap_err()).red());
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,16 @@
+use colored::Colorize;
+use packets::room::list::RoomListMsg;
+
+pub async fn on_room_list(msg: RoomListMsg) -> anyhow::Result<()> {
+    if msg.rooms.is_empty() {
+        println!("{}", "There are no rooms yet. Use /room create <name> to create one.".yellow());
+        return Ok(());
+    }
+
+    println!("{}", "Rooms:".green());
+    for room in msg.rooms {
+        println!("{}", format!("  #{} ({} member(s))", room.name, room.members).bright_black());
+    }
+
+    return Ok(());
+}
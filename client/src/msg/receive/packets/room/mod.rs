@@ -0,0 +1,4 @@
+pub mod roster;
+pub mod key;
+pub mod msg;
+pub mod list;
\ No newline at end of file
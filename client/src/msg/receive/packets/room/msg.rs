@@ -0,0 +1,23 @@
+use colored::Colorize;
+use packets::room::msg::RoomMsg;
+
+use crate::{room::tools::open_msg, util::{msg::print_from_msg, tools::uuid_to_name}};
+
+pub async fn on_room_msg(msg: RoomMsg) -> anyhow::Result<()> {
+    let decrypted = open_msg(&msg).await;
+    if decrypted.is_err() {
+        let err = decrypted.unwrap_err();
+        eprintln!("{}", format!("Dropped message in room '{}': {}", msg.room.yellow(), err).red());
+        return Ok(());
+    }
+
+    let text = String::from_utf8(decrypted.unwrap());
+    if text.is_err() {
+        return Ok(());
+    }
+
+    let display_name = uuid_to_name(msg.user).await?;
+    print_from_msg(&format!("{} {}", format!("#{}", msg.room).magenta(), display_name), &text.unwrap());
+
+    return Ok(());
+}
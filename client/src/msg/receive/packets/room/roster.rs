@@ -0,0 +1,57 @@
+use colored::Colorize;
+use log::trace;
+use packets::room::roster::RoomRosterMsg;
+
+use crate::{
+    room::tools::{distribute_key, set_current_room, store_key},
+    util::{arcs::get_curr_id, consts::ROOMS, tools::uuid_to_name},
+};
+
+pub async fn on_room_roster(msg: RoomRosterMsg) -> anyhow::Result<()> {
+    let RoomRosterMsg { room, epoch, members } = msg;
+    let curr_id = get_curr_id().await?;
+
+    if !members.contains(&curr_id) {
+        trace!("Received roster of room '{}' without us in it", room);
+        return Ok(());
+    }
+
+    let mut state = ROOMS.write().await;
+    let room_state = state.entry(room.clone()).or_default();
+    if room_state.is_joined() && epoch <= room_state.epoch {
+        trace!("Ignoring outdated roster of room '{}' (epoch {})", room, epoch);
+        return Ok(());
+    }
+
+    let joined_now = !room_state.is_joined();
+    let old_members = room_state.members.clone();
+
+    room_state.epoch = epoch;
+    room_state.members = members.clone();
+
+    let pending = room_state.take_pending_key(epoch);
+    drop(state);
+
+    if joined_now {
+        set_current_room(Some(room.clone())).await;
+        println!("{}", format!("Joined room '{}' with {} member(s). Messages are now sent to the room, use /rec to go back.", room.yellow(), members.len()).green());
+    } else {
+        for member in members.iter().filter(|e| !old_members.contains(e)) {
+            println!("{}", format!("{} joined room '{}'.", uuid_to_name(member.clone()).await?.blue(), room.yellow()).bright_black());
+        }
+
+        for member in old_members.iter().filter(|e| !members.contains(e)) {
+            println!("{}", format!("{} left room '{}'.", uuid_to_name(member.clone()).await?.blue(), room.yellow()).bright_black());
+        }
+    }
+
+    if members[0] == curr_id {
+        return distribute_key(&room, epoch, &members).await;
+    }
+
+    if pending.is_some() {
+        return store_key(pending.unwrap()).await;
+    }
+
+    return Ok(());
+}
@@ -1,7 +1,7 @@
 use std::sync::atomic::Ordering;
 
 use colored::Colorize;
-use packets::{initialize::uid_reply::UidReplyMsg, types::ByteMessage};
+use packets::initialize::uid_reply::UidReplyMsg;
 
 use crate::{
     input::receiver::select_receiver,
@@ -9,9 +9,9 @@ use crate::{
 };
 
 pub async fn on_uid(
-    data: &mut Vec<u8>
+    msg: UidReplyMsg
 ) -> anyhow::Result<()> {
-    let UidReplyMsg { uuid } = UidReplyMsg::deserialize(data)?;
+    let UidReplyMsg { uuid } = msg;
 
     println!("{}", format!("Your id is: '{}'", uuid.to_string().cyan()).bright_black());
     let mut state = CURR_ID.write().await;
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use colored::Colorize;
+use openssl::{pkey::Private, rsa::Rsa};
+use packets::{initialize::pubkey::PubkeyMsg, types::ByteMessage, encryption::fingerprint::{format_fingerprint, get_fingerprint}};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    encryption::keystore::{export_identity, get_identity_path, import_identity, rotate_identity},
+    util::{consts::KEYPAIR, msg::send_msg, tools::wait_confirm, arcs::get_curr_keypair},
+};
+
+pub async fn on_identity(line: &str) -> anyhow::Result<()> {
+    let args: Vec<&str> = line.split(" ").skip(1).collect();
+    let action = args.get(0).unwrap_or(&"show").to_owned();
+    let path = args.iter().skip(1).map(|e| e.to_owned()).collect::<Vec<&str>>().join(" ");
+
+    if action == "show" {
+        let path = get_identity_path().await?;
+        let pubkey = PubkeyMsg::from_private(get_curr_keypair().await?)?.pubkey;
+        let fingerprint = format_fingerprint(&get_fingerprint(&pubkey)?);
+
+        println!("{}", format!("Your identity is stored at {}", path.to_string_lossy().yellow()).bright_black());
+        println!("{}", format!("Fingerprint: {}", fingerprint).bright_black());
+        return Ok(());
+    }
+
+    if action == "export" {
+        if path.is_empty() {
+            println!("{}", "Usage: /identity export <path>".red());
+            return Ok(());
+        }
+
+        export_identity(&PathBuf::from(&path)).await?;
+        println!("{}", format!("Identity exported to {}.", path.yellow()).green());
+        return Ok(());
+    }
+
+    if action == "import" {
+        if path.is_empty() {
+            println!("{}", "Usage: /identity import <path>".red());
+            return Ok(());
+        }
+
+        let passphrase = inquire::Password::new("Passphrase of the identity to import:").prompt()?;
+
+        let keypair = import_identity(&PathBuf::from(&path), &passphrase).await?;
+        update_keypair(keypair).await?;
+
+        println!("{}", format!("Identity imported from {}.", path.yellow()).green());
+        return Ok(());
+    }
+
+    if action == "rotate" {
+        println!("{}", format!("Rotating your identity makes running transfers fail and your peers will see a new key. Continue? ({}/{})", "y".green(), "n".red()).yellow());
+        if !wait_confirm().await? {
+            return Ok(());
+        }
+
+        let keypair = rotate_identity().await?;
+        update_keypair(keypair).await?;
+
+        println!("{}", "Identity has been rotated.".green());
+        return Ok(());
+    }
+
+    println!("{}", "Usage: /identity [show|export <path>|import <path>|rotate]".red());
+    return Ok(());
+}
+
+async fn update_keypair(keypair: Rsa<Private>) -> anyhow::Result<()> {
+    let mut state = KEYPAIR.write().await;
+    *state = Some(keypair.clone());
+
+    drop(state);
+
+    let msg = PubkeyMsg::from_private(keypair)?.serialize();
+    send_msg(Message::binary(msg)).await?;
+
+    return Ok(());
+}
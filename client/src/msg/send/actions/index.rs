@@ -1,6 +1,6 @@
 use colored::Colorize;
 
-use super::{name::on_name, receiver::on_receiver, send::on_send};
+use super::{name::on_name, receiver::on_receiver, send::on_send, identity::on_identity, verify::on_verify, room::on_room, resume::on_resume, transfers::{on_transfers, on_cancel}, limit::on_limit};
 
 pub fn is_command(line: &str, aliases: Vec<&str>) -> bool{
     return aliases.iter().any(|e|{
@@ -13,9 +13,16 @@ pub fn is_command(line: &str, aliases: Vec<&str>) -> bool{
 pub fn get_help_str() -> String {
     let rec_cmd = format!("{}: {}", "/receiver".bold().bright_blue(), "Change the user you want to write a message to / send a file to. (alias: /r, /rec)".bright_black());
     let name_cmd = format!("{} {}: {}", "/name".bold().bright_blue(), "<name>".bright_blue(), "Changes your display name to the given name. (alias /n)".bright_black());
-    let send_cmd = format!("{} {}: {}", "/send".bold().bright_blue(), "<file>".bright_blue(), "Send a file to the other user. (alias /s)".bright_black());
+    let send_cmd = format!("{} {}: {}", "/send".bold().bright_blue(), "[--to <a,b,...>] <file|dir|glob...>".bright_blue(), "Send files or directories to the other user, a file can be sent to multiple users at once. (alias /s)".bright_black());
+    let verify_cmd = format!("{} {}: {}", "/verify".bold().bright_blue(), "[forget|trust <name>]".bright_blue(), "Show the safety number of you and your receiver, forget a known key or pin the key of your receiver. (alias /v)".bright_black());
+    let identity_cmd = format!("{} {}: {}", "/identity".bold().bright_blue(), "[show|export <path>|import <path>|rotate]".bright_blue(), "Manage your persistent identity. (alias /id)".bright_black());
+    let resume_cmd = format!("{} {}: {}", "/resume".bold().bright_blue(), "[<uuid>|all|discard <uuid>]".bright_blue(), "List or continue file transfers which were interrupted by a disconnect.".bright_black());
+    let transfers_cmd = format!("{}: {}", "/transfers".bold().bright_blue(), "List queued, active and finished file transfers. (alias /t)".bright_black());
+    let cancel_cmd = format!("{} {}: {}", "/cancel".bold().bright_blue(), "<id>".bright_blue(), "Cancel the file transfer with the given id.".bright_black());
+    let limit_cmd = format!("{} {}: {}", "/limit".bold().bright_blue(), "[up|down|all <rate>]".bright_blue(), "Show or change the bandwidth limit of all transfers (e.g. 500K, 2M or off).".bright_black());
+    let room_cmd = format!("{} {}: {}", "/room".bold().bright_blue(), "[create <name>|join <name>|leave [name]|members [name]|list]".bright_blue(), "Chat with multiple users in a room. Use /rec to go back to direct messages.".bright_black());
 
-    return format!("--------------------\n{}\n{}\n{}\n-----------------", rec_cmd, name_cmd, send_cmd);
+    return format!("--------------------\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n-----------------", rec_cmd, name_cmd, send_cmd, transfers_cmd, cancel_cmd, limit_cmd, resume_cmd, identity_cmd, verify_cmd, room_cmd);
 }
 
 
@@ -26,6 +33,20 @@ pub async fn on_command(line: &str) -> anyhow::Result<()> {
         return on_name(line).await;
     } else if is_command(line, vec!["s", "send"]) {
         return on_send(line).await;
+    } else if is_command(line, vec!["id", "identity"]) {
+        return on_identity(line).await;
+    } else if is_command(line, vec!["v", "verify"]) {
+        return on_verify(line).await;
+    } else if is_command(line, vec!["resume"]) {
+        return on_resume(line).await;
+    } else if is_command(line, vec!["t", "transfers"]) {
+        return on_transfers(line).await;
+    } else if is_command(line, vec!["cancel"]) {
+        return on_cancel(line).await;
+    } else if is_command(line, vec!["limit"]) {
+        return on_limit(line).await;
+    } else if is_command(line, vec!["room"]) {
+        return on_room(line).await;
     } else if is_command(line, vec!["h", "help"]) {
         println!("{}", get_help_str());
     } else {
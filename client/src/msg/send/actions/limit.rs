@@ -0,0 +1,51 @@
+use colored::Colorize;
+use indicatif::HumanBytes;
+use packets::util::rate::parse_rate;
+
+use crate::{
+    util::{consts::{DOWNLOAD_LIMIT, UPLOAD_LIMIT}, types::RateLimit},
+    web::throttle::{get_rate, set_rate},
+};
+
+fn rate_to_str(rate: u64) -> String {
+    if rate == 0 {
+        return "unlimited".to_string();
+    }
+
+    return format!("{}/s", HumanBytes(rate));
+}
+
+pub async fn on_limit(line: &str) -> anyhow::Result<()> {
+    let args: Vec<&str> = line.split(" ").skip(1).filter(|e| !e.is_empty()).collect();
+    if args.is_empty() {
+        println!("{} {}", "Upload limit:".bright_blue(), rate_to_str(get_rate(&UPLOAD_LIMIT).await).yellow());
+        println!("{} {}", "Download limit:".bright_blue(), rate_to_str(get_rate(&DOWNLOAD_LIMIT).await).yellow());
+        return Ok(());
+    }
+
+    let limits: Vec<(&str, &RateLimit)> = match args[0] {
+        "up" | "upload" => vec![("Upload", &*UPLOAD_LIMIT)],
+        "down" | "download" => vec![("Download", &*DOWNLOAD_LIMIT)],
+        "all" => vec![("Upload", &*UPLOAD_LIMIT), ("Download", &*DOWNLOAD_LIMIT)],
+        _ => Vec::new(),
+    };
+
+    if limits.is_empty() || args.len() != 2 {
+        println!("{}", "Usage: /limit [up|down|all <rate>], e.g. /limit up 500K or /limit all off".red());
+        return Ok(());
+    }
+
+    let rate = parse_rate(args[1]);
+    if rate.is_err() {
+        println!("{}", rate.unwrap_err().to_string().red());
+        return Ok(());
+    }
+
+    let rate = rate.unwrap();
+    for (name, limit) in limits {
+        set_rate(limit, rate).await;
+        println!("{}", format!("{} limit set to {}.", name, rate_to_str(rate)).green());
+    }
+
+    return Ok(());
+}
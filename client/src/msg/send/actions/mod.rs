@@ -1,4 +1,10 @@
 pub mod receiver;
 pub mod name;
 pub mod send;
-pub mod index;
\ No newline at end of file
+pub mod index;
+pub mod identity;
+pub mod verify;
+pub mod room;
+pub mod resume;
+pub mod transfers;
+pub mod limit;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::{util::consts::RECEIVER, input::receiver::select_receiver};
+use crate::{util::consts::RECEIVER, input::receiver::select_receiver, room::tools::set_current_room};
 
 pub async fn on_receiver(_line: &str) -> anyhow::Result<()> {
     let new_rec = select_receiver().await;
@@ -12,5 +12,7 @@ pub async fn on_receiver(_line: &str) -> anyhow::Result<()> {
     *state = Some(new_rec.clone());
 
     drop(state);
+
+    set_current_room(None).await;
     return Ok(());
 }
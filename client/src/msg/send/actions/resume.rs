@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use colored::Colorize;
+use packets::{file::processing::{abort::ChunkAbortMsg, resume::FileResumeMsg}, types::ByteMessage};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::{
+    file::journal::{get_journal, get_journals, get_own_fingerprint, remove_journal},
+    util::msg::send_msg,
+};
+
+pub async fn on_resume(line: &str) -> anyhow::Result<()> {
+    let args: Vec<&str> = line.split(" ").skip(1).collect();
+    let journals = get_journals().await?;
+
+    if args.is_empty() {
+        if journals.is_empty() {
+            println!("{}", "There are no interrupted transfers.".yellow());
+            return Ok(());
+        }
+
+        let own_fingerprint = get_own_fingerprint().await?;
+
+        println!("{}", "Interrupted transfers:".green());
+        for journal in journals {
+            let direction = if journal.sender == own_fingerprint { "upload" } else { "download" };
+            println!("{}", format!("  {} {} '{}' ({}/{} chunks)", journal.uuid, direction, journal.filename, journal.get_finished().len(), journal.get_max_chunks()).bright_black());
+        }
+
+        println!("{}", "Use /resume <uuid|all> to continue them or /resume discard <uuid> to cancel one.".bright_black());
+        return Ok(());
+    }
+
+    if args[0] == "discard" {
+        let uuid = args.get(1).and_then(|e| Uuid::from_str(e).ok());
+        if uuid.is_none() || get_journal(&uuid.unwrap()).await?.is_none() {
+            println!("{}", "Usage: /resume discard <uuid of an interrupted transfer>".red());
+            return Ok(());
+        }
+
+        let uuid = uuid.unwrap();
+        remove_journal(&uuid).await?;
+        send_msg(Message::binary(ChunkAbortMsg { uuid }.serialize())).await?;
+
+        println!("{}", format!("Discarded transfer {}.", uuid).green());
+        return Ok(());
+    }
+
+    let to_resume: Vec<_> = if args[0] == "all" {
+        journals
+    } else {
+        let uuid = Uuid::from_str(args[0]);
+        journals.into_iter().filter(|e| uuid.as_ref().ok() == Some(&e.uuid)).collect()
+    };
+
+    if to_resume.is_empty() {
+        println!("{}", "Usage: /resume [<uuid>|all|discard <uuid>]".red());
+        return Ok(());
+    }
+
+    for journal in to_resume {
+        send_msg(Message::binary(FileResumeMsg {
+            uuid: journal.uuid,
+            sender: Uuid::nil(),
+            receivers: Vec::new(),
+            done: journal.get_finished().into_iter().collect(),
+            ready: Vec::new(),
+        }.serialize())).await?;
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,88 @@
+use colored::Colorize;
+use packets::{
+    initialize::hello::FEATURE_ROOMS,
+    room::{action::{RoomAction, RoomActionMsg}, list::RoomListMsg, tools::is_valid_room_name},
+    types::ByteMessage,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    room::tools::{get_current_room, is_in_room, set_current_room},
+    util::{arcs::has_feature, consts::ROOMS, msg::send_msg, tools::uuid_to_name},
+};
+
+pub async fn on_room(line: &str) -> anyhow::Result<()> {
+    let args: Vec<&str> = line.split(" ").skip(1).collect();
+    let action = args.get(0).unwrap_or(&"").to_owned();
+    let name = args.get(1).map(|e| e.to_string()).or(get_current_room().await);
+
+    if !has_feature(FEATURE_ROOMS).await {
+        println!("{}", "The server does not support rooms.".red());
+        return Ok(());
+    }
+
+    if action == "list" {
+        send_msg(Message::binary(RoomListMsg { rooms: Vec::new() }.serialize())).await?;
+        return Ok(());
+    }
+
+    if name.is_none() || !["create", "join", "leave", "members"].contains(&action) {
+        println!("{}", "Usage: /room [create <name>|join <name>|leave [name]|members [name]|list]".red());
+        return Ok(());
+    }
+
+    let name = name.unwrap();
+    if !is_valid_room_name(&name) {
+        println!("{}", "Invalid room name. Only letters, digits, '-' and '_' are allowed.".red());
+        return Ok(());
+    }
+
+    if action == "members" {
+        let state = ROOMS.read().await;
+        let members = state.get(&name).map(|e| e.members.clone()).unwrap_or(Vec::new());
+
+        drop(state);
+        if members.is_empty() {
+            println!("{}", format!("You are not a member of room '{}'.", name.yellow()).red());
+            return Ok(());
+        }
+
+        println!("{}", format!("Members of room '{}':", name.yellow()).green());
+        for member in members {
+            println!("{}", format!("  {}", uuid_to_name(member).await?).bright_black());
+        }
+
+        return Ok(());
+    }
+
+    if action == "leave" {
+        if !is_in_room(&name).await {
+            println!("{}", format!("You are not a member of room '{}'.", name.yellow()).red());
+            return Ok(());
+        }
+
+        let mut state = ROOMS.write().await;
+        state.remove(&name);
+
+        drop(state);
+        if get_current_room().await == Some(name.clone()) {
+            set_current_room(None).await;
+        }
+
+        send_msg(Message::binary(RoomActionMsg { action: RoomAction::Leave, room: name.clone() }.serialize())).await?;
+        println!("{}", format!("Left room '{}'.", name.yellow()).green());
+        return Ok(());
+    }
+
+    if is_in_room(&name).await {
+        // Already a member, only switch to the room
+        set_current_room(Some(name.clone())).await;
+        println!("{}", format!("Messages are now sent to room '{}'.", name.yellow()).green());
+        return Ok(());
+    }
+
+    let action = if action == "create" { RoomAction::Create } else { RoomAction::Join };
+    send_msg(Message::binary(RoomActionMsg { action, room: name }.serialize())).await?;
+
+    return Ok(());
+}
@@ -1,41 +1,119 @@
-use std::{path::Path, fs::File};
+use std::{path::{Path, PathBuf}, fs::File};
 
 use log::trace;
 use colored::Colorize;
-use packets::{file::{question::index::FileQuestionMsg, types::FileInfo}, types::ByteMessage};
+use indicatif::HumanBytes;
+use packets::{consts::MAX_RECEIVERS, file::{question::index::FileQuestionMsg, types::FileInfo, manifest::index::{FileManifestMsg, ManifestEntry}}, types::ByteMessage, initialize::hello::FEATURE_MANIFEST};
 use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
-use crate::{util::{tools::uuid_to_name, arcs::{get_receiver, get_curr_id}, msg::{send_msg, print_from_msg}, consts::PENDING_FILES}, file::tools::get_hash_progress};
+use crate::{util::{tools::{uuid_to_name, name_to_uuid}, arcs::{get_receiver, get_curr_id, has_feature, get_transfer_chunk_size}, msg::{send_msg, print_from_msg}, consts::PENDING_FILES}, file::{tools::{get_hash_progress, get_file_compression}, manifest::{resolve_send_paths, collect_files, get_permissions}}, encryption::{handshake::{is_handshake_pending, start_handshake}, ratchet::{authenticate_control, has_ratchet}}};
 
 pub async fn on_send(line: &str) -> anyhow::Result<()> {
-    let filename = line.split(" ");
+    let args = line.split(" ");
+    let args = Vec::from_iter(args.skip(1)).join(" ");
 
-    let given_path = Vec::from_iter(filename.skip(1)).join(" ");
-    let given_path = Path::new(&given_path);
+    // `--to a,b,c` sends to these users instead of the current receiver
+    let mut args = args.as_str();
+    let mut names = None;
+    if args.starts_with("--to ") {
+        let rest = args["--to ".len()..].trim_start();
+        let (list, paths) = rest.split_once(" ").unwrap_or((rest, ""));
 
-    if !given_path.is_file() {
-        let msg = format!("File '{}' does not exist.", given_path.to_str().unwrap_or("()"));
-        println!("{}", msg.red());
+        names = Some(list);
+        args = paths;
+    }
+
+    let paths = resolve_send_paths(args);
+    if paths.is_err() {
+        let err = paths.unwrap_err();
+        println!("{}", err.to_string().red());
 
         return Ok(());
     }
 
-    let file = File::open(&given_path)?;
-    let size = file.metadata()?.len();
-
+    let paths = paths.unwrap();
 
-    let receiver = get_receiver().await?;
-    let receiver_name = uuid_to_name(receiver).await?;
+    let receivers = if names.is_some() { resolve_receivers(names.unwrap()).await? } else { Some(vec![get_receiver().await?]) };
+    if receivers.is_none() {
+        return Ok(());
+    }
 
+    let receivers = receivers.unwrap();
     let curr_id = get_curr_id().await?;
-    let uuid = Uuid::new_v4();
 
-    if curr_id == receiver {
+    if receivers.contains(&curr_id) {
         eprintln!("{}", format!("You can not send the file to yourself.").on_red());
         return Ok(())
     }
 
+    let mut missing = Vec::new();
+    for receiver in &receivers {
+        if !has_ratchet(receiver).await {
+            missing.push(uuid_to_name(receiver.clone()).await?);
+            if !is_handshake_pending(receiver).await {
+                start_handshake(receiver, false).await?;
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        println!("{}", format!("Key exchange with {} has not finished yet. Please try again in a moment.", missing.join(", ")).yellow());
+        return Ok(());
+    }
+
+    if paths.len() == 1 && paths[0].is_file() {
+        return send_file(&paths[0], receivers, curr_id).await;
+    }
+
+    if receivers.len() > 1 {
+        println!("{}", "Directories and multiple files can only be sent to a single user.".red());
+        return Ok(());
+    }
+
+    if !has_feature(FEATURE_MANIFEST).await {
+        println!("{}", "The server does not support sending directories or multiple files.".red());
+        return Ok(());
+    }
+
+    return send_manifest(&paths, receivers[0], curr_id).await;
+}
+
+// Ids of the comma separated names or ids, None if one of them is not online
+async fn resolve_receivers(names: &str) -> anyhow::Result<Option<Vec<Uuid>>> {
+    let mut receivers = Vec::new();
+    for name in names.split(",").map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let receiver = name_to_uuid(name).await?;
+        if receiver.is_none() {
+            println!("{}", format!("Could not find user '{}'.", name.yellow()).red());
+            return Ok(None);
+        }
+
+        let receiver = receiver.unwrap();
+        if !receivers.contains(&receiver) {
+            receivers.push(receiver);
+        }
+    }
+
+    if receivers.is_empty() || receivers.len() > MAX_RECEIVERS {
+        println!("{}", format!("Usage: /send --to <a,b,...> <file> with up to {} users", MAX_RECEIVERS).red());
+        return Ok(None);
+    }
+
+    return Ok(Some(receivers));
+}
+
+async fn send_file(given_path: &Path, receivers: Vec<Uuid>, curr_id: Uuid) -> anyhow::Result<()> {
+    let file = File::open(&given_path)?;
+    let size = file.metadata()?.len();
+
+    let mut receiver_names = Vec::new();
+    for receiver in &receivers {
+        receiver_names.push(uuid_to_name(receiver.clone()).await?);
+    }
+
+    let uuid = Uuid::new_v4();
+
     let filename = given_path.file_name();
     if filename.is_none() {
         eprintln!("Could not get filename of path {:?}", given_path.as_os_str());
@@ -47,34 +125,138 @@ pub async fn on_send(line: &str) -> anyhow::Result<()> {
 
     println!("{}", format!("Calculating hash for file...").yellow());
 
-    let hash = get_hash_progress(given_path.to_str().unwrap().to_owned()).await?;
-    let to_send = FileQuestionMsg {
+    let chunk_size = get_transfer_chunk_size().await;
+    let (hash, chunk_hashes) = get_hash_progress(given_path.to_str().unwrap().to_owned(), chunk_size).await?;
+    let compression = get_file_compression(given_path, &filename).await?;
+
+    let mut question = FileQuestionMsg {
         filename: filename.clone(),
         sender: curr_id,
-        receiver,
+        receiver: receivers[0],
+        receivers: receivers.clone(),
         uuid,
         size,
-        hash: hash.clone()
-    }.serialize();
-
-    send_msg(Message::Binary(to_send)).await?;
-    print_from_msg(&"you".on_bright_red(), &format!("Sending file request to {}", receiver_name.yellow()));
+        chunk_size,
+        compression,
+        hash: hash.clone(),
+        chunk_hashes: chunk_hashes.clone(),
+        seq: 0,
+        mac: Vec::new()
+    };
 
     let info = FileInfo {
         filename: filename.clone(),
         sender: curr_id,
-        receiver,
+        receiver: receivers[0],
         size,
+        chunk_size,
+        compression,
         hash,
+        chunk_hashes,
         path: Some(given_path.to_path_buf())
     };
 
     trace!("Storing file info {:#?}", info);
 
+    // Has to be stored before the receivers can accept
     let mut state = PENDING_FILES.write().await;
     state.insert(uuid, info);
 
     drop(state);
 
+    // Every receiver gets its own question as the mac is bound to its session
+    for receiver in &receivers {
+        question.receiver = receiver.clone();
+
+        let (seq, mac) = authenticate_control(receiver, &question.get_auth_data()).await?;
+        question.seq = seq;
+        question.mac = mac;
+
+        send_msg(Message::Binary(question.serialize())).await?;
+    }
+
+    print_from_msg(&"you".on_bright_red(), &format!("Sending file request to {}", receiver_names.join(", ").yellow()));
+    return Ok(());
+}
+
+async fn send_manifest(paths: &Vec<PathBuf>, receiver: Uuid, curr_id: Uuid) -> anyhow::Result<()> {
+    let files = collect_files(paths);
+    if files.is_err() {
+        let err = files.unwrap_err();
+        println!("{}", err.to_string().red());
+
+        return Ok(());
+    }
+
+    let files = files.unwrap();
+    if files.is_empty() {
+        println!("{}", "There are no files to send.".red());
+        return Ok(());
+    }
+
+    let receiver_name = uuid_to_name(receiver).await?;
+    let chunk_size = get_transfer_chunk_size().await;
+
+    let mut entries = Vec::new();
+    let mut infos = Vec::new();
+    for (path, relative) in files {
+        let meta = std::fs::metadata(&path)?;
+
+        println!("{}", format!("Calculating hash for '{}'...", relative).yellow());
+        let (hash, chunk_hashes) = get_hash_progress(path.to_str().unwrap().to_owned(), chunk_size).await?;
+        let compression = get_file_compression(&path, &relative).await?;
+
+        let uuid = Uuid::new_v4();
+        entries.push(ManifestEntry {
+            uuid,
+            path: relative.clone(),
+            size: meta.len(),
+            permissions: get_permissions(&meta),
+            hash: hash.clone(),
+            chunk_hashes: chunk_hashes.clone(),
+            compression
+        });
+
+        infos.push((uuid, FileInfo {
+            filename: relative,
+            sender: curr_id,
+            receiver,
+            size: meta.len(),
+            chunk_size,
+            compression,
+            hash,
+            chunk_hashes,
+            path: Some(path)
+        }));
+    }
+
+    let mut manifest = FileManifestMsg {
+        uuid: Uuid::new_v4(),
+        sender: curr_id,
+        receiver,
+        entries,
+        chunk_size,
+        seq: 0,
+        mac: Vec::new()
+    };
+
+    let (seq, mac) = authenticate_control(&receiver, &manifest.get_auth_data()).await?;
+    manifest.seq = seq;
+    manifest.mac = mac;
+
+    // Has to be stored before the receiver can accept
+    let mut state = PENDING_FILES.write().await;
+    for (uuid, info) in infos {
+        state.insert(uuid, info);
+    }
+
+    drop(state);
+
+    let count = manifest.entries.len();
+    let size = manifest.get_total_size();
+
+    send_msg(Message::Binary(manifest.serialize())).await?;
+    print_from_msg(&"you".on_bright_red(), &format!("Sending {} files ({}) to {}", count, HumanBytes(size), receiver_name.yellow()));
+
     return Ok(());
 }
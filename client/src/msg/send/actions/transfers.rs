@@ -0,0 +1,64 @@
+use colored::Colorize;
+use indicatif::HumanBytes;
+use packets::{file::processing::abort::ChunkAbortMsg, types::ByteMessage};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    transfer::{state::{Direction, TransferState}, tools::{find_transfer, get_transfers, set_transfer_state}},
+    util::{msg::send_msg, tools::uuid_to_name},
+};
+
+pub async fn on_transfers(_line: &str) -> anyhow::Result<()> {
+    let transfers = get_transfers().await;
+    if transfers.is_empty() {
+        println!("{}", "There are no transfers.".yellow());
+        return Ok(());
+    }
+
+    for transfer in transfers {
+        let (arrow, prep) = if transfer.direction == Direction::Upload { ("↑", "to") } else { ("↓", "from") };
+        let peer = uuid_to_name(transfer.peer).await.unwrap_or(transfer.peer.to_string());
+
+        let compression = transfer.get_compression_info().map(|e| format!(" ({})", e)).unwrap_or_default();
+
+        println!(
+            "{} {} {:>9} {:>5.1}% {:>10} '{}' {} {}{}",
+            transfer.get_short_id().bright_blue(),
+            arrow,
+            transfer.state.to_colored(),
+            transfer.get_progress() * 100 as f32,
+            HumanBytes(transfer.size).to_string(),
+            transfer.filename.yellow(),
+            prep,
+            peer.bright_blue(),
+            compression.bright_black()
+        );
+    }
+
+    return Ok(());
+}
+
+pub async fn on_cancel(line: &str) -> anyhow::Result<()> {
+    let id = line.split(" ").nth(1).unwrap_or("");
+
+    let transfer = find_transfer(id).await;
+    if transfer.is_err() {
+        println!("{}", transfer.unwrap_err().to_string().red());
+        println!("{}", "Usage: /cancel <id>, see /transfers for ids".red());
+        return Ok(());
+    }
+
+    let transfer = transfer.unwrap();
+    if transfer.state.is_done() {
+        println!("{}", format!("Transfer of '{}' is already {}.", transfer.filename, transfer.state.to_colored()).yellow());
+        return Ok(());
+    }
+
+    // The server forwards the abort to both sides, which stops the workers.
+    // Other receivers keep the transfer running, so it is only marked as cancelled here.
+    send_msg(Message::binary(ChunkAbortMsg { uuid: transfer.uuid }.serialize())).await?;
+    set_transfer_state(&transfer.uuid, TransferState::Cancelled).await;
+    println!("{}", format!("Cancelling transfer of '{}'...", transfer.filename.yellow()).green());
+
+    return Ok(());
+}
@@ -0,0 +1,93 @@
+use colored::Colorize;
+use packets::{encryption::fingerprint::{format_fingerprint, get_fingerprint, get_safety_number}, initialize::pubkey::PubkeyMsg};
+
+use crate::{
+    encryption::known_peers::{get_known_fingerprint, get_known_name, set_known_fingerprint},
+    util::{arcs::{get_curr_keypair, get_receiver}, tools::uuid_to_name},
+    web::user_info::fetch_user_info,
+};
+
+pub async fn on_verify(line: &str) -> anyhow::Result<()> {
+    let args: Vec<&str> = line.split(" ").skip(1).collect();
+
+    if args.get(0) == Some(&"forget") {
+        let name = args.iter().skip(1).map(|e| e.to_owned()).collect::<Vec<&str>>().join(" ");
+        if name.is_empty() {
+            println!("{}", "Usage: /verify forget <name>".red());
+            return Ok(());
+        }
+
+        if get_known_fingerprint(&name).await?.is_none() {
+            println!("{}", format!("No key of '{}' is known.", name.yellow()).red());
+            return Ok(());
+        }
+
+        set_known_fingerprint(&name, None).await?;
+        println!("{}", format!("Forgot key of '{}'. The next key will be trusted on first use.", name.yellow()).green());
+        return Ok(());
+    }
+
+    if args.get(0) == Some(&"trust") {
+        let name = args.iter().skip(1).map(|e| e.to_owned()).collect::<Vec<&str>>().join(" ");
+        if name.is_empty() {
+            println!("{}", "Usage: /verify trust <name>".red());
+            return Ok(());
+        }
+
+        return on_trust(&name).await;
+    }
+
+    let keypair = get_curr_keypair().await?;
+    let own_key = PubkeyMsg::from_private(keypair)?.pubkey;
+
+    let receiver = get_receiver().await?;
+    let receiver_name = uuid_to_name(receiver).await?;
+
+    // Not verified, this is how the user gets to see a key which has not been pinned yet
+    let info = fetch_user_info(&receiver).await?;
+    if info.public_key.is_none() {
+        println!("{}", format!("'{}' does not have a public key yet.", receiver_name.yellow()).red());
+        return Ok(());
+    }
+
+    let peer_key = info.public_key.unwrap();
+
+    let own_fingerprint = format_fingerprint(&get_fingerprint(&own_key)?);
+    let peer_fingerprint = format_fingerprint(&get_fingerprint(&peer_key)?);
+    let safety_number = get_safety_number(&own_key, &peer_key)?;
+
+    println!("{}", format!("Your fingerprint:      {}", own_fingerprint).bright_black());
+    println!("{}", format!("{}'s fingerprint: {}", receiver_name, peer_fingerprint).bright_black());
+    println!("{} {}", "Safety number:".green(), safety_number.bold());
+    println!("{}", format!("Compare the safety number with {} over another channel (phone, in person).", receiver_name.yellow()).bright_black());
+
+    let known_name = get_known_name(&get_fingerprint(&peer_key)?).await?;
+    if known_name.is_none() {
+        println!("{}", "This key is not pinned yet. Use /verify trust <name> once the safety number matches.".yellow());
+    }
+
+    return Ok(());
+}
+
+// Pins the key of the current receiver, the only way to trust a peer without a name
+async fn on_trust(name: &str) -> anyhow::Result<()> {
+    let receiver = get_receiver().await?;
+
+    let info = fetch_user_info(&receiver).await?;
+    if info.public_key.is_none() {
+        println!("{}", format!("'{}' does not have a public key yet.", receiver).red());
+        return Ok(());
+    }
+
+    let fingerprint = get_fingerprint(&info.public_key.unwrap())?;
+    let known = get_known_fingerprint(name).await?;
+    if known.is_some() && known.unwrap() != fingerprint {
+        println!("{}", format!("Another key is pinned for '{}'. Run /verify forget {} first.", name.yellow(), name).red());
+        return Ok(());
+    }
+
+    set_known_fingerprint(name, Some(fingerprint.clone())).await?;
+    println!("{}", format!("Pinned key {} as '{}'.", format_fingerprint(&fingerprint), name.yellow()).green());
+
+    return Ok(());
+}
@@ -7,11 +7,15 @@ use log::trace;
 use packets::communication::to::ToMsg;
 use packets::initialize::pubkey::PubkeyMsg;
 use packets::types::ByteMessage;
-use packets::util::modes::Modes;
+use packets::packet::index::Packet;
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::msg::send::actions::index::on_command;
-use crate::util::arcs::{get_curr_keypair, get_symm_key_or_default};
+use crate::encryption::handshake::{is_handshake_pending, start_handshake};
+use crate::encryption::offline::{has_left, send_offline};
+use crate::encryption::ratchet::{encrypt_chat, has_ratchet};
+use crate::room::tools::{get_current_room, send_room_msg};
+use crate::util::arcs::get_curr_keypair;
 use crate::util::consts::{RECEIVER, RECEIVE_INPUT, RECEIVE_TX, SEND_DISABLED};
 use crate::util::msg::{print_from_msg, send_msg};
 pub async fn send_msgs() -> anyhow::Result<()> {
@@ -20,7 +24,7 @@ pub async fn send_msgs() -> anyhow::Result<()> {
     let initial_msg = PubkeyMsg::from_private(keypair)?.serialize();
 
     send_msg(Message::binary(initial_msg)).await?;
-    send_msg(Message::binary(Modes::WantUid.get_send(&Vec::new()))).await?;
+    send_msg(Message::binary(Packet::WantUid.encode())).await?;
 
     let prefix = "| ".blue();
     println!("{}", format!("\n\n{}Use /rec to change receiver\n{}Use /name <your name>\n{}Use /send <file> to send files.\n{}Use /h to get help\n\n", prefix, prefix, prefix, prefix));
@@ -59,7 +63,9 @@ pub async fn main_loop(stdin: &Stdin, tx: &Sender<String>) -> anyhow::Result<()>
     let is_nothing = rec_got.is_none();
 
     drop(rec);
-    if is_nothing {
+
+    let room = get_current_room().await;
+    if is_nothing && room.is_none() {
         return Ok(());
     }
 
@@ -87,10 +93,45 @@ pub async fn main_loop(stdin: &Stdin, tx: &Sender<String>) -> anyhow::Result<()>
     if line == "" {
         return Ok(());
     }
+
+    if room.is_some() {
+        let room = room.unwrap();
+        let res = send_room_msg(&room, line.as_bytes()).await;
+        if res.is_err() {
+            println!("{}", res.unwrap_err().to_string().yellow());
+            return Ok(());
+        }
+
+        print_from_msg(&format!("{} {}", format!("#{}", room).magenta(), "you"), &line);
+        return Ok(());
+    }
+
+    if rec_got.is_none() {
+        return Ok(());
+    }
     let rec_got = rec_got.clone().unwrap();
 
-    let key = get_symm_key_or_default(&rec_got).await?;
-    let encrypted = key.encrypt(line.as_bytes())?;
+    if has_left(&rec_got).await {
+        let res = send_offline(&rec_got, line.as_bytes()).await;
+        if res.is_err() {
+            println!("{}", res.unwrap_err().to_string().yellow());
+            return Ok(());
+        }
+
+        print_from_msg(&format!("{} {}", "you", "(queued)".bright_black()), &line);
+        return Ok(());
+    }
+
+    if !has_ratchet(&rec_got).await {
+        println!("{}", "Key exchange with the receiver has not finished yet. Please try again in a moment.".yellow());
+        if !is_handshake_pending(&rec_got).await {
+            start_handshake(&rec_got, false).await?;
+        }
+
+        return Ok(());
+    }
+
+    let encrypted = encrypt_chat(&rec_got, line.as_bytes()).await?;
 
     print_from_msg("you", &line);
 
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use packets::{consts::ROOM_KEEP_EPOCHS, encryption::replay::ReplayWindow, room::key::RoomKeyMsg};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default)]
+pub struct RoomState {
+    pub epoch: u64,
+    // Same order as on the server, members[0] distributes the group key
+    pub members: Vec<Uuid>,
+    // Keys of the last epochs, so messages sent right before a re-key can still be read
+    keys: HashMap<u64, Vec<u8>>,
+    // Keys which arrived before the roster of their epoch
+    pending_keys: HashMap<u64, RoomKeyMsg>,
+    send_seq: u64,
+    windows: HashMap<(Uuid, u64), ReplayWindow>,
+}
+
+impl RoomState {
+    pub fn is_joined(&self) -> bool {
+        return !self.members.is_empty();
+    }
+
+    pub fn get_distributor(&self) -> Option<Uuid> {
+        return self.members.first().cloned();
+    }
+
+    pub fn get_key(&self, epoch: u64) -> Option<Vec<u8>> {
+        return self.keys.get(&epoch).cloned();
+    }
+
+    pub fn insert_key(&mut self, epoch: u64, key: Vec<u8>) {
+        self.keys.insert(epoch, key);
+
+        let oldest = epoch.saturating_sub(ROOM_KEEP_EPOCHS - 1);
+        self.keys.retain(|e, _| *e >= oldest);
+        self.windows.retain(|(_, e), _| *e >= oldest);
+        self.pending_keys.retain(|e, _| *e > epoch);
+    }
+
+    pub fn add_pending_key(&mut self, msg: RoomKeyMsg) {
+        self.pending_keys.insert(msg.epoch, msg);
+    }
+
+    pub fn take_pending_key(&mut self, epoch: u64) -> Option<RoomKeyMsg> {
+        return self.pending_keys.remove(&epoch);
+    }
+
+    pub fn next_seq(&mut self) -> u64 {
+        self.send_seq += 1;
+        return self.send_seq;
+    }
+
+    pub fn check_seq(&mut self, sender: &Uuid, epoch: u64, seq: u64) -> anyhow::Result<()> {
+        let window = self.windows.entry((sender.clone(), epoch)).or_default();
+        return window.check_and_update(seq);
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::anyhow;
+use log::trace;
+use packets::{
+    room::{key::RoomKeyMsg, msg::RoomMsg, tools::{generate_group_key, open_room_msg, seal_room_msg, unwrap_group_key, wrap_group_key}},
+    types::ByteMessage,
+    util::compression::{compress_msg, decompress_msg},
+};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::{
+    encryption::rsa::get_pubkey_from_rec,
+    util::{arcs::{get_curr_id, get_curr_keypair}, consts::{CURRENT_ROOM, ROOMS}, msg::send_msg},
+};
+
+pub async fn get_current_room() -> Option<String> {
+    let state = CURRENT_ROOM.read().await;
+    let room = state.clone();
+
+    drop(state);
+    return room;
+}
+
+pub async fn set_current_room(room: Option<String>) {
+    let mut state = CURRENT_ROOM.write().await;
+    *state = room;
+
+    drop(state);
+}
+
+pub async fn is_in_room(room: &str) -> bool {
+    let state = ROOMS.read().await;
+    let joined = state.get(room).map(|e| e.is_joined()).unwrap_or(false);
+
+    drop(state);
+    return joined;
+}
+
+// Called by the first member of the roster whenever the epoch changes
+pub async fn distribute_key(room: &str, epoch: u64, members: &Vec<Uuid>) -> anyhow::Result<()> {
+    let curr_id = get_curr_id().await?;
+    let keypair = get_curr_keypair().await?;
+    let key = generate_group_key()?;
+
+    let mut state = ROOMS.write().await;
+    let room_state = state.get_mut(room);
+    if room_state.is_none() {
+        return Err(anyhow!("Not a member of room '{}'.", room));
+    }
+
+    room_state.unwrap().insert_key(epoch, key.clone());
+    drop(state);
+
+    for member in members.iter().filter(|e| **e != curr_id) {
+        // Verifies the key against the known peers as well
+        let pubkey = get_pubkey_from_rec(member).await;
+        if pubkey.is_err() {
+            trace!("Could not get pubkey of room member {}: {}", member, pubkey.unwrap_err());
+            continue;
+        }
+
+        let (encrypted_key, signature) = wrap_group_key(&key, room, epoch, member, &pubkey.unwrap(), &keypair)?;
+        send_msg(Message::binary(RoomKeyMsg {
+            room: room.to_string(),
+            epoch,
+            user: member.clone(),
+            encrypted_key,
+            signature
+        }.serialize())).await?;
+    }
+
+    trace!("Distributed key of epoch {} for room '{}'", epoch, room);
+    return Ok(());
+}
+
+// `msg.user` is the distributing member
+pub async fn store_key(msg: RoomKeyMsg) -> anyhow::Result<()> {
+    let RoomKeyMsg { room, epoch, user, encrypted_key, signature } = msg;
+
+    let state = ROOMS.read().await;
+    let distributor = state.get(&room).and_then(|e| e.get_distributor());
+
+    drop(state);
+    if distributor != Some(user) {
+        return Err(anyhow!("Key for room '{}' was not sent by its distributing member.", room));
+    }
+
+    let curr_id = get_curr_id().await?;
+    let keypair = get_curr_keypair().await?;
+    let distributor_key = get_pubkey_from_rec(&user).await?;
+
+    let key = unwrap_group_key(&encrypted_key, &signature, &room, epoch, &curr_id, &distributor_key, &keypair)?;
+
+    let mut state = ROOMS.write().await;
+    let room_state = state.get_mut(&room);
+    if room_state.is_some() {
+        room_state.unwrap().insert_key(epoch, key);
+    }
+
+    drop(state);
+    trace!("Received key of epoch {} for room '{}'", epoch, room);
+    return Ok(());
+}
+
+pub async fn send_room_msg(room: &str, data: &[u8]) -> anyhow::Result<()> {
+    let curr_id = get_curr_id().await?;
+
+    let mut state = ROOMS.write().await;
+    let room_state = state.get_mut(room);
+    if room_state.is_none() {
+        return Err(anyhow!("Not a member of room '{}'.", room));
+    }
+
+    let room_state = room_state.unwrap();
+    let epoch = room_state.epoch;
+    let key = room_state.get_key(epoch);
+    if key.is_none() {
+        return Err(anyhow!("Group key of room '{}' has not been received yet. Please try again in a moment.", room));
+    }
+
+    let seq = room_state.next_seq();
+    drop(state);
+
+    let encrypted = seal_room_msg(&key.unwrap(), room, epoch, &curr_id, seq, &compress_msg(data)?)?;
+    send_msg(Message::binary(RoomMsg {
+        room: room.to_string(),
+        epoch,
+        user: curr_id,
+        msg: encrypted
+    }.serialize())).await?;
+
+    return Ok(());
+}
+
+pub async fn open_msg(msg: &RoomMsg) -> anyhow::Result<Vec<u8>> {
+    let mut state = ROOMS.write().await;
+    let room_state = state.get_mut(&msg.room);
+    if room_state.is_none() {
+        return Err(anyhow!("Not a member of room '{}'.", msg.room));
+    }
+
+    let room_state = room_state.unwrap();
+    let key = room_state.get_key(msg.epoch);
+    if key.is_none() {
+        return Err(anyhow!("No key for epoch {} of room '{}'.", msg.epoch, msg.room));
+    }
+
+    let (seq, decrypted) = open_room_msg(&key.unwrap(), &msg.room, msg.epoch, &msg.user, &msg.msg)?;
+    room_state.check_seq(&msg.user, msg.epoch, seq)?;
+
+    drop(state);
+    return decompress_msg(&decrypted);
+}
@@ -0,0 +1,2 @@
+pub mod state;
+pub mod tools;
\ No newline at end of file
@@ -0,0 +1,78 @@
+use std::time::Instant;
+
+use colored::{ColoredString, Colorize};
+use indicatif::ProgressBar;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    // Waiting for a worker of the global budget
+    Queued,
+    Active,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl TransferState {
+    pub fn is_done(&self) -> bool {
+        return !matches!(self, Self::Queued | Self::Active);
+    }
+
+    pub fn to_colored(&self) -> ColoredString {
+        return match self {
+            Self::Queued => "queued".bright_black(),
+            Self::Active => "active".yellow(),
+            Self::Finished => "finished".green(),
+            Self::Failed => "failed".red(),
+            Self::Cancelled => "cancelled".red(),
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub uuid: Uuid,
+    pub direction: Direction,
+    pub filename: String,
+    pub size: u64,
+    // Receiver of uploads, sender of downloads
+    pub peer: Uuid,
+    pub state: TransferState,
+    pub bar: ProgressBar,
+    pub added: Instant,
+    // Bytes of the finished chunks before and after compressing
+    pub raw_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+impl Transfer {
+    // Shown to the user and accepted by `/cancel`
+    pub fn get_short_id(&self) -> String {
+        return self.uuid.simple().to_string()[..8].to_string();
+    }
+
+    pub fn get_progress(&self) -> f32 {
+        if self.state == TransferState::Finished || self.size == 0 {
+            return 1 as f32;
+        }
+
+        return (self.bar.position() as f32 / self.size as f32).min(1 as f32);
+    }
+
+    // e.g. `zstd 4.2x`, none until a compressed chunk has been transferred
+    pub fn get_compression_info(&self) -> Option<String> {
+        if self.compressed_bytes == 0 || self.raw_bytes == self.compressed_bytes {
+            return None;
+        }
+
+        let ratio = self.raw_bytes as f32 / self.compressed_bytes as f32;
+        return Some(format!("zstd {:.1}x", ratio));
+    }
+}
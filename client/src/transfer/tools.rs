@@ -0,0 +1,160 @@
+use std::{fmt::Write, time::{Duration, Instant}};
+
+use anyhow::anyhow;
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use log::trace;
+use packets::file::types::FileInfo;
+use tokio::sync::OwnedSemaphorePermit;
+use uuid::Uuid;
+
+use crate::util::consts::{MULTI_PROGRESS, TRANSFERS, WORKER_PERMITS};
+
+use super::state::{Direction, Transfer, TransferState};
+
+pub fn get_bar_style() -> ProgressStyle {
+    return ProgressStyle::with_template("{prefix:.bold} {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) {msg}")
+    .unwrap()
+    .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+    .progress_chars("#>-");
+}
+
+// Adds the transfer as queued, its bar is drawn together with all other transfers
+pub async fn register_transfer(uuid: &Uuid, direction: Direction, info: &FileInfo) -> ProgressBar {
+    let arrow = if direction == Direction::Upload { "↑" } else { "↓" };
+    let peer = if direction == Direction::Upload { info.receiver } else { info.sender };
+
+    let bar = MULTI_PROGRESS.add(ProgressBar::new(info.size));
+    bar.set_style(get_bar_style());
+    bar.set_prefix(format!("{} {}", arrow, info.filename));
+    bar.enable_steady_tick(Duration::from_millis(250));
+
+    let transfer = Transfer {
+        uuid: uuid.clone(),
+        direction,
+        filename: info.filename.clone(),
+        size: info.size,
+        peer,
+        state: TransferState::Queued,
+        bar: bar.clone(),
+        added: Instant::now(),
+        raw_bytes: 0,
+        compressed_bytes: 0,
+    };
+
+    let mut state = TRANSFERS.write().await;
+    let old = state.insert(uuid.clone(), transfer);
+
+    drop(state);
+    // Resumed transfers get a new bar
+    if old.is_some() {
+        old.unwrap().bar.finish_and_clear();
+    }
+
+    return bar;
+}
+
+pub async fn set_transfer_state(uuid: &Uuid, new_state: TransferState) {
+    let mut state = TRANSFERS.write().await;
+    let transfer = state.get_mut(uuid);
+    if transfer.is_none() {
+        drop(state);
+        return;
+    }
+
+    let transfer = transfer.unwrap();
+    if transfer.state.is_done() {
+        drop(state);
+        return;
+    }
+
+    trace!("Transfer {} is {:?} now", uuid, new_state);
+    transfer.state = new_state;
+    if new_state.is_done() {
+        transfer.bar.disable_steady_tick();
+        transfer.bar.finish_and_clear();
+    }
+
+    drop(state);
+}
+
+// Called for every finished chunk, shows the compression ratio next to the bar
+pub async fn add_transfer_bytes(uuid: &Uuid, raw: u64, compressed: u64) {
+    let mut state = TRANSFERS.write().await;
+    let transfer = state.get_mut(uuid);
+    if transfer.is_none() {
+        drop(state);
+        return;
+    }
+
+    let transfer = transfer.unwrap();
+    transfer.raw_bytes += raw;
+    transfer.compressed_bytes += compressed;
+
+    let info = transfer.get_compression_info();
+    if info.is_some() {
+        transfer.bar.set_message(info.unwrap());
+    }
+
+    drop(state);
+}
+
+// Appended to the message of a finished transfer
+pub async fn get_compression_suffix(uuid: &Uuid) -> String {
+    let state = TRANSFERS.read().await;
+    let info = state.get(uuid).and_then(|e| e.get_compression_info());
+
+    drop(state);
+    return info.map(|e| format!(" ({})", e)).unwrap_or_default();
+}
+
+// Every worker of every transfer holds one permit while it runs
+pub async fn acquire_worker(uuid: &Uuid) -> anyhow::Result<OwnedSemaphorePermit> {
+    let permit = WORKER_PERMITS.clone().acquire_owned().await?;
+
+    let mut state = TRANSFERS.write().await;
+    let transfer = state.get_mut(uuid);
+    if transfer.is_some() && transfer.as_ref().unwrap().state == TransferState::Queued {
+        transfer.unwrap().state = TransferState::Active;
+    }
+
+    drop(state);
+    return Ok(permit);
+}
+
+pub async fn get_transfers() -> Vec<Transfer> {
+    let state = TRANSFERS.read().await;
+    let mut transfers: Vec<Transfer> = state.values().cloned().collect();
+
+    drop(state);
+    transfers.sort_by_key(|e| e.added);
+
+    return transfers;
+}
+
+// Accepts the short id of `/transfers` or the full uuid
+pub async fn find_transfer(id: &str) -> anyhow::Result<Transfer> {
+    let id = id.to_lowercase().replace("-", "");
+    if id.is_empty() {
+        return Err(anyhow!("No transfer id given."));
+    }
+
+    let transfers = get_transfers().await;
+    let found: Vec<&Transfer> = transfers.iter()
+        .filter(|e| e.uuid.simple().to_string().starts_with(&id))
+        .collect();
+
+    if found.len() > 1 {
+        return Err(anyhow!("Transfer id '{}' is ambiguous.", id));
+    }
+
+    if found.is_empty() {
+        return Err(anyhow!("Could not find transfer '{}'.", id));
+    }
+
+    return Ok(found[0].clone());
+}
+
+// Prints above the progress bars instead of through them
+pub fn print_transfer(msg: String) {
+    MULTI_PROGRESS.suspend(|| println!("{}", msg));
+}
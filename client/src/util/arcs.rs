@@ -1,9 +1,9 @@
 use anyhow::anyhow;
 use openssl::{rsa::Rsa, pkey::Private};
-use packets::other::key_iv::KeyIVPair;
+use packets::util::compression::Compression;
 use uuid::Uuid;
 
-use super::consts::{RECEIVER, CURR_ID, KEYPAIR, BASE_URL, USE_TLS, CONCURRENT_THREADS, CHAT_SYMM_KEYS};
+use super::consts::{RECEIVER, CURR_ID, KEYPAIR, BASE_URL, USE_TLS, CONCURRENT_THREADS, FEATURES, CHUNK_SIZE, CHUNK_BOUNDS, COMPRESSION};
 
 
 pub async fn get_curr_keypair() -> anyhow::Result<Rsa<Private>> {
@@ -60,6 +60,14 @@ pub async fn use_tls() -> bool {
     return out;
 }
 
+pub async fn has_feature(feature: u64) -> bool {
+    let state = FEATURES.read().await;
+    let features = state.clone();
+
+    drop(state);
+    return features & feature != 0;
+}
+
 pub async fn get_concurrent_threads() -> u64 {
     let state = CONCURRENT_THREADS.read().await;
     let threads = state.clone();
@@ -68,28 +76,24 @@ pub async fn get_concurrent_threads() -> u64 {
     return threads;
 }
 
-pub async fn get_symm_key(user: &Uuid) -> anyhow::Result<KeyIVPair> {
-    let state = CHAT_SYMM_KEYS.read().await;
-    let key = state.get(&user).unwrap_or(&(None as Option<KeyIVPair>)).to_owned();
+// Chunk size for new transfers, the preferred one limited by the bounds of the server
+pub async fn get_transfer_chunk_size() -> u64 {
+    let state = CHUNK_SIZE.read().await;
+    let chunk_size = state.clone();
 
     drop(state);
-    if key.is_none() {
-        return Err(anyhow!("Could not get symm key"));
-    } else {
-        return Ok(key.unwrap());
-    }
-}
 
-pub async fn get_symm_key_or_default(user: &Uuid) -> anyhow::Result<KeyIVPair> {
-    let mut state = CHAT_SYMM_KEYS.write().await;
-    let key = state.get(&user).unwrap_or(&(None as Option<KeyIVPair>)).to_owned();
+    let state = CHUNK_BOUNDS.read().await;
+    let (min, max) = state.clone();
 
-    if key.is_none() {
-        let key = KeyIVPair::generate()?;
-        state.insert(user.clone(), Some(key.clone()));
+    drop(state);
+    return chunk_size.clamp(min, max);
+}
 
-        return Ok(key);
-    } else {
-        return Ok(key.unwrap());
-    }
-}
\ No newline at end of file
+pub async fn get_compression() -> Compression {
+    let state = COMPRESSION.read().await;
+    let compression = state.clone();
+
+    drop(state);
+    return compression;
+}
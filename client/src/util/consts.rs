@@ -1,6 +1,8 @@
 use std::sync::{Arc, atomic::AtomicBool};
 use futures_util::lock::Mutex;
-use tokio::sync::RwLock;
+use indicatif::MultiProgress;
+use packets::{util::{rate::TokenBucket, compression::Compression}, consts::{DEFAULT_CHUNK_SIZE, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE}};
+use tokio::sync::{RwLock, Semaphore};
 
 use lazy_static::lazy_static;
 
@@ -15,6 +17,13 @@ lazy_static! {
     pub static ref SEND_DISABLED: SendDisabled = Arc::new(AtomicBool::new(true));
     pub static ref RECEIVER: ReceiverArc = ReceiverArc::new(RwLock::new(None));
     pub static ref KEYPAIR: Keypair = Arc::new(RwLock::new(None));
+    pub static ref IDENTITY_PATH: IdentityPath = Arc::new(RwLock::new(None));
+    pub static ref IDENTITY_PASSPHRASE: IdentityPassphrase = Arc::new(RwLock::new(None));
+    pub static ref KNOWN_PEERS: KnownPeers = Arc::new(RwLock::new(None));
+    pub static ref WARNED_PEERS: WarnedPeers = WarnedPeers::default();
+    pub static ref PEER_KEYS: PeerKeys = PeerKeys::default();
+    pub static ref LEFT_PEERS: LeftPeers = LeftPeers::default();
+    pub static ref SEEN_MESSAGES: SeenMessages = Arc::new(RwLock::new(None));
 
 
     pub static ref TX_CHANNEL: TXChannelArc = Arc::new(Mutex::new(None));
@@ -25,8 +34,28 @@ lazy_static! {
     pub static ref RECEIVE_INPUT: ReceiveInput = Arc::new(AtomicBool::new(false));
 
     pub static ref PENDING_FILES: PendingFiles = PendingFiles::default();
+    pub static ref FILE_PERMISSIONS: FilePermissions = FilePermissions::default();
     pub static ref FILE_UPLOADS: FileUploads = FileUploads::default();
     pub static ref FILE_DOWNLOADS: FileDownloads = FileDownloads::default();
+    pub static ref TRANSFER_JOURNALS: TransferJournals = Arc::new(RwLock::new(None));
     
-    pub static ref CHAT_SYMM_KEYS: ChatSymmKeys = ChatSymmKeys::default();
+    pub static ref CHAT_RATCHETS: ChatRatchets = ChatRatchets::default();
+    pub static ref PENDING_HANDSHAKES: PendingHandshakes = PendingHandshakes::default();
+
+    pub static ref FEATURES: Features = Features::default();
+    pub static ref ROOMS: Rooms = Rooms::default();
+    pub static ref CURRENT_ROOM: CurrentRoom = CurrentRoom::default();
+
+    pub static ref TRANSFERS: Transfers = Transfers::default();
+    // Permits are added on startup, one per concurrent thread
+    pub static ref WORKER_PERMITS: WorkerPermits = Arc::new(Semaphore::new(0));
+    pub static ref MULTI_PROGRESS: MultiProgress = MultiProgress::new();
+
+    pub static ref UPLOAD_LIMIT: RateLimit = Arc::new(tokio::sync::Mutex::new(TokenBucket::new(0)));
+    pub static ref DOWNLOAD_LIMIT: RateLimit = Arc::new(tokio::sync::Mutex::new(TokenBucket::new(0)));
+
+    pub static ref CHUNK_SIZE: ChunkSize = Arc::new(RwLock::new(DEFAULT_CHUNK_SIZE));
+    pub static ref CHUNK_BOUNDS: ChunkBounds = Arc::new(RwLock::new((MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)));
+    pub static ref COMPRESSION: CompressionArc = Arc::new(RwLock::new(Compression::Zstd));
+    pub static ref UPLOADED_BLOBS: UploadedBlobs = UploadedBlobs::default();
 }
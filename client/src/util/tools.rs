@@ -1,11 +1,12 @@
 use uuid::Uuid;
 
-use crate::web::user_info::get_user_info;
+use crate::web::user_info::{fetch_user_info, get_user_list};
 
 use super::msg::get_input;
 
+// Names are only displayed, so the key of the user is not verified here
 pub async fn uuid_to_name(uuid: Uuid) -> anyhow::Result<String> {
-    let info = get_user_info(&uuid).await?;
+    let info = fetch_user_info(&uuid).await?;
 
     if info.name.is_some() {
         return Ok(info.name.unwrap());
@@ -14,6 +15,18 @@ pub async fn uuid_to_name(uuid: Uuid) -> anyhow::Result<String> {
     return Ok(uuid.to_string());
 }
 
+// Finds the connected user with the given name or id
+pub async fn name_to_uuid(name: &str) -> anyhow::Result<Option<Uuid>> {
+    let users = get_user_list().await?;
+    for user in users {
+        if user.to_string() == name || uuid_to_name(user).await? == name {
+            return Ok(Some(user));
+        }
+    }
+
+    return Ok(None);
+}
+
 pub async fn wait_confirm() -> anyhow::Result<bool> {
     let accepted:bool;
     loop {
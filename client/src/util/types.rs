@@ -1,17 +1,29 @@
-use std::{sync::{atomic::AtomicBool, Arc}, collections::HashMap};
+use std::{sync::{atomic::AtomicBool, Arc}, collections::{HashMap, HashSet}, path::PathBuf};
 
 use async_channel::{Receiver, Sender};
 use clap::{arg, command, Parser};
 use futures_util::{stream::{SplitSink, SplitStream}, lock::Mutex};
-use openssl::{pkey::Private, rsa::Rsa};
-use packets::{file::types::FileInfo, other::key_iv::KeyIVPair};
-use tokio::{net::TcpStream, sync::RwLock};
+use openssl::{pkey::{Private, Public}, rsa::Rsa};
+use packets::{file::{types::FileInfo, journal::TransferJournal}, encryption::ratchet::Ratchet, util::{rate::{parse_rate, parse_size, TokenBucket}, compression::Compression}};
+use tokio::{net::TcpStream, sync::{RwLock, Semaphore}};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
 use uuid::Uuid;
 
-use crate::file::{uploader::index::Uploader, downloader::index::Downloader};
+use crate::{file::{uploader::index::Uploader, downloader::index::Downloader, blobs::UploadedBlob}, room::state::RoomState, transfer::state::Transfer, encryption::handshake::PendingHandshake};
 
 pub type Keypair = Arc<RwLock<Option<Rsa<Private>>>>;
+pub type IdentityPath = Arc<RwLock<Option<PathBuf>>>;
+pub type IdentityPassphrase = Arc<RwLock<Option<String>>>;
+// Key is the fingerprint, value the name the peer had when its key was pinned
+pub type KnownPeers = Arc<RwLock<Option<HashMap<Vec<u8>, String>>>>;
+// Fingerprints of renamed peers the user has been warned about, only once per run
+pub type WarnedPeers = Arc<RwLock<HashSet<Vec<u8>>>>;
+// Verified keys of the peers, kept after they disconnect so messages can still be sealed to them
+pub type PeerKeys = Arc<RwLock<HashMap<Uuid, Rsa<Public>>>>;
+// Users who disconnected, uuids are never reused so they stay offline
+pub type LeftPeers = Arc<RwLock<HashSet<Uuid>>>;
+// Ids of received sealed messages with the time they were sent, loaded lazily from next to the identity
+pub type SeenMessages = Arc<RwLock<Option<HashMap<Uuid, u64>>>>;
 pub type ConcurrentThreads= Arc<RwLock<u64>>;
 pub type BaseUrl = Arc<RwLock<String>>;
 pub type UseTls = Arc<RwLock<bool>>;
@@ -32,7 +44,22 @@ pub type ReceiveTX = Sender<String>;
 pub type FileUploads = Arc<RwLock<HashMap<Uuid, Uploader>>>;
 pub type FileDownloads = Arc<RwLock<HashMap<Uuid, Downloader>>>;
 pub type PendingFiles = Arc<RwLock<HashMap<Uuid, FileInfo>>>;
-pub type ChatSymmKeys = Arc<RwLock<HashMap<Uuid, Option<KeyIVPair>>>>;
+pub type FilePermissions = Arc<RwLock<HashMap<Uuid, u32>>>;
+pub type TransferJournals = Arc<RwLock<Option<HashMap<Uuid, TransferJournal>>>>;
+pub type ChatRatchets = Arc<RwLock<HashMap<Uuid, Ratchet>>>;
+pub type PendingHandshakes = Arc<RwLock<HashMap<Uuid, PendingHandshake>>>;
+pub type Rooms = Arc<RwLock<HashMap<String, RoomState>>>;
+pub type CurrentRoom = Arc<RwLock<Option<String>>>;
+pub type Features = Arc<RwLock<u64>>;
+pub type Transfers = Arc<RwLock<HashMap<Uuid, Transfer>>>;
+pub type WorkerPermits = Arc<Semaphore>;
+pub type RateLimit = Arc<tokio::sync::Mutex<TokenBucket>>;
+pub type ChunkSize = Arc<RwLock<u64>>;
+// Min and max chunk size of the server
+pub type ChunkBounds = Arc<RwLock<(u64, u64)>>;
+pub type CompressionArc = Arc<RwLock<Compression>>;
+// Chunk hash and compression of the file to the blob of the chunk on the server
+pub type UploadedBlobs = Arc<RwLock<HashMap<(Vec<u8>, Compression), UploadedBlob>>>;
 
 /// An client designed to communicate via rsa to other clients
 #[derive(Parser, Debug)]
@@ -53,6 +80,38 @@ pub struct Args {
     #[arg(short = 't', long)]
     pub threads: Option<usize>,
 
+    /// Max upload rate of all transfers together (e.g. 500K or 2M), unlimited by default
+    #[arg(long, value_parser = parse_rate)]
+    pub max_upload_rate: Option<u64>,
+
+    /// Max download rate of all transfers together (e.g. 500K or 2M), unlimited by default
+    #[arg(long, value_parser = parse_rate)]
+    pub max_download_rate: Option<u64>,
+
+    /// Chunk size of files you send (e.g. 1M or 50M), 10M by default. Limited by the bounds of the server
+    #[arg(long, value_parser = parse_size)]
+    pub chunk_size: Option<u64>,
+
+    /// Compression of files you send (zstd or none). Already compressed files are always sent as-is
+    #[arg(long, default_value = "zstd", value_parser = Compression::from_name)]
+    pub compression: Compression,
+
+    /// Path of the passphrase-encrypted identity (defaults to ~/.rsa-msg/identity.pem)
+    #[arg(short = 'i', long)]
+    pub identity: Option<PathBuf>,
+
+    /// Writes a copy of the encrypted identity to the given path and exits
+    #[arg(long)]
+    pub export_identity: Option<PathBuf>,
+
+    /// Replaces the current identity with the encrypted identity at the given path
+    #[arg(long)]
+    pub import_identity: Option<PathBuf>,
+
+    /// Generates a new keypair for the identity before connecting (old one is kept as .old)
+    #[arg(long, default_value_t = false)]
+    pub rotate_identity: bool,
+
     #[command(subcommand)]
     action: Option<Action>,
 }
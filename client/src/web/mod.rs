@@ -1,3 +1,4 @@
 pub mod user_info;
 pub mod progress;
-pub mod prefix;
\ No newline at end of file
+pub mod prefix;
+pub mod throttle;
\ No newline at end of file
@@ -1,130 +1,138 @@
 use anyhow::anyhow;
 use bytes::Bytes;
-use futures::{io::BufReader, stream};
-use futures::{AsyncBufReadExt, TryStreamExt};
+use futures::{io::BufReader, Stream};
+use futures::{AsyncReadExt, TryStreamExt};
 use futures_util::StreamExt;
 use log::warn;
-use packets::consts::ONE_MB_SIZE;
 use std::{cmp::min, sync::Arc};
 use surf::Body;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::RwLock;
-use tokio::sync::RwLock as TokioRwLock;
 
-use crate::file::tools::WorkerProgress;
+use crate::{file::tools::WorkerProgress, util::consts::{DOWNLOAD_LIMIT, UPLOAD_LIMIT}};
 
-pub async fn download_file(
-    url: String,
-    sender: &UnboundedSender<WorkerProgress>,
+use super::throttle::throttle;
+
+// Response of a chunk download, read piece by piece so only small buffers are kept in memory
+pub struct ChunkDownload {
+    reader: BufReader<surf::Response>,
+    total_size: u64,
+    downloaded: u64,
+    sender: UnboundedSender<WorkerProgress>,
     chunk_index: u64,
-) -> anyhow::Result<Vec<u8>> {
-    let arc = Arc::new(TokioRwLock::new(sender));
-
-    let res = surf::get(url.clone())
-        .await
-        .or(Err(anyhow!(format!("Failed to GET from '{}'", &url))))?;
-    let total_size = res.header("Content-Length");
-    if total_size.is_none() {
-        return Err(anyhow!(format!(
-            "Failed to get content length from '{}'",
-            &url
-        )));
-    }
+}
 
-    let total_size = total_size.unwrap();
-    let total_size = total_size.get(0);
-    if total_size.is_none() {
-        return Err(anyhow!(format!(
-            "Failed to get content length from '{}'",
-            &url
-        )));
-    }
+impl ChunkDownload {
+    pub async fn new(
+        url: String,
+        sender: UnboundedSender<WorkerProgress>,
+        chunk_index: u64,
+    ) -> anyhow::Result<Self> {
+        let res = surf::get(url.clone())
+            .await
+            .or(Err(anyhow!(format!("Failed to GET from '{}'", &url))))?;
+        let total_size = res.header("Content-Length");
+        if total_size.is_none() {
+            return Err(anyhow!(format!(
+                "Failed to get content length from '{}'",
+                &url
+            )));
+        }
 
-    let total_size = total_size.unwrap();
-    let total_size = total_size.to_string().parse::<u64>()?;
+        let total_size = total_size.unwrap();
+        let total_size = total_size.get(0);
+        if total_size.is_none() {
+            return Err(anyhow!(format!(
+                "Failed to get content length from '{}'",
+                &url
+            )));
+        }
 
-    let mut buffer = Vec::new();
-    let stream = BufReader::new(res);
-    let arc_stream = Arc::new(TokioRwLock::new(stream));
+        let total_size = total_size.unwrap();
+        let total_size = total_size.to_string().parse::<u64>()?;
 
-    let mut downloaded: u64 = 0;
+        return Ok(ChunkDownload {
+            reader: BufReader::new(res),
+            total_size,
+            downloaded: 0,
+            sender,
+            chunk_index,
+        });
+    }
 
-    loop {
-        let mut buf_state = arc_stream.write().await;
-        let mut chunk = buf_state.fill_buf().await?.to_vec();
-        if chunk.len() <= 0 {
-            break;
+    // Reads exactly `size` bytes of the response
+    pub async fn read_vec(&mut self, size: usize) -> anyhow::Result<Vec<u8>> {
+        if size as u64 > self.total_size - self.downloaded {
+            return Err(anyhow!("Can not read {} bytes, only {} are left.", size, self.total_size - self.downloaded));
         }
 
-        drop(buf_state);
-        let mut buf_state = arc_stream.write().await;
+        let mut buffer = vec![0 as u8; size];
+        self.reader.read_exact(&mut buffer).await?;
 
-        buf_state.consume_unpin(chunk.len());
-        drop(buf_state);
+        throttle(&DOWNLOAD_LIMIT, size as u64).await;
 
-        let length = chunk.len();
-        buffer.append(&mut chunk);
+        self.downloaded = min(self.downloaded + size as u64, self.total_size);
+        let prog = (self.downloaded as f32) / (self.total_size as f32);
 
-        let new = min(downloaded + (length as u64), total_size);
-        let prog = (new as f32) / (total_size as f32);
-        downloaded = new;
-
-        let state = arc.read().await;
-        let e = state.send(WorkerProgress {
+        self.sender.send(WorkerProgress {
             progress: prog,
-            chunk: chunk_index,
-        });
-        drop(state);
-        e?;
+            chunk: self.chunk_index,
+        })?;
+
+        return Ok(buffer);
     }
 
-    let state = arc.read().await;
-    let e = state.send(WorkerProgress {
-        progress: 1 as f32,
-        chunk: chunk_index,
-    });
-    drop(state);
-    e?;
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if self.downloaded != self.total_size {
+            return Err(anyhow!("Chunk has {} unread bytes.", self.total_size - self.downloaded));
+        }
+
+        self.sender.send(WorkerProgress {
+            progress: 1 as f32,
+            chunk: self.chunk_index,
+        })?;
 
-    return Ok(buffer);
+        return Ok(());
+    }
 }
 
-pub async fn upload_file(
+// Uploads `body` while it is produced, `size` has to be the exact amount of bytes of it
+pub async fn upload_file<S>(
     url: String,
-    buf: Vec<u8>,
+    body: S,
+    size: usize,
     sender_arc: Arc<RwLock<UnboundedSender<WorkerProgress>>>,
-    offset: f32,
     index: u64,
-) -> anyhow::Result<surf::Response> {
-    let uploaded = Arc::new(RwLock::new(0));
-
-    let e = buf.clone();
-    let chunk_size = buf.len();
-
-    let mut cursor = stream::iter(e).chunks(std::cmp::min(ONE_MB_SIZE as usize, buf.len()));
-
+) -> anyhow::Result<surf::Response>
+where
+    S: Stream<Item = anyhow::Result<Vec<u8>>> + Send + Sync + 'static,
+{
     let sender = sender_arc.write_owned().await;
 
     let stream = async_stream::stream! {
-        while let Some(chunk) = cursor.next().await {
-            let l = chunk.len();
-            yield Ok(Bytes::from(chunk)) as Result<Bytes, std::io::Error>;
-
-            let mut s = uploaded.write().await;
-            *s += l;
+        let mut body = Box::pin(body);
+        let mut uploaded = 0;
 
-            let curr = s.clone();
-            drop(s);
+        while let Some(piece) = body.next().await {
+            if piece.is_err() {
+                let err = piece.unwrap_err();
+                yield Err(std::io::Error::new(std::io::ErrorKind::Other, err.to_string()));
+                return;
+            }
 
+            let piece = piece.unwrap();
+            let l = piece.len();
+            throttle(&UPLOAD_LIMIT, l as u64).await;
+            yield Ok(Bytes::from(piece)) as Result<Bytes, std::io::Error>;
 
-            let prog = ((curr as f32) / (chunk_size as f32) * offset ) + offset;
+            uploaded += l;
+            let prog = (uploaded as f32) / (size as f32);
 
             let e = sender.send(WorkerProgress { chunk: index, progress: prog });
             if e.is_err() {
                 warn!("Could not update progress bar (send): {}", e.unwrap_err());
                 return;
             }
-
         }
 
         drop(sender);
@@ -132,7 +140,7 @@ pub async fn upload_file(
 
     let reader = Box::pin(stream.into_async_read());
     let e = surf::post(url)
-        .body(Body::from_reader(reader, Some(buf.len())))
+        .body(Body::from_reader(reader, Some(size)))
         .send()
         .await;
 
@@ -0,0 +1,38 @@
+use log::trace;
+
+use crate::util::types::RateLimit;
+
+// Waits until `bytes` may be sent / received, shared by all workers
+pub async fn throttle(limit: &RateLimit, bytes: u64) {
+    let mut state = limit.lock().await;
+    let wait = state.take(bytes);
+
+    drop(state);
+    if !wait.is_zero() {
+        trace!("Throttling {} bytes for {:?}", bytes, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+pub async fn get_piece_size(limit: &RateLimit, default: usize) -> usize {
+    let state = limit.lock().await;
+    let size = state.get_piece_size(default);
+
+    drop(state);
+    return size;
+}
+
+pub async fn get_rate(limit: &RateLimit) -> u64 {
+    let state = limit.lock().await;
+    let rate = state.get_rate();
+
+    drop(state);
+    return rate;
+}
+
+pub async fn set_rate(limit: &RateLimit, rate: u64) {
+    let mut state = limit.lock().await;
+    state.set_rate(rate);
+
+    drop(state);
+}
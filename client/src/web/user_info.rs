@@ -1,14 +1,18 @@
+use std::str::FromStr;
+
 use anyhow::anyhow;
 use log::trace;
 use packets::other::info::UserInfoBasic;
 use uuid::Uuid;
 
 use crate::{
-    util::{arcs::get_base_url},
-    web::prefix::get_web_protocol,
+    util::{arcs::get_base_url, consts::PEER_KEYS},
+    web::prefix::get_web_protocol, encryption::known_peers::verify_peer_key,
 };
 
-pub async fn get_user_info(uuid: &Uuid) -> anyhow::Result<UserInfoBasic> {
+// Info as the server reports it, the key is not checked against the pinned ones.
+// Only use it for display, everything that relies on the key goes through `get_user_info`.
+pub async fn fetch_user_info(uuid: &Uuid) -> anyhow::Result<UserInfoBasic> {
     let uuid = uuid.clone();
     let e = tokio::spawn(async move {
         let protocol = get_web_protocol().await;
@@ -40,3 +44,40 @@ pub async fn get_user_info(uuid: &Uuid) -> anyhow::Result<UserInfoBasic> {
 
     return e.await?;
 }
+
+pub async fn get_user_info(uuid: &Uuid) -> anyhow::Result<UserInfoBasic> {
+    let info = fetch_user_info(uuid).await?;
+    if info.public_key.is_some() {
+        let pubkey = info.public_key.clone().unwrap();
+        verify_peer_key(uuid, &info.name, &pubkey).await?;
+
+        PEER_KEYS.write().await.insert(uuid.clone(), pubkey);
+    }
+
+    return Ok(info);
+}
+
+
+// Connection ids of every connected user
+pub async fn get_user_list() -> anyhow::Result<Vec<Uuid>> {
+    let protocol = get_web_protocol().await;
+    let base = get_base_url().await;
+
+    let list_url = format!("{}//{}/list", protocol, base);
+    let resp = surf::get(list_url.to_string()).await;
+    if resp.is_err() {
+        return Err(anyhow!(resp.unwrap_err()));
+    }
+
+    let text = resp.unwrap().body_string().await;
+    if text.is_err() {
+        return Err(text.unwrap_err().into_inner());
+    }
+
+    let users = text.unwrap()
+        .split(",")
+        .filter_map(|e| Uuid::from_str(e.trim()).ok())
+        .collect();
+
+    return Ok(users);
+}
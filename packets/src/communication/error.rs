@@ -1,20 +1,64 @@
-use crate::{types::ByteMessage, util::modes::Modes};
+use crate::{types::ByteMessage, util::{converter::pop_front_vec, modes::Modes}};
 
+// Tells the client why the server rejected a request, so it can react without parsing the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Generic,
+    // The file is larger than the server allows
+    FileTooLarge,
+    // The sender has too many transfers running already
+    TooManyTransfers,
+    // The transfers of the sender would reserve more space than they may use
+    UserQuotaExceeded,
+    // The transfers on the server have reserved all the space the server allows
+    ReservedQuotaExceeded,
+}
+
+impl ErrorCode {
+    pub fn get_indicator(self) -> u8 {
+        match self {
+            Self::Generic => 0,
+            Self::FileTooLarge => 1,
+            Self::TooManyTransfers => 2,
+            Self::UserQuotaExceeded => 3,
+            Self::ReservedQuotaExceeded => 4,
+        }
+    }
+
+    // Codes of newer servers are shown as generic errors
+    pub fn from_indicator(ind: u8) -> Self {
+        match ind {
+            1 => Self::FileTooLarge,
+            2 => Self::TooManyTransfers,
+            3 => Self::UserQuotaExceeded,
+            4 => Self::ReservedQuotaExceeded,
+            _ => Self::Generic,
+        }
+    }
+}
+
+// Layout: `code | error`
 pub struct ErrorMsg {
+    pub code: ErrorCode,
     pub error: String
 }
 
 impl ByteMessage for ErrorMsg {
     fn serialize(&self) -> Vec<u8> {
-        return Modes::Error.get_send(&self.error.as_bytes().to_vec());
+        let mut merged = vec![self.code.get_indicator()];
+        merged.append(&mut self.error.as_bytes().to_vec());
+
+        return Modes::Error.get_send(&merged);
     }
 
     fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
-        let data = data.clone();
+        let mut data = data.clone();
 
+        let code = ErrorCode::from_indicator(pop_front_vec(&mut data)?);
         let error = String::from_utf8(data)?;
         return Ok(ErrorMsg {
+            code,
             error
         });
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,35 @@
+use openssl::{pkey::Public, rsa::Rsa};
+
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{usize_to_vec, vec_to_usize}, vec::extract_vec}};
+
+// Queued message, the uuid of the sender is stale by now so its key is sent instead
+pub struct FromOfflineMsg {
+    pub msg: Vec<u8>,
+    pub sender_key: Rsa<Public>,
+}
+
+impl ByteMessage for FromOfflineMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+        let mut pem = self.sender_key.public_key_to_pem().unwrap();
+
+        merged.append(&mut usize_to_vec(pem.len()).unwrap());
+        merged.append(&mut pem);
+        merged.append(&mut self.msg.clone());
+
+        return Modes::FromOffline.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let pem_len = vec_to_usize(&mut data)?;
+        let pem = extract_vec(0..pem_len, &mut data)?;
+        let sender_key = Rsa::public_key_from_pem(&pem)?;
+
+        return Ok(FromOfflineMsg {
+            msg: data,
+            sender_key
+        });
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::anyhow;
+use openssl::{derive::Deriver, pkey::{Id, PKey, Private, Public}, rand::rand_bytes, rsa::Rsa};
+use uuid::Uuid;
+
+use crate::{
+    consts::{AES_IVSIZE_BYTES, AES_KEYSIZE_BYTES, HANDSHAKE_NONCE_BYTES, X25519_KEY_BYTES},
+    encryption::{kdf::hkdf, sign::{get_signature, validate_signature}},
+    other::key_iv::KeyIVPair,
+    util::converter::uuid_to_vec,
+};
+
+pub const HANDSHAKE_INFO: &[u8] = b"rsa-msg chat session";
+
+pub fn generate_ephemeral() -> anyhow::Result<PKey<Private>> {
+    return Ok(PKey::generate_x25519()?);
+}
+
+pub fn get_ephemeral_public(key: &PKey<Private>) -> anyhow::Result<Vec<u8>> {
+    return Ok(key.raw_public_key()?);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeRole {
+    Initiator,
+    Responder,
+}
+
+impl HandshakeRole {
+    pub fn get_indicator(self) -> u8 {
+        match self {
+            Self::Initiator => 0,
+            Self::Responder => 1,
+        }
+    }
+}
+
+// Everything the long-term rsa key signs together with an ephemeral key. The ids stop the server from
+// redirecting it, the role from reflecting a request as reply and the fresh nonce of the initiator from replaying
+// an old reply. The responder signs the nonce and rekey flag of the request it answers.
+#[derive(Debug, Clone)]
+pub struct HandshakeBinding {
+    pub role: HandshakeRole,
+    pub from: Uuid,
+    pub to: Uuid,
+    pub nonce: Vec<u8>,
+    // The initiator replaces a session the responder may still have
+    pub rekey: bool,
+}
+
+impl HandshakeBinding {
+    fn get_signed_data(&self, ephemeral: &[u8]) -> Vec<u8> {
+        let mut merged = vec![self.role.get_indicator()];
+        merged.append(&mut ephemeral.to_vec());
+        merged.append(&mut uuid_to_vec(&self.from));
+        merged.append(&mut uuid_to_vec(&self.to));
+        merged.append(&mut self.nonce.clone());
+        merged.push(self.rekey as u8);
+
+        return merged;
+    }
+}
+
+pub fn generate_nonce() -> anyhow::Result<Vec<u8>> {
+    let mut nonce = vec![0 as u8; HANDSHAKE_NONCE_BYTES];
+    rand_bytes(&mut nonce)?;
+
+    return Ok(nonce);
+}
+
+pub fn sign_ephemeral(ephemeral: &[u8], binding: &HandshakeBinding, keypair: &Rsa<Private>) -> anyhow::Result<Vec<u8>> {
+    let data = binding.get_signed_data(ephemeral);
+    return get_signature(&data, keypair);
+}
+
+pub fn verify_ephemeral(ephemeral: &[u8], signature: &Vec<u8>, binding: &HandshakeBinding, pubkey: &Rsa<Public>) -> anyhow::Result<()> {
+    let data = binding.get_signed_data(ephemeral);
+    let valid = validate_signature(&data, signature, pubkey)?;
+
+    if !valid {
+        return Err(anyhow!("Invalid signature of ephemeral key. The key exchange may have been tampered with."));
+    }
+
+    return Ok(());
+}
+
+pub fn derive_session_key(
+    own: &PKey<Private>,
+    peer_ephemeral: &[u8],
+    initiator: &Uuid,
+    initiator_ephemeral: &[u8],
+    responder: &Uuid,
+    responder_ephemeral: &[u8],
+    nonce: &[u8],
+) -> anyhow::Result<KeyIVPair> {
+    if peer_ephemeral.len() != X25519_KEY_BYTES {
+        return Err(anyhow!("Invalid length of ephemeral key ({})", peer_ephemeral.len()));
+    }
+
+    let peer: PKey<Public> = PKey::public_key_from_raw_bytes(peer_ephemeral, Id::X25519)?;
+
+    let mut deriver = Deriver::new(own)?;
+    deriver.set_peer(&peer)?;
+    let shared = deriver.derive_to_vec()?;
+
+    let mut salt = nonce.to_vec();
+    salt.append(&mut initiator_ephemeral.to_vec());
+    salt.append(&mut responder_ephemeral.to_vec());
+
+    let mut info = HANDSHAKE_INFO.to_vec();
+    info.append(&mut uuid_to_vec(initiator));
+    info.append(&mut uuid_to_vec(responder));
+
+    let mut okm = hkdf(&shared, &salt, &info, AES_KEYSIZE_BYTES + AES_IVSIZE_BYTES)?;
+    let iv = okm.split_off(AES_KEYSIZE_BYTES);
+
+    return Ok(KeyIVPair { key: okm, iv });
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::rsa::Rsa;
+    use uuid::Uuid;
+
+    use super::{derive_session_key, generate_ephemeral, generate_nonce, get_ephemeral_public, sign_ephemeral, verify_ephemeral, HandshakeBinding, HandshakeRole};
+
+    fn get_binding() -> HandshakeBinding {
+        return HandshakeBinding {
+            role: HandshakeRole::Initiator,
+            from: Uuid::from_u128(1),
+            to: Uuid::from_u128(2),
+            nonce: generate_nonce().unwrap(),
+            rekey: false
+        };
+    }
+
+    #[test]
+    fn signature_is_bound_to_role_ids_nonce_and_rekey() {
+        let keypair = Rsa::generate(2048).unwrap();
+        let pubkey = Rsa::public_key_from_pem(&keypair.public_key_to_pem().unwrap()).unwrap();
+
+        let ephemeral = get_ephemeral_public(&generate_ephemeral().unwrap()).unwrap();
+        let binding = get_binding();
+        let signature = sign_ephemeral(&ephemeral, &binding, &keypair).unwrap();
+
+        assert!(verify_ephemeral(&ephemeral, &signature, &binding, &pubkey).is_ok());
+
+        let changed = vec![
+            HandshakeBinding { role: HandshakeRole::Responder, ..binding.clone() },
+            HandshakeBinding { from: Uuid::from_u128(3), ..binding.clone() },
+            HandshakeBinding { to: Uuid::from_u128(3), ..binding.clone() },
+            HandshakeBinding { nonce: generate_nonce().unwrap(), ..binding.clone() },
+            HandshakeBinding { rekey: true, ..binding.clone() },
+        ];
+
+        for other in changed {
+            assert!(verify_ephemeral(&ephemeral, &signature, &other, &pubkey).is_err());
+        }
+    }
+
+    #[test]
+    fn both_sides_derive_the_same_key() {
+        let initiator = Uuid::from_u128(1);
+        let responder = Uuid::from_u128(2);
+        let nonce = generate_nonce().unwrap();
+
+        let initiator_key = generate_ephemeral().unwrap();
+        let responder_key = generate_ephemeral().unwrap();
+        let initiator_raw = get_ephemeral_public(&initiator_key).unwrap();
+        let responder_raw = get_ephemeral_public(&responder_key).unwrap();
+
+        let a = derive_session_key(&initiator_key, &responder_raw, &initiator, &initiator_raw, &responder, &responder_raw, &nonce).unwrap();
+        let b = derive_session_key(&responder_key, &initiator_raw, &initiator, &initiator_raw, &responder, &responder_raw, &nonce).unwrap();
+
+        assert_eq!(a.key, b.key);
+        assert_eq!(a.iv, b.iv);
+
+        let other_nonce = generate_nonce().unwrap();
+        let c = derive_session_key(&responder_key, &initiator_raw, &initiator, &initiator_raw, &responder, &responder_raw, &other_nonce).unwrap();
+        assert_ne!(a.key, c.key);
+    }
+}
@@ -1,35 +1,40 @@
-use openssl::{rsa::Rsa, pkey::{Private, Public}};
 use uuid::Uuid;
 
-use crate::{util::{converter::uuid_to_vec, modes::Modes, tools::uuid_from_vec}, other::key_iv::KeyIVPair};
+use crate::{types::ByteMessage, util::{converter::uuid_to_vec, modes::Modes, tools::uuid_from_vec, vec::extract_vec}, consts::X25519_KEY_BYTES};
 
+// Answer to WantSymmKeyMsg with the signed ephemeral key of the responder. The session key itself is never sent.
+// The signature covers the nonce and rekey flag of the request, which are not sent again.
 pub struct SymmKeyReplyMsg {
-    pub key: KeyIVPair,
     pub user: Uuid,
+    pub ephemeral: Vec<u8>,
+    pub signature: Vec<u8>,
 }
 
-impl SymmKeyReplyMsg {
-    pub fn serialize(&self, rec_key: Rsa<Public>) -> anyhow::Result<Vec<u8>> {
+impl ByteMessage for SymmKeyReplyMsg {
+    fn serialize(&self) -> Vec<u8> {
         let mut merged: Vec<u8> = Vec::new();
 
-        let mut b_encrypted_key = self.key.serialize(&rec_key)?;
         let mut b_user = uuid_to_vec(&self.user);
+        let mut b_ephemeral = self.ephemeral.clone();
+        let mut b_signature = self.signature.clone();
 
         merged.append(&mut b_user);
-        merged.append(&mut b_encrypted_key);
+        merged.append(&mut b_ephemeral);
+        merged.append(&mut b_signature);
 
-        return Ok(Modes::SymmKey.get_send(&merged));
+        return Modes::SymmKey.get_send(&merged);
     }
 
-    pub fn deserialize(data: &Vec<u8>, key: Rsa<Private>) -> anyhow::Result<Self> {
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
         let mut data = data.clone();
 
         let user = uuid_from_vec(&mut data)?;
+        let ephemeral = extract_vec(0..X25519_KEY_BYTES, &mut data)?;
 
-        let decrypted_key = KeyIVPair::deserialize_mut(&mut data, &key)?;
         return Ok(SymmKeyReplyMsg {
-            key: decrypted_key,
-            user
+            user,
+            ephemeral,
+            signature: data
         });
     }
-}
\ No newline at end of file
+}
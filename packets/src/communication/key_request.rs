@@ -2,10 +2,16 @@ use std::collections::VecDeque;
 
 use uuid::Uuid;
 
-use crate::{types::ByteMessage, util::{converter::uuid_to_decque, vec::decque_to_vec, modes::Modes, tools::uuid_from_vec}};
+use crate::{types::ByteMessage, util::{converter::{pop_front_vec, uuid_to_decque}, vec::{decque_to_vec, vec_to_decque, extract_vec}, modes::Modes, tools::uuid_from_vec}, consts::{HANDSHAKE_NONCE_BYTES, X25519_KEY_BYTES}};
 
+// Starts the key exchange. `ephemeral` is the raw x25519 public key of the initiator, signed with its rsa key
+// together with a fresh `nonce`, see `handshake::HandshakeBinding`. Without `rekey` an existing session is kept.
 pub struct WantSymmKeyMsg {
     pub user: Uuid,
+    pub ephemeral: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub rekey: bool,
+    pub signature: Vec<u8>,
 }
 
 impl ByteMessage for WantSymmKeyMsg {
@@ -13,7 +19,15 @@ impl ByteMessage for WantSymmKeyMsg {
         let mut merged: VecDeque<u8> = VecDeque::new();
 
         let mut b_user = uuid_to_decque(&self.user);
+        let mut b_ephemeral = vec_to_decque(self.ephemeral.clone());
+        let mut b_nonce = vec_to_decque(self.nonce.clone());
+        let mut b_signature = vec_to_decque(self.signature.clone());
+
         merged.append(&mut b_user);
+        merged.append(&mut b_ephemeral);
+        merged.append(&mut b_nonce);
+        merged.push_back(self.rekey as u8);
+        merged.append(&mut b_signature);
 
         return Modes::WantSymmKey.get_send(&decque_to_vec(merged));
     }
@@ -22,9 +36,16 @@ impl ByteMessage for WantSymmKeyMsg {
         let mut data = data.clone();
 
         let user = uuid_from_vec(&mut data)?;
+        let ephemeral = extract_vec(0..X25519_KEY_BYTES, &mut data)?;
+        let nonce = extract_vec(0..HANDSHAKE_NONCE_BYTES, &mut data)?;
+        let rekey = pop_front_vec(&mut data)? != 0;
 
         return Ok(WantSymmKeyMsg {
-            user
+            user,
+            ephemeral,
+            nonce,
+            rekey,
+            signature: data
         });
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,23 @@
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{converter::uuid_to_vec, modes::Modes, tools::uuid_from_vec}};
+
+// Sent to everyone when a user disconnects, its uuid is never used again
+pub struct UserLeftMsg {
+    pub user: Uuid,
+}
+
+impl ByteMessage for UserLeftMsg {
+    fn serialize(&self) -> Vec<u8> {
+        return Modes::UserLeft.get_send(&uuid_to_vec(&self.user));
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let user = uuid_from_vec(&mut data)?;
+        return Ok(UserLeftMsg {
+            user
+        });
+    }
+}
@@ -1,6 +1,9 @@
 pub mod from;
 pub mod to;
+pub mod to_offline;
+pub mod from_offline;
+pub mod left;
 pub mod error;
 pub mod key_reply;
 pub mod key_request;
-pub mod key_reply_encrypted;
\ No newline at end of file
+pub mod handshake;
\ No newline at end of file
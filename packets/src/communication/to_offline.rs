@@ -0,0 +1,31 @@
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{converter::uuid_to_vec, modes::Modes, tools::uuid_from_vec}};
+
+// Chat message for a user who is offline, sealed to their identity key so it survives a reconnect
+pub struct ToOfflineMsg {
+    pub msg: Vec<u8>,
+    pub receiver: Uuid,
+}
+
+impl ByteMessage for ToOfflineMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut uuid_to_vec(&self.receiver));
+        merged.append(&mut self.msg.clone());
+
+        return Modes::ToOffline.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let receiver = uuid_from_vec(&mut data)?;
+
+        return Ok(ToOfflineMsg {
+            msg: data,
+            receiver
+        });
+    }
+}
@@ -3,10 +3,22 @@ use openssl::{hash::MessageDigest, symm::Cipher, rsa::Padding};
 
 pub const UUID_SIZE: usize = 16;
 pub const U64_SIZE: usize = 8;
-pub const CHUNK_SIZE: u64 = 10 * 1000 * 1000 ; // 10 MB
-pub const CHUNK_SIZE_I64: i64 = CHUNK_SIZE as i64 ; // 10 MB
+// Chunk size is chosen per transfer, the server narrows the allowed range in the hello ack
+pub const DEFAULT_CHUNK_SIZE: u64 = 10 * 1000 * 1000 ; // 10 MB
+pub const MIN_CHUNK_SIZE: u64 = 64 * 1000 ; // 64 KB
+pub const MAX_CHUNK_SIZE: u64 = 100 * 1000 * 1000 ; // 100 MB
+// Largest file a peer may offer, keeps the chunk count of a question in bounds
+pub const MAX_FILE_SIZE: u64 = 16 * 1000 * 1000 * 1000 * 1000 ; // 16 TB
 
 pub const ONE_MB_SIZE: u64 = 1000 * 1000 ; // 1 MB
+// Chunks are read, encrypted and sent in pieces of this size
+pub const STREAM_PIECE_SIZE: usize = 64 * 1000 ; // 64 KB
+// zstd level of compressed chunks and chat messages, favours speed over ratio
+pub const ZSTD_LEVEL: i32 = 3;
+// Chat messages below this size are never compressed
+pub const MIN_COMPRESS_MSG_SIZE: usize = 128;
+// Upper bound of a decompressed chat message
+pub const MAX_DECOMPRESSED_MSG_SIZE: usize = 16 * 1000 * 1000 ; // 16 MB
 pub const AES_KEYSIZE_BITS: usize = 256;
 pub const AES_KEYSIZE_BYTES: usize = AES_KEYSIZE_BITS / 8;
 
@@ -17,8 +29,42 @@ pub const RSA_PADDING: Padding = Padding::PKCS1_OAEP;
 pub const AES_IVSIZE_BITS: usize = 128;
 pub const AES_IVSIZE_BYTES: usize = AES_IVSIZE_BITS / 8;
 
+pub const AEAD_NONCE_BYTES: usize = 12;
+pub const AEAD_TAG_BYTES: usize = 16;
+
+pub const X25519_KEY_BYTES: usize = 32;
+pub const HANDSHAKE_NONCE_BYTES: usize = 32;
+
+// Max message keys skipped in one chain and stored overall for out-of-order messages
+pub const RATCHET_MAX_SKIP: u64 = 1000;
+pub const RATCHET_MAX_STORED_SKIPPED: usize = 2000;
+// Receiver answers with an empty message after this many messages / seconds so the sender can do a DH step
+pub const RATCHET_HEARTBEAT_MESSAGES: u64 = 50;
+pub const RATCHET_HEARTBEAT_SECS: u64 = 60 * 60;
+
+// Amount of sequence numbers below the highest one that are still accepted (once).
+// Only used for control packets and room messages, chat messages are checked by the ratchet.
+pub const REPLAY_WINDOW_SIZE: u64 = 64;
+// Sealed messages older than this are dropped, receivers only remember the ids of younger ones
+pub const SEALED_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+// Allowed clock difference between the sender and the receiver of a sealed message
+pub const SEALED_MAX_SKEW_SECS: u64 = 60 * 60;
+
+pub const MAX_ROOM_NAME_LENGTH: usize = 32;
+// Group keys of this many previous epochs are kept to decrypt messages sent right before a re-key
+pub const ROOM_KEEP_EPOCHS: u64 = 4;
+
+// Receivers a single file can be offered to at once
+pub const MAX_RECEIVERS: usize = 32;
+
 
 lazy_static! {
     pub static ref AES_DIGEST: Cipher = Cipher::aes_256_cbc();
+    pub static ref AEAD_DIGEST: Cipher = Cipher::aes_256_gcm();
     pub static ref MSG_DIGEST: MessageDigest = MessageDigest:: sha256();
-}
\ No newline at end of file
+}
+
+// Version of the packet layout, sent in the hello packet. Bump on every incompatible change.
+pub const PROTOCOL_VERSION: u64 = 12;
+// Oldest version this build can still talk to
+pub const MIN_PROTOCOL_VERSION: u64 = 12;
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use openssl::{rand::rand_bytes, symm::{encrypt_aead, decrypt_aead}};
+use uuid::Uuid;
+
+use crate::{
+    consts::{AEAD_DIGEST, AEAD_NONCE_BYTES, AEAD_TAG_BYTES},
+    other::key_iv::KeyIVPair,
+    util::{converter::{pop_front_vec, uuid_to_vec}, vec::extract_vec},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    // Bare AES-256-CBC with a static iv, used by clients <= 0.1.4. Never sent with a suite byte
+    Aes256Cbc,
+    Aes256Gcm,
+}
+
+impl CipherSuite {
+    pub const CURRENT: CipherSuite = CipherSuite::Aes256Gcm;
+
+    pub fn get_indicator(self) -> u8 {
+        match self {
+            Self::Aes256Cbc => 0,
+            Self::Aes256Gcm => 1,
+        }
+    }
+
+    pub fn from_indicator(ind: u8) -> anyhow::Result<Self> {
+        match ind {
+            0 => Ok(Self::Aes256Cbc),
+            1 => Ok(Self::Aes256Gcm),
+            _ => Err(anyhow!("Unknown cipher suite {}. The other client is probably outdated.", ind)),
+        }
+    }
+}
+
+// Layout: suite (1 byte) | nonce | tag | ciphertext
+pub fn seal(key: &KeyIVPair, data: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0 as u8; AEAD_NONCE_BYTES];
+    rand_bytes(&mut nonce)?;
+
+    let mut tag = [0 as u8; AEAD_TAG_BYTES];
+    let mut encrypted = encrypt_aead(*AEAD_DIGEST, &key.key, Some(&nonce), aad, data, &mut tag)?;
+
+    let mut merged = Vec::new();
+    merged.push(CipherSuite::CURRENT.get_indicator());
+    merged.append(&mut nonce.to_vec());
+    merged.append(&mut tag.to_vec());
+    merged.append(&mut encrypted);
+
+    return Ok(merged);
+}
+
+pub fn open(key: &KeyIVPair, data: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut data = data.to_vec();
+
+    let suite = CipherSuite::from_indicator(pop_front_vec(&mut data)?)?;
+    if suite != CipherSuite::CURRENT {
+        return Err(anyhow!("Cipher suite {:?} is not supported anymore. The other client is outdated.", suite));
+    }
+
+    let nonce = extract_vec(0..AEAD_NONCE_BYTES, &mut data)?;
+    let tag = extract_vec(0..AEAD_TAG_BYTES, &mut data)?;
+
+    let decrypted = decrypt_aead(*AEAD_DIGEST, &key.key, Some(&nonce), aad, &data, &tag);
+    if decrypted.is_err() {
+        return Err(anyhow!("Could not authenticate message. It has been tampered with or was encrypted with another key."));
+    }
+
+    return Ok(decrypted.unwrap());
+}
+
+// Binds a chat message to its sender and receiver so the server can not redirect it
+pub fn get_chat_aad(sender: &Uuid, receiver: &Uuid) -> Vec<u8> {
+    let mut merged = Vec::new();
+    merged.append(&mut uuid_to_vec(sender));
+    merged.append(&mut uuid_to_vec(receiver));
+
+    return merged;
+}
+
+// Sealed messages outlive the connection, so they are bound to the identity keys instead of the uuids
+pub fn get_sealed_aad(sender_fingerprint: &[u8], receiver_fingerprint: &[u8]) -> Vec<u8> {
+    let mut merged = Vec::new();
+    merged.append(&mut sender_fingerprint.to_vec());
+    merged.append(&mut receiver_fingerprint.to_vec());
+
+    return merged;
+}
@@ -0,0 +1,49 @@
+use openssl::{hash::hash, pkey::Public, rsa::Rsa};
+
+use crate::consts::MSG_DIGEST;
+
+pub const SAFETY_NUMBER_GROUPS: usize = 6;
+pub const SAFETY_NUMBER_GROUP_BYTES: usize = 5;
+
+// Sha256 of the DER encoded public key
+pub fn get_fingerprint(pubkey: &Rsa<Public>) -> anyhow::Result<Vec<u8>> {
+    let der = pubkey.public_key_to_der()?;
+    let digest = hash(*MSG_DIGEST, &der)?;
+
+    return Ok(digest.to_vec());
+}
+
+pub fn format_fingerprint(fingerprint: &[u8]) -> String {
+    let hex = hex_encode(fingerprint);
+    let groups: Vec<String> = hex
+        .as_bytes()
+        .chunks(4)
+        .map(|e| String::from_utf8_lossy(e).to_string())
+        .collect();
+
+    return groups.join(" ");
+}
+
+// Short number both users can compare out of band, independent of who computes it
+pub fn get_safety_number(a: &Rsa<Public>, b: &Rsa<Public>) -> anyhow::Result<String> {
+    let mut fingerprints = vec![get_fingerprint(a)?, get_fingerprint(b)?];
+    fingerprints.sort();
+
+    let merged = fingerprints.concat();
+    let digest = hash(*MSG_DIGEST, &merged)?;
+
+    let groups: Vec<String> = digest
+        .chunks(SAFETY_NUMBER_GROUP_BYTES)
+        .take(SAFETY_NUMBER_GROUPS)
+        .map(|e| {
+            let numb = e.iter().fold(0 as u64, |acc, b| (acc << 8) | (*b as u64));
+            return format!("{:05}", numb % 100000);
+        })
+        .collect();
+
+    return Ok(groups.join(" "));
+}
+
+pub fn hex_encode(data: &[u8]) -> String {
+    return data.iter().map(|e| format!("{:02x}", e)).collect::<Vec<String>>().join("");
+}
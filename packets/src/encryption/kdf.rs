@@ -0,0 +1,43 @@
+use anyhow::anyhow;
+use openssl::{pkey::PKey, sign::Signer};
+
+use crate::consts::MSG_DIGEST;
+
+pub fn hmac(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let p_key = PKey::hmac(key)?;
+    let mut signer = Signer::new(*MSG_DIGEST, &p_key)?;
+    signer.update(data)?;
+
+    return Ok(signer.sign_to_vec()?);
+}
+
+// HKDF (RFC 5869) with sha256
+pub fn hkdf(ikm: &[u8], salt: &[u8], info: &[u8], length: usize) -> anyhow::Result<Vec<u8>> {
+    let hash_len = MSG_DIGEST.size();
+    if length > 255 * hash_len {
+        return Err(anyhow!("Can not derive more than {} bytes with hkdf", 255 * hash_len));
+    }
+
+    // A missing salt is a string of hash_len zeros (RFC 5869, section 2.2)
+    let zeros = vec![0; hash_len];
+    let salt = if salt.is_empty() { &zeros } else { salt };
+
+    let prk = hmac(salt, ikm)?;
+
+    let mut out = Vec::new();
+    let mut previous: Vec<u8> = Vec::new();
+    let mut counter: u8 = 1;
+
+    while out.len() < length {
+        let mut data = previous.clone();
+        data.append(&mut info.to_vec());
+        data.push(counter);
+
+        previous = hmac(&prk, &data)?;
+        out.append(&mut previous.clone());
+        counter += 1;
+    }
+
+    out.truncate(length);
+    return Ok(out);
+}
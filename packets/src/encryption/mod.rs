@@ -1 +1,10 @@
-pub mod sign;
\ No newline at end of file
+pub mod sign;
+pub mod fingerprint;
+
+pub mod aead;
+pub mod sealed;
+
+pub mod kdf;
+
+pub mod ratchet;
+pub mod replay;
@@ -0,0 +1,421 @@
+use std::{collections::{HashMap, VecDeque}, time::{Duration, Instant}};
+
+use anyhow::anyhow;
+use openssl::{derive::Deriver, memcmp, pkey::{Id, PKey, Private, Public}};
+
+use crate::{
+    consts::{RATCHET_HEARTBEAT_MESSAGES, RATCHET_HEARTBEAT_SECS, RATCHET_MAX_SKIP, RATCHET_MAX_STORED_SKIPPED, U64_SIZE, X25519_KEY_BYTES},
+    encryption::{aead::{open, seal}, kdf::{hkdf, hmac}, replay::{prepend_sequence, ReplayWindow}},
+    other::key_iv::KeyIVPair,
+    util::{tools::u64_from_vec, vec::extract_vec},
+};
+
+pub const RATCHET_ROOT_INFO: &[u8] = b"rsa-msg ratchet root";
+pub const RATCHET_INITIAL_INFO: &[u8] = b"rsa-msg ratchet responder chain";
+pub const CONTROL_KEY_INFO: &[u8] = b"rsa-msg control";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatchetHeader {
+    // Current ratchet public key of the sender
+    pub dh: Vec<u8>,
+    // Length of the previous sending chain
+    pub pn: u64,
+    pub n: u64,
+}
+
+impl RatchetHeader {
+    pub const BYTE_SIZE: usize = X25519_KEY_BYTES + U64_SIZE * 2;
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+        merged.append(&mut self.dh.clone());
+        merged.append(&mut self.pn.to_le_bytes().to_vec());
+        merged.append(&mut self.n.to_le_bytes().to_vec());
+
+        return merged;
+    }
+
+    pub fn deserialize_mut(data: &mut Vec<u8>) -> anyhow::Result<Self> {
+        let dh = extract_vec(0..X25519_KEY_BYTES, data)?;
+        let pn = u64_from_vec(data)?;
+        let n = u64_from_vec(data)?;
+
+        return Ok(RatchetHeader { dh, pn, n });
+    }
+}
+
+// Double ratchet (signal spec) seeded by the session key of the signed x25519 handshake.
+// The responder gets a sending chain right away, so both sides can write first.
+// Replayed messages are rejected by the ratchet itself, every message key is used once (skipped ones are removed when used).
+// Control packets carry their own sequence number, which is checked against a replay window.
+#[derive(Debug, Clone)]
+pub struct Ratchet {
+    root_key: Vec<u8>,
+    // Authenticates packets the server has to read (e.g. file questions)
+    control_key: Vec<u8>,
+
+    dh_self: PKey<Private>,
+    dh_remote: Option<Vec<u8>>,
+
+    send_chain: Option<Vec<u8>>,
+    recv_chain: Option<Vec<u8>>,
+
+    send_n: u64,
+    recv_n: u64,
+    prev_send_n: u64,
+
+    skipped: HashMap<(Vec<u8>, u64), Vec<u8>>,
+    skipped_order: VecDeque<(Vec<u8>, u64)>,
+
+    control_send_seq: u64,
+    control_window: ReplayWindow,
+
+    received_since_send: u64,
+    last_send: Instant,
+}
+
+fn dh(own: &PKey<Private>, remote: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let remote: PKey<Public> = PKey::public_key_from_raw_bytes(remote, Id::X25519)?;
+
+    let mut deriver = Deriver::new(own)?;
+    deriver.set_peer(&remote)?;
+
+    return Ok(deriver.derive_to_vec()?);
+}
+
+fn kdf_root(root_key: &[u8], dh_out: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut out = hkdf(dh_out, root_key, RATCHET_ROOT_INFO, 64)?;
+    let chain = out.split_off(32);
+
+    return Ok((out, chain));
+}
+
+fn kdf_chain(chain: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let message_key = hmac(chain, &[1])?;
+    let next_chain = hmac(chain, &[2])?;
+
+    return Ok((next_chain, message_key));
+}
+
+fn to_key(message_key: Vec<u8>) -> KeyIVPair {
+    return KeyIVPair { key: message_key, iv: Vec::new() };
+}
+
+impl Ratchet {
+    pub fn new_initiator(session_key: &KeyIVPair, responder_ephemeral: &[u8]) -> anyhow::Result<Self> {
+        let dh_self = PKey::generate_x25519()?;
+        let (root_key, send_chain) = kdf_root(&session_key.key, &dh(&dh_self, responder_ephemeral)?)?;
+        let recv_chain = hkdf(&session_key.key, &[], RATCHET_INITIAL_INFO, 32)?;
+
+        return Ok(Ratchet {
+            root_key,
+            control_key: hkdf(&session_key.key, &[], CONTROL_KEY_INFO, 32)?,
+            dh_self,
+            dh_remote: Some(responder_ephemeral.to_vec()),
+            send_chain: Some(send_chain),
+            recv_chain: Some(recv_chain),
+            send_n: 0,
+            recv_n: 0,
+            prev_send_n: 0,
+            skipped: HashMap::new(),
+            skipped_order: VecDeque::new(),
+            control_send_seq: 0,
+            control_window: ReplayWindow::default(),
+            received_since_send: 0,
+            last_send: Instant::now(),
+        });
+    }
+
+    pub fn new_responder(session_key: &KeyIVPair, own_ephemeral: PKey<Private>) -> anyhow::Result<Self> {
+        let send_chain = hkdf(&session_key.key, &[], RATCHET_INITIAL_INFO, 32)?;
+
+        return Ok(Ratchet {
+            root_key: session_key.key.clone(),
+            control_key: hkdf(&session_key.key, &[], CONTROL_KEY_INFO, 32)?,
+            dh_self: own_ephemeral,
+            dh_remote: None,
+            send_chain: Some(send_chain),
+            recv_chain: None,
+            send_n: 0,
+            recv_n: 0,
+            prev_send_n: 0,
+            skipped: HashMap::new(),
+            skipped_order: VecDeque::new(),
+            control_send_seq: 0,
+            control_window: ReplayWindow::default(),
+            received_since_send: 0,
+            last_send: Instant::now(),
+        });
+    }
+
+    // Returns header | aead envelope. `aad` is authenticated together with the header.
+    pub fn encrypt(&mut self, data: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if self.send_chain.is_none() {
+            return Err(anyhow!("Ratchet has no sending chain."));
+        }
+
+        let (next_chain, message_key) = kdf_chain(self.send_chain.as_ref().unwrap())?;
+        let header = RatchetHeader {
+            dh: self.dh_self.raw_public_key()?,
+            pn: self.prev_send_n,
+            n: self.send_n,
+        };
+
+        let mut b_header = header.serialize();
+        let mut full_aad = aad.to_vec();
+        full_aad.append(&mut b_header.clone());
+
+        let mut encrypted = seal(&to_key(message_key), data, &full_aad)?;
+
+        self.send_chain = Some(next_chain);
+        self.send_n += 1;
+        self.received_since_send = 0;
+        self.last_send = Instant::now();
+
+        let mut merged = Vec::new();
+        merged.append(&mut b_header);
+        merged.append(&mut encrypted);
+
+        return Ok(merged);
+    }
+
+    // State is only updated if the message could be authenticated
+    pub fn decrypt(&mut self, data: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut data = data.to_vec();
+        let header = RatchetHeader::deserialize_mut(&mut data)?;
+
+        let mut full_aad = aad.to_vec();
+        full_aad.append(&mut header.serialize());
+
+        let mut next = self.clone();
+        let skipped_key = next.skipped.remove(&(header.dh.clone(), header.n));
+        if skipped_key.is_some() {
+            next.skipped_order.retain(|e| e != &(header.dh.clone(), header.n));
+
+            let decrypted = open(&to_key(skipped_key.unwrap()), &data, &full_aad)?;
+
+            *self = next;
+            return Ok(decrypted);
+        }
+
+        let is_current_chain = next.dh_remote.as_ref() == Some(&header.dh);
+        if is_current_chain && header.n < next.recv_n {
+            return Err(anyhow!("Duplicate message (already received message {} of this chain).", header.n));
+        }
+
+        if next.dh_remote.as_ref() != Some(&header.dh) {
+            next.skip_message_keys(header.pn)?;
+            next.dh_step(&header.dh)?;
+        }
+
+        next.skip_message_keys(header.n)?;
+
+        let (next_chain, message_key) = kdf_chain(next.recv_chain.as_ref().unwrap())?;
+        next.recv_chain = Some(next_chain);
+        next.recv_n += 1;
+
+        let decrypted = open(&to_key(message_key), &data, &full_aad)?;
+
+        next.received_since_send += 1;
+        *self = next;
+        return Ok(decrypted);
+    }
+
+    // Sequence number and mac for a packet that can not be encrypted because the server has to read it
+    pub fn authenticate(&mut self, data: &[u8]) -> anyhow::Result<(u64, Vec<u8>)> {
+        let seq = self.control_send_seq + 1;
+        let mac = hmac(&self.control_key, &prepend_sequence(seq, data))?;
+
+        self.control_send_seq = seq;
+        return Ok((seq, mac));
+    }
+
+    pub fn verify(&mut self, seq: u64, mac: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        let expected = hmac(&self.control_key, &prepend_sequence(seq, data))?;
+        if expected.len() != mac.len() || !memcmp::eq(&expected, mac) {
+            return Err(anyhow!("Invalid authentication code of packet with sequence number {}.", seq));
+        }
+
+        self.control_window.check_and_update(seq)?;
+        return Ok(());
+    }
+
+    // True if the peer has not seen a new ratchet key from us for a while and we should send an (empty) message
+    pub fn needs_heartbeat(&self) -> bool {
+        if self.received_since_send == 0 {
+            return false;
+        }
+
+        let old = self.last_send.elapsed() >= Duration::from_secs(RATCHET_HEARTBEAT_SECS);
+        return self.received_since_send >= RATCHET_HEARTBEAT_MESSAGES || old;
+    }
+
+    fn skip_message_keys(&mut self, until: u64) -> anyhow::Result<()> {
+        if self.recv_chain.is_none() {
+            return Ok(());
+        }
+
+        if until > self.recv_n + RATCHET_MAX_SKIP {
+            return Err(anyhow!("Too many skipped messages ({}).", until - self.recv_n));
+        }
+
+        let dh_remote = self.dh_remote.clone().unwrap_or(Vec::new());
+        while self.recv_n < until {
+            let (next_chain, message_key) = kdf_chain(self.recv_chain.as_ref().unwrap())?;
+            self.recv_chain = Some(next_chain);
+
+            let id = (dh_remote.clone(), self.recv_n);
+            self.skipped.insert(id.clone(), message_key);
+            self.skipped_order.push_back(id);
+
+            self.recv_n += 1;
+        }
+
+        while self.skipped_order.len() > RATCHET_MAX_STORED_SKIPPED {
+            let oldest = self.skipped_order.pop_front().unwrap();
+            self.skipped.remove(&oldest);
+        }
+
+        return Ok(());
+    }
+
+    fn dh_step(&mut self, remote: &[u8]) -> anyhow::Result<()> {
+        self.prev_send_n = self.send_n;
+        self.send_n = 0;
+        self.recv_n = 0;
+        self.dh_remote = Some(remote.to_vec());
+
+        let (root_key, recv_chain) = kdf_root(&self.root_key, &dh(&self.dh_self, remote)?)?;
+        self.recv_chain = Some(recv_chain);
+
+        self.dh_self = PKey::generate_x25519()?;
+        let (root_key, send_chain) = kdf_root(&root_key, &dh(&self.dh_self, remote)?)?;
+
+        self.root_key = root_key;
+        self.send_chain = Some(send_chain);
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::pkey::PKey;
+
+    use crate::{consts::RATCHET_MAX_SKIP, other::key_iv::KeyIVPair};
+
+    use super::Ratchet;
+
+    const AAD: &[u8] = b"aad";
+
+    // Initiator and responder of the same session
+    fn get_pair() -> (Ratchet, Ratchet) {
+        let session_key = KeyIVPair::generate().unwrap();
+        let ephemeral = PKey::generate_x25519().unwrap();
+
+        let initiator = Ratchet::new_initiator(&session_key, &ephemeral.raw_public_key().unwrap()).unwrap();
+        let responder = Ratchet::new_responder(&session_key, ephemeral).unwrap();
+
+        return (initiator, responder);
+    }
+
+    fn encrypt_all(ratchet: &mut Ratchet, count: u8) -> Vec<Vec<u8>> {
+        return (0..count).map(|i| ratchet.encrypt(&[i], AAD).unwrap()).collect();
+    }
+
+    #[test]
+    fn decrypts_in_order() {
+        let (mut alice, mut bob) = get_pair();
+
+        for (i, msg) in encrypt_all(&mut alice, 3).iter().enumerate() {
+            assert_eq!(bob.decrypt(msg, AAD).unwrap(), vec![i as u8]);
+        }
+
+        for (i, msg) in encrypt_all(&mut bob, 3).iter().enumerate() {
+            assert_eq!(alice.decrypt(msg, AAD).unwrap(), vec![i as u8]);
+        }
+
+        // Second ratchet step of both sides
+        let msg = alice.encrypt(b"again", AAD).unwrap();
+        assert_eq!(bob.decrypt(&msg, AAD).unwrap(), b"again".to_vec());
+    }
+
+    #[test]
+    fn responder_can_write_first() {
+        let (mut alice, mut bob) = get_pair();
+
+        let msg = bob.encrypt(b"first", AAD).unwrap();
+        assert_eq!(alice.decrypt(&msg, AAD).unwrap(), b"first".to_vec());
+    }
+
+    #[test]
+    fn decrypts_out_of_order() {
+        let (mut alice, mut bob) = get_pair();
+        let msgs = encrypt_all(&mut alice, 3);
+
+        assert_eq!(bob.decrypt(&msgs[2], AAD).unwrap(), vec![2]);
+        assert_eq!(bob.decrypt(&msgs[0], AAD).unwrap(), vec![0]);
+        assert_eq!(bob.decrypt(&msgs[1], AAD).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn decrypts_skipped_messages_of_previous_chain() {
+        let (mut alice, mut bob) = get_pair();
+        let msgs = encrypt_all(&mut alice, 2);
+        assert_eq!(bob.decrypt(&msgs[0], AAD).unwrap(), vec![0]);
+
+        // Alice gets a new sending chain, message 1 of the old one is still in flight
+        let reply = bob.encrypt(b"reply", AAD).unwrap();
+        assert_eq!(alice.decrypt(&reply, AAD).unwrap(), b"reply".to_vec());
+
+        let next = alice.encrypt(b"next", AAD).unwrap();
+        assert_eq!(bob.decrypt(&next, AAD).unwrap(), b"next".to_vec());
+        assert_eq!(bob.decrypt(&msgs[1], AAD).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let (mut alice, mut bob) = get_pair();
+        let msgs = encrypt_all(&mut alice, 2);
+
+        assert!(bob.decrypt(&msgs[1], AAD).is_ok());
+        assert!(bob.decrypt(&msgs[1], AAD).is_err());
+
+        // Skipped keys are removed once they have been used
+        assert!(bob.decrypt(&msgs[0], AAD).is_ok());
+        assert!(bob.decrypt(&msgs[0], AAD).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_messages_without_changing_state() {
+        let (mut alice, mut bob) = get_pair();
+        let msg = alice.encrypt(b"hello", AAD).unwrap();
+
+        let mut tampered = msg.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+
+        assert!(bob.decrypt(&tampered, AAD).is_err());
+        assert!(bob.decrypt(&msg, b"other aad").is_err());
+        assert_eq!(bob.decrypt(&msg, AAD).unwrap(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn rejects_too_many_skipped_messages() {
+        let (mut alice, mut bob) = get_pair();
+        let msgs: Vec<Vec<u8>> = (0..RATCHET_MAX_SKIP + 2).map(|_| alice.encrypt(b"", AAD).unwrap()).collect();
+
+        assert!(bob.decrypt(msgs.last().unwrap(), AAD).is_err());
+        assert!(bob.decrypt(&msgs[0], AAD).is_ok());
+    }
+
+    #[test]
+    fn verifies_control_packets_once() {
+        let (mut alice, mut bob) = get_pair();
+
+        let (seq, mac) = alice.authenticate(b"question").unwrap();
+        assert!(bob.verify(seq, &mac, b"other question").is_err());
+        assert!(bob.verify(seq, &mac, b"question").is_ok());
+        assert!(bob.verify(seq, &mac, b"question").is_err());
+    }
+}
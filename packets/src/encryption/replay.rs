@@ -0,0 +1,131 @@
+use anyhow::anyhow;
+
+use crate::{consts::{REPLAY_WINDOW_SIZE, U64_SIZE}, util::tools::u64_from_vec};
+
+// Sliding window over received sequence numbers (like IPsec / DTLS).
+// Sequence numbers start at 1, bit 0 of `seen` is `highest`.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayWindow {
+    highest: u64,
+    seen: u64,
+}
+
+impl ReplayWindow {
+    pub fn check(&self, seq: u64) -> anyhow::Result<()> {
+        if seq == 0 {
+            return Err(anyhow!("Invalid sequence number 0."));
+        }
+
+        if seq > self.highest {
+            return Ok(());
+        }
+
+        let offset = self.highest - seq;
+        if offset >= REPLAY_WINDOW_SIZE {
+            return Err(anyhow!("Sequence number {} is too old (highest is {}).", seq, self.highest));
+        }
+
+        if self.seen & (1 << offset) != 0 {
+            return Err(anyhow!("Replayed packet with sequence number {}.", seq));
+        }
+
+        return Ok(());
+    }
+
+    pub fn update(&mut self, seq: u64) {
+        if seq > self.highest {
+            let shift = seq - self.highest;
+            self.seen = if shift >= REPLAY_WINDOW_SIZE { 0 } else { self.seen << shift };
+            self.seen |= 1;
+            self.highest = seq;
+            return;
+        }
+
+        self.seen |= 1 << (self.highest - seq);
+    }
+
+    pub fn check_and_update(&mut self, seq: u64) -> anyhow::Result<()> {
+        self.check(seq)?;
+        self.update(seq);
+
+        return Ok(());
+    }
+}
+
+pub fn prepend_sequence(seq: u64, data: &[u8]) -> Vec<u8> {
+    let mut merged = Vec::with_capacity(U64_SIZE + data.len());
+    merged.append(&mut seq.to_le_bytes().to_vec());
+    merged.append(&mut data.to_vec());
+
+    return merged;
+}
+
+pub fn split_sequence(data: &[u8]) -> anyhow::Result<(u64, Vec<u8>)> {
+    let mut data = data.to_vec();
+    let seq = u64_from_vec(&mut data)?;
+
+    return Ok((seq, data));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consts::REPLAY_WINDOW_SIZE;
+
+    use super::ReplayWindow;
+
+    #[test]
+    fn accepts_increasing_sequence_numbers() {
+        let mut window = ReplayWindow::default();
+        for seq in 1..=REPLAY_WINDOW_SIZE * 2 {
+            assert!(window.check_and_update(seq).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_zero() {
+        let mut window = ReplayWindow::default();
+        assert!(window.check_and_update(0).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let mut window = ReplayWindow::default();
+        assert!(window.check_and_update(1).is_ok());
+        assert!(window.check_and_update(1).is_err());
+
+        // Also once they are no longer the highest one
+        assert!(window.check_and_update(5).is_ok());
+        assert!(window.check_and_update(1).is_err());
+        assert!(window.check_and_update(5).is_err());
+    }
+
+    #[test]
+    fn accepts_reordered_packets_inside_the_window() {
+        let mut window = ReplayWindow::default();
+        assert!(window.check_and_update(10).is_ok());
+        assert!(window.check_and_update(8).is_ok());
+        assert!(window.check_and_update(9).is_ok());
+        assert!(window.check_and_update(8).is_err());
+    }
+
+    #[test]
+    fn rejects_too_old_packets() {
+        let mut window = ReplayWindow::default();
+        let highest = REPLAY_WINDOW_SIZE + 10;
+        assert!(window.check_and_update(highest).is_ok());
+
+        assert!(window.check_and_update(highest - REPLAY_WINDOW_SIZE).is_err());
+        assert!(window.check_and_update(highest - REPLAY_WINDOW_SIZE + 1).is_ok());
+    }
+
+    #[test]
+    fn forgets_the_window_after_a_large_jump() {
+        let mut window = ReplayWindow::default();
+        assert!(window.check_and_update(1).is_ok());
+        assert!(window.check_and_update(1 + REPLAY_WINDOW_SIZE * 3).is_ok());
+
+        // Everything below the new window is too old, everything inside it is unseen
+        assert!(window.check_and_update(1).is_err());
+        assert!(window.check_and_update(2 + REPLAY_WINDOW_SIZE * 2).is_ok());
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use openssl::{pkey::{Private, Public}, rand::rand_bytes, rsa::Rsa};
+use uuid::Uuid;
+
+use crate::{
+    consts::{SEALED_MAX_AGE_SECS, SEALED_MAX_SKEW_SECS, UUID_SIZE},
+    other::key_iv::KeyIVPair,
+    util::{converter::uuid_to_vec, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}, vec::extract_vec},
+};
+
+use super::{aead::{get_sealed_aad, open, seal}, fingerprint::get_fingerprint, sign::{get_signature, validate_signature}};
+
+// Plaintext of a sealed message. The server could hand it out any number of times,
+// so receivers remember the id until the message is too old to be accepted anyway.
+#[derive(Debug, Clone)]
+pub struct SealedContent {
+    pub id: Uuid,
+    // Unix timestamp in seconds
+    pub sent_at: u64,
+    pub data: Vec<u8>,
+}
+
+impl SealedContent {
+    pub fn is_expired(&self, now: u64) -> bool {
+        return is_sealed_expired(self.sent_at, now);
+    }
+}
+
+pub fn is_sealed_expired(sent_at: u64, now: u64) -> bool {
+    return sent_at + SEALED_MAX_AGE_SECS < now || sent_at > now + SEALED_MAX_SKEW_SECS;
+}
+
+pub fn get_unix_secs() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map(|e| e.as_secs()).unwrap_or(0);
+}
+
+// Messages for offline users are sealed to their identity key, a ratchet is gone once its connection closes.
+// Layout: `rsa encrypted key | sig len | signature | cipher suite | nonce | tag | ciphertext`,
+// the signature of the sender covers the aad, the encrypted key and the aead envelope.
+// The plaintext is `message id | sent at | data`.
+pub fn seal_to_identity(data: &[u8], sender: &Rsa<Private>, receiver: &Rsa<Public>) -> anyhow::Result<Vec<u8>> {
+    let sender_pub = Rsa::public_key_from_pem(&sender.public_key_to_pem()?)?;
+    let aad = get_sealed_aad(&get_fingerprint(&sender_pub)?, &get_fingerprint(receiver)?);
+
+    let mut id = [0 as u8; UUID_SIZE];
+    rand_bytes(&mut id)?;
+
+    let mut plain = uuid_to_vec(&Uuid::from_bytes(id));
+    plain.append(&mut get_unix_secs().to_le_bytes().to_vec());
+    plain.append(&mut data.to_vec());
+
+    let key = KeyIVPair::generate()?;
+    let mut wrapped = key.serialize(receiver)?;
+    let mut envelope = seal(&key, &plain, &aad)?;
+
+    let mut signed = aad.clone();
+    signed.append(&mut wrapped.clone());
+    signed.append(&mut envelope.clone());
+
+    let mut signature = get_signature(&signed, sender)?;
+
+    let mut merged = Vec::new();
+    merged.append(&mut wrapped);
+    merged.append(&mut usize_to_vec(signature.len())?);
+    merged.append(&mut signature);
+    merged.append(&mut envelope);
+
+    return Ok(merged);
+}
+
+pub fn open_from_identity(data: &[u8], receiver: &Rsa<Private>, sender: &Rsa<Public>) -> anyhow::Result<SealedContent> {
+    let receiver_pub = Rsa::public_key_from_pem(&receiver.public_key_to_pem()?)?;
+    let aad = get_sealed_aad(&get_fingerprint(sender)?, &get_fingerprint(&receiver_pub)?);
+
+    let mut data = data.to_vec();
+    let raw = data.clone();
+
+    let key = KeyIVPair::deserialize_mut(&mut data, receiver)?;
+    let wrapped = raw[0..raw.len() - data.len()].to_vec();
+
+    let sig_len = vec_to_usize(&mut data)?;
+    let signature = extract_vec(0..sig_len, &mut data)?;
+
+    let mut signed = aad.clone();
+    signed.append(&mut wrapped.clone());
+    signed.append(&mut data.clone());
+
+    let valid = validate_signature(&signed, &signature, sender)?;
+    if !valid {
+        return Err(anyhow!("Invalid signature of the sender."));
+    }
+
+    let mut plain = open(&key, &data, &aad)?;
+    let id = uuid_from_vec(&mut plain)?;
+    let sent_at = u64_from_vec(&mut plain)?;
+
+    return Ok(SealedContent { id, sent_at, data: plain });
+}
@@ -0,0 +1,114 @@
+use std::io::Write;
+
+use anyhow::anyhow;
+use zstd::stream::{raw::{Decoder, Operation}, write::Encoder};
+
+use crate::{consts::{STREAM_PIECE_SIZE, ZSTD_LEVEL}, util::compression::Compression};
+
+// Compresses a chunk piece by piece before it is encrypted, passes pieces through for `Compression::None`
+pub struct ChunkCompressor {
+    encoder: Option<Encoder<'static, Vec<u8>>>
+}
+
+impl ChunkCompressor {
+    pub fn new(compression: Compression) -> anyhow::Result<Self> {
+        let encoder = match compression {
+            Compression::None => None,
+            Compression::Zstd => Some(Encoder::new(Vec::new(), ZSTD_LEVEL)?),
+        };
+
+        return Ok(ChunkCompressor { encoder });
+    }
+
+    // May return nothing, zstd buffers data until it has a full block
+    pub fn update(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if self.encoder.is_none() {
+            return Ok(data.to_vec());
+        }
+
+        let encoder = self.encoder.as_mut().unwrap();
+        encoder.write_all(data)?;
+
+        return Ok(std::mem::take(encoder.get_mut()));
+    }
+
+    pub fn finalize(self) -> anyhow::Result<Vec<u8>> {
+        if self.encoder.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let out = self.encoder.unwrap().finish()?;
+        return Ok(out);
+    }
+}
+
+// Decompresses the decrypted pieces of a chunk. Never returns more than `size` bytes in total,
+// so a small malicious chunk can not fill the disk of the receiver.
+pub struct ChunkDecompressor {
+    decoder: Option<Decoder<'static>>,
+    size: u64,
+    written: u64,
+    frame_done: bool
+}
+
+impl ChunkDecompressor {
+    pub fn new(compression: Compression, size: u64) -> anyhow::Result<Self> {
+        let decoder = match compression {
+            Compression::None => None,
+            Compression::Zstd => Some(Decoder::new()?),
+        };
+
+        return Ok(ChunkDecompressor { decoder, size, written: 0, frame_done: false });
+    }
+
+    fn add_written(&mut self, len: usize) -> anyhow::Result<()> {
+        self.written += len as u64;
+        if self.written > self.size {
+            return Err(anyhow!("Chunk is larger than {} bytes after decompressing.", self.size));
+        }
+
+        return Ok(());
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if self.decoder.is_none() {
+            self.add_written(data.len())?;
+            return Ok(data.to_vec());
+        }
+
+        let mut res = Vec::new();
+        let mut input = data;
+        let mut out = vec![0 as u8; STREAM_PIECE_SIZE];
+        loop {
+            let status = self.decoder.as_mut().unwrap().run_on_buffers(input, &mut out)?;
+            input = &input[status.bytes_read..];
+
+            self.add_written(status.bytes_written)?;
+            res.extend_from_slice(&out[..status.bytes_written]);
+
+            if status.remaining == 0 {
+                self.frame_done = true;
+            }
+
+            // A full output buffer means the decoder may still hold data
+            if input.is_empty() && status.bytes_written < out.len() {
+                break;
+            }
+        }
+
+        return Ok(res);
+    }
+
+    // Checks that the whole chunk has been decompressed
+    pub fn finalize(self) -> anyhow::Result<()> {
+        if self.decoder.is_some() && !self.frame_done {
+            return Err(anyhow!("Compressed chunk ended unexpectedly."));
+        }
+
+        if self.written != self.size {
+            return Err(anyhow!("Chunk has {} bytes after decompressing, expected {}.", self.written, self.size));
+        }
+
+        return Ok(());
+    }
+}
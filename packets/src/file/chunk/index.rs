@@ -1,68 +1,255 @@
 use anyhow::anyhow;
 
-use openssl::pkey::{Public, Private};
+use openssl::pkey::{PKey, Public, Private};
 use openssl::rsa::Rsa;
+use openssl::sign::{Signer, Verifier};
+use openssl::symm::{Crypter, Mode};
 use uuid::Uuid;
 
-use crate::encryption::sign::validate_signature;
+use crate::consts::{AES_DIGEST, MSG_DIGEST, U64_SIZE};
 use crate::other::key_iv::KeyIVPair;
-use crate::util::tools::{u64_from_vec, usize_to_vec};
-use crate::util::{tools::{vec_to_usize, uuid_from_vec}, vec::extract_vec};
-
-
+use crate::util::compression::Compression;
+use crate::util::converter::pop_front_vec;
+use crate::util::tools::{u64_from_vec, usize_to_vec, vec_to_usize};
+use crate::util::tools::uuid_from_vec;
+use crate::util::vec::extract_vec;
+
+// Layout of an uploaded chunk, the server stores and serves it unchanged:
+// `uuid | chunk index | compression | key | encrypted size | encrypted | signature size | signature`
+// The signature over `encrypted` comes last, so both sides can process a chunk as a stream.
+// The server stores `encrypted | signature size | signature` once as a blob addressed by its sha256,
+// only the header with the wrapped key differs between receivers.
 #[derive(Debug, Clone)]
-pub struct ChunkMsg {
-    pub signature: Vec<u8>,
-    pub encrypted: Vec<u8>,
-    pub key: KeyIVPair,
+pub struct ChunkHeader {
     pub uuid: Uuid,
-    pub chunk_index: u64
+    pub chunk_index: u64,
+    // Data is compressed before it is encrypted
+    pub compression: Compression,
+    pub key: KeyIVPair,
+    pub encrypted_size: u64
 }
 
 
-impl ChunkMsg {
+impl ChunkHeader {
     pub fn serialize(&self, receiver_key: &Rsa<Public>) -> anyhow::Result<Vec<u8>> {
         let mut merged = Vec::new();
-        let mut b_encrypted = self.encrypted.clone();
         let mut b_key = self.key.serialize(receiver_key)?;
 
-        let signature_size = usize_to_vec(self.signature.len())?;
-        let mut b_uuid = self.uuid.clone().as_bytes().to_vec();
-
-        let mut b_chunk_index = self.chunk_index.clone().to_le_bytes().to_vec();
-
-        merged.append(&mut signature_size.clone());
-        merged.append(&mut self.signature.clone());
-        merged.append(&mut b_uuid);
-        merged.append(&mut b_chunk_index);
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.chunk_index.to_le_bytes().to_vec());
+        merged.push(self.compression.get_indicator());
         merged.append(&mut b_key);
-        merged.append(&mut b_encrypted);
+        merged.append(&mut self.encrypted_size.to_le_bytes().to_vec());
 
         return Ok(merged);
     }
 
-    pub fn deserialize(data: &Vec<u8>, sender_pubkey: &Rsa<Public>, receiver_key: &Rsa<Private>) -> anyhow::Result<Self> {
+    pub fn deserialize(data: &Vec<u8>, receiver_key: &Rsa<Private>) -> anyhow::Result<Self> {
         let mut data = data.clone();
 
-        let signature_size = vec_to_usize(&mut data)?;
-        let signature = extract_vec(0..signature_size, &mut data)?;
+        let uuid = uuid_from_vec(&mut data)?;
+        let chunk_index = u64_from_vec(&mut data)?;
+        let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
+        let key = KeyIVPair::deserialize_mut(&mut data, receiver_key)?;
+        let encrypted_size = u64_from_vec(&mut data)?;
+
+        if !data.is_empty() {
+            return Err(anyhow!("Trailing bytes after chunk header."));
+        }
+
+        return Ok(ChunkHeader {
+            uuid,
+            chunk_index,
+            compression,
+            key,
+            encrypted_size
+        });
+    }
+}
+
+// Fields of a chunk header the server can read without the key of the receiver
+#[derive(Debug, Clone)]
+pub struct ChunkHeaderInfo {
+    pub uuid: Uuid,
+    pub chunk_index: u64,
+    pub compression: Compression,
+    pub encrypted_size: u64
+}
+
+impl ChunkHeaderInfo {
+    pub fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
 
         let uuid = uuid_from_vec(&mut data)?;
         let chunk_index = u64_from_vec(&mut data)?;
+        let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
 
-        let key = KeyIVPair::deserialize_mut(&mut data, receiver_key)?;
+        // Wrapped key and iv
+        for _ in 0..2 {
+            let size = vec_to_usize(&mut data)?;
+            extract_vec(0..size, &mut data)?;
+        }
 
-        let valid = validate_signature(&data, &signature, sender_pubkey)?;
-        if !valid {
-            return Err(anyhow!("Invalid signature in ChunkByteMsg"));
+        let encrypted_size = u64_from_vec(&mut data)?;
+        if !data.is_empty() {
+            return Err(anyhow!("Trailing bytes after chunk header."));
         }
 
-        return Ok(ChunkMsg {
-            signature,
-            encrypted: data,
+        return Ok(ChunkHeaderInfo {
             uuid,
             chunk_index,
-            key
+            compression,
+            encrypted_size
         });
     }
+}
+
+// Body of `/file/link`, stores a chunk whose blob the server already has (e.g. the same file sent to someone else):
+// `header size | header | blob hash | signature size | signature`. The sender signs header and blob hash.
+#[derive(Debug, Clone)]
+pub struct ChunkLink {
+    pub header: Vec<u8>,
+    pub blob_hash: Vec<u8>,
+    pub signature: Vec<u8>
+}
+
+impl ChunkLink {
+    // Everything the signature is computed over
+    pub fn get_auth_data(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+        merged.append(&mut self.header.clone());
+        merged.append(&mut self.blob_hash.clone());
+
+        return merged;
+    }
+
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut usize_to_vec(self.header.len())?);
+        merged.append(&mut self.header.clone());
+        merged.append(&mut self.blob_hash.clone());
+        merged.append(&mut usize_to_vec(self.signature.len())?);
+        merged.append(&mut self.signature.clone());
+
+        return Ok(merged);
+    }
+
+    pub fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let header_size = vec_to_usize(&mut data)?;
+        let header = extract_vec(0..header_size, &mut data)?;
+        let blob_hash = extract_vec(0..MSG_DIGEST.size(), &mut data)?;
+
+        let signature_size = vec_to_usize(&mut data)?;
+        let signature = extract_vec(0..signature_size, &mut data)?;
+        if !data.is_empty() {
+            return Err(anyhow!("Trailing bytes after chunk link."));
+        }
+
+        return Ok(ChunkLink {
+            header,
+            blob_hash,
+            signature
+        });
+    }
+}
+
+// Size of `size` bytes after encrypting them (padding always adds up to one block)
+pub fn get_encrypted_size(size: u64) -> u64 {
+    let block = AES_DIGEST.block_size() as u64;
+    return (size / block + 1) * block;
+}
+
+// Compressed chunks are only sent if they are smaller than the plain ones
+pub fn is_valid_encrypted_size(encrypted_size: u64, chunk_size: u64, compression: Compression) -> bool {
+    let expected = get_encrypted_size(chunk_size);
+    if compression == Compression::None {
+        return encrypted_size == expected;
+    }
+
+    let block = AES_DIGEST.block_size() as u64;
+    return encrypted_size > 0 && encrypted_size <= expected && encrypted_size % block == 0;
+}
+
+// Encrypts a chunk piece by piece and signs the encrypted data
+pub struct ChunkEncryptor<'a> {
+    crypter: Crypter,
+    signer: Signer<'a>
+}
+
+impl<'a> ChunkEncryptor<'a> {
+    pub fn new(key: &KeyIVPair, keypair: &'a PKey<Private>) -> anyhow::Result<Self> {
+        let crypter = Crypter::new(*AES_DIGEST, Mode::Encrypt, &key.key, Some(&key.iv))?;
+        let signer = Signer::new(*MSG_DIGEST, keypair)?;
+
+        return Ok(ChunkEncryptor { crypter, signer });
+    }
+
+    // Bytes added after the encrypted data by `finalize`
+    pub fn get_trailer_size(keypair: &PKey<Private>) -> usize {
+        return U64_SIZE + keypair.size();
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut out = vec![0 as u8; data.len() + AES_DIGEST.block_size()];
+        let len = self.crypter.update(data, &mut out)?;
+        out.truncate(len);
+
+        self.signer.update(&out)?;
+        return Ok(out);
+    }
+
+    // Returns the last encrypted block followed by `signature size | signature`
+    pub fn finalize(mut self) -> anyhow::Result<Vec<u8>> {
+        let mut out = vec![0 as u8; AES_DIGEST.block_size()];
+        let len = self.crypter.finalize(&mut out)?;
+        out.truncate(len);
+
+        self.signer.update(&out)?;
+        let mut signature = self.signer.sign_to_vec()?;
+
+        out.append(&mut usize_to_vec(signature.len())?);
+        out.append(&mut signature);
+        return Ok(out);
+    }
+}
+
+// Decrypts a chunk piece by piece, the signature is checked once all data has been read
+pub struct ChunkDecryptor<'a> {
+    crypter: Crypter,
+    verifier: Verifier<'a>
+}
+
+impl<'a> ChunkDecryptor<'a> {
+    pub fn new(key: &KeyIVPair, sender_key: &'a PKey<Public>) -> anyhow::Result<Self> {
+        let crypter = Crypter::new(*AES_DIGEST, Mode::Decrypt, &key.key, Some(&key.iv))?;
+        let verifier = Verifier::new(*MSG_DIGEST, sender_key)?;
+
+        return Ok(ChunkDecryptor { crypter, verifier });
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.verifier.update(data)?;
+
+        let mut out = vec![0 as u8; data.len() + AES_DIGEST.block_size()];
+        let len = self.crypter.update(data, &mut out)?;
+        out.truncate(len);
+
+        return Ok(out);
+    }
+
+    // Returns the last decrypted bytes if the signature is valid
+    pub fn finalize(mut self, signature: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if !self.verifier.verify(signature)? {
+            return Err(anyhow!("Invalid signature of chunk"));
+        }
+
+        let mut out = vec![0 as u8; AES_DIGEST.block_size()];
+        let len = self.crypter.finalize(&mut out)?;
+        out.truncate(len);
+
+        return Ok(out);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,215 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use crate::{consts::MSG_DIGEST, util::{compression::Compression, converter::pop_front_vec, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}, vec::extract_vec}};
+
+use super::{processing::tools::{get_max_chunks, is_valid_chunk_size}, types::FileInfo};
+
+// A receiver which accepted the transfer
+#[derive(Debug, Clone)]
+pub struct JournalReceiver {
+    // Position of the receiver in the offer, the server stores chunk files per position
+    pub position: usize,
+    pub fingerprint: Vec<u8>,
+    // Chunks stored on the server for this receiver
+    pub uploaded: BTreeSet<u64>,
+    // Chunks written to the file of this receiver
+    pub downloaded: BTreeSet<u64>,
+}
+
+impl JournalReceiver {
+    pub fn new(position: usize, fingerprint: Vec<u8>) -> Self {
+        return JournalReceiver {
+            position,
+            fingerprint,
+            uploaded: BTreeSet::new(),
+            downloaded: BTreeSet::new(),
+        };
+    }
+}
+
+// Persisted state of a transfer so it can be resumed after a reconnect or restart.
+// Used by the server and both clients, each one fills in what it knows.
+// The journal of a receiving client only lists the client itself.
+#[derive(Debug, Clone)]
+pub struct TransferJournal {
+    pub uuid: Uuid,
+    pub filename: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    pub compression: Compression,
+    pub hash: Vec<u8>,
+    pub chunk_hashes: Vec<Vec<u8>>,
+    pub path: Option<PathBuf>,
+    // Fingerprints of the rsa keys as connection ids change on every reconnect
+    pub sender: Vec<u8>,
+    pub receivers: Vec<JournalReceiver>,
+}
+
+impl TransferJournal {
+    pub fn new(uuid: &Uuid, info: &FileInfo, sender: Vec<u8>, receivers: Vec<JournalReceiver>) -> Self {
+        return TransferJournal {
+            uuid: uuid.clone(),
+            filename: info.filename.clone(),
+            size: info.size,
+            chunk_size: info.chunk_size,
+            compression: info.compression,
+            hash: info.hash.clone(),
+            chunk_hashes: info.chunk_hashes.clone(),
+            path: info.path.clone(),
+            sender,
+            receivers,
+        };
+    }
+
+    pub fn to_file_info(&self, sender: &Uuid, receiver: &Uuid) -> FileInfo {
+        return FileInfo {
+            path: self.path.clone(),
+            filename: self.filename.clone(),
+            size: self.size,
+            chunk_size: self.chunk_size,
+            compression: self.compression,
+            receiver: receiver.clone(),
+            sender: sender.clone(),
+            hash: self.hash.clone(),
+            chunk_hashes: self.chunk_hashes.clone(),
+        };
+    }
+
+    pub fn get_max_chunks(&self) -> u64 {
+        return get_max_chunks(self.size, self.chunk_size);
+    }
+
+    pub fn is_valid_chunk(&self, chunk_index: u64) -> bool {
+        return chunk_index < self.get_max_chunks();
+    }
+
+    pub fn get_receiver(&self, fingerprint: &Vec<u8>) -> Option<&JournalReceiver> {
+        return self.receivers.iter().find(|e| e.fingerprint == *fingerprint);
+    }
+
+    pub fn get_receiver_mut(&mut self, position: usize) -> Option<&mut JournalReceiver> {
+        return self.receivers.iter_mut().find(|e| e.position == position);
+    }
+
+    // Chunks every receiver has written to its file
+    pub fn get_finished(&self) -> BTreeSet<u64> {
+        if self.receivers.is_empty() {
+            return BTreeSet::new();
+        }
+
+        return self.receivers[0].downloaded.iter()
+            .filter(|e| self.receivers.iter().all(|r| r.downloaded.contains(e)))
+            .cloned()
+            .collect();
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.get_finished().len() as u64 >= self.get_max_chunks();
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.size.to_le_bytes().to_vec());
+        merged.append(&mut self.chunk_size.to_le_bytes().to_vec());
+        merged.push(self.compression.get_indicator());
+
+        let chunk_hashes = self.chunk_hashes.concat();
+        for field in [&self.hash, &chunk_hashes, &self.sender] {
+            merged.append(&mut usize_to_vec(field.len()).unwrap());
+            merged.append(&mut field.clone());
+        }
+
+        merged.append(&mut usize_to_vec(self.receivers.len()).unwrap());
+        for receiver in &self.receivers {
+            merged.append(&mut usize_to_vec(receiver.position).unwrap());
+            merged.append(&mut usize_to_vec(receiver.fingerprint.len()).unwrap());
+            merged.append(&mut receiver.fingerprint.clone());
+
+            for chunks in [&receiver.uploaded, &receiver.downloaded] {
+                merged.append(&mut usize_to_vec(chunks.len()).unwrap());
+                for chunk in chunks {
+                    merged.append(&mut chunk.to_le_bytes().to_vec());
+                }
+            }
+        }
+
+        let path = self.path.as_ref().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+        merged.append(&mut usize_to_vec(path.len()).unwrap());
+        merged.append(&mut path.as_bytes().to_vec());
+        merged.append(&mut self.filename.as_bytes().to_vec());
+
+        return merged;
+    }
+
+    pub fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let uuid = uuid_from_vec(&mut data)?;
+        let size = u64_from_vec(&mut data)?;
+        let chunk_size = u64_from_vec(&mut data)?;
+        if !is_valid_chunk_size(chunk_size) {
+            return Err(anyhow!("Invalid chunk size in journal ({}).", chunk_size));
+        }
+
+        let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
+
+        let mut fields = Vec::new();
+        for _ in 0..3 {
+            let len = vec_to_usize(&mut data)?;
+            fields.push(extract_vec(0..len, &mut data)?);
+        }
+
+        // Every receiver takes at least its position, fingerprint length and two chunk counts
+        let receiver_count = vec_to_usize(&mut data)?;
+        if receiver_count > data.len() / 32 {
+            return Err(anyhow!("Invalid amount of receivers in journal ({}).", receiver_count));
+        }
+
+        let mut receivers = Vec::with_capacity(receiver_count);
+        for _ in 0..receiver_count {
+            let position = vec_to_usize(&mut data)?;
+            let len = vec_to_usize(&mut data)?;
+            let mut receiver = JournalReceiver::new(position, extract_vec(0..len, &mut data)?);
+
+            for chunks in [&mut receiver.uploaded, &mut receiver.downloaded] {
+                let len = vec_to_usize(&mut data)?;
+                if len > data.len() / 8 {
+                    return Err(anyhow!("Invalid amount of chunks in journal ({}).", len));
+                }
+
+                for _ in 0..len {
+                    chunks.insert(u64_from_vec(&mut data)?);
+                }
+            }
+
+            receivers.push(receiver);
+        }
+
+        let path_len = vec_to_usize(&mut data)?;
+        let path = String::from_utf8(extract_vec(0..path_len, &mut data)?)?;
+        let path = if path.is_empty() { None } else { Some(PathBuf::from(path)) };
+
+        let filename = String::from_utf8(data)?;
+        let sender = fields.pop().unwrap();
+        let chunk_hashes = fields.pop().unwrap().chunks(MSG_DIGEST.size()).map(|e| e.to_vec()).collect();
+        let hash = fields.pop().unwrap();
+
+        return Ok(TransferJournal {
+            uuid,
+            filename,
+            size,
+            chunk_size,
+            compression,
+            hash,
+            chunk_hashes,
+            path,
+            sender,
+            receivers,
+        });
+    }
+}
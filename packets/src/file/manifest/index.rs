@@ -0,0 +1,273 @@
+use std::path::{Component, Path};
+
+use anyhow::anyhow;
+use log::trace;
+use uuid::Uuid;
+
+use crate::{
+    consts::{MAX_FILE_SIZE, MSG_DIGEST},
+    file::processing::tools::{get_max_chunks, is_valid_chunk_size},
+    types::ByteMessage,
+    util::{
+        modes::Modes,
+        compression::Compression,
+        converter::pop_front_vec,
+        tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize},
+        vec::extract_vec,
+    },
+};
+
+// One file of a manifest, transferred like a single file with its own `uuid`
+#[derive(Debug, Clone)]
+pub struct ManifestEntry {
+    pub uuid: Uuid,
+    // Relative path with `/` as separator
+    pub path: String,
+    pub size: u64,
+    // Unix mode bits
+    pub permissions: u32,
+    pub hash: Vec<u8>,
+    pub chunk_hashes: Vec<Vec<u8>>,
+    // Chosen per file, e.g. images are sent uncompressed
+    pub compression: Compression,
+}
+
+impl ManifestEntry {
+    // Only plain relative paths are allowed, so the receiver never writes outside of the chosen directory
+    pub fn is_valid_path(&self) -> bool {
+        if self.path.is_empty() || self.path.contains('\\') || self.path.contains('\0') {
+            return false;
+        }
+
+        let path = Path::new(&self.path);
+        return path.components().all(|c| matches!(c, Component::Normal(_)));
+    }
+}
+
+// Offers multiple files (e.g. a directory) at once, the receiver answers
+// with a `FileQuestionReplyMsg` for every entry.
+#[derive(Debug, Clone)]
+pub struct FileManifestMsg {
+    pub uuid: Uuid,
+    pub sender: Uuid,
+    pub receiver: Uuid,
+    pub entries: Vec<ManifestEntry>,
+    // Used for every entry
+    pub chunk_size: u64,
+    // Sequence number and mac of the sender's session, see `Ratchet::authenticate`
+    pub seq: u64,
+    pub mac: Vec<u8>,
+}
+
+impl FileManifestMsg {
+    pub fn get_total_size(&self) -> u64 {
+        return self.entries.iter().map(|e| e.size).sum();
+    }
+
+    // Everything the mac is computed over
+    pub fn get_auth_data(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.receiver.as_bytes().to_vec());
+        merged.append(&mut self.sender.as_bytes().to_vec());
+        merged.append(&mut self.chunk_size.to_le_bytes().to_vec());
+        merged.append(&mut FileManifestMsg::serialize_entries(&self.entries));
+
+        return merged;
+    }
+
+    fn serialize_entries(entries: &Vec<ManifestEntry>) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut usize_to_vec(entries.len()).unwrap());
+        for entry in entries {
+            merged.append(&mut entry.uuid.as_bytes().to_vec());
+            merged.append(&mut entry.size.to_le_bytes().to_vec());
+            merged.append(&mut (entry.permissions as u64).to_le_bytes().to_vec());
+            merged.push(entry.compression.get_indicator());
+            merged.append(&mut entry.hash.clone());
+
+            for hash in &entry.chunk_hashes {
+                merged.append(&mut hash.clone());
+            }
+
+            merged.append(&mut usize_to_vec(entry.path.len()).unwrap());
+            merged.append(&mut entry.path.as_bytes().to_vec());
+        }
+
+        return merged;
+    }
+}
+
+impl ByteMessage for FileManifestMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.sender.as_bytes().to_vec());
+        merged.append(&mut self.receiver.as_bytes().to_vec());
+        merged.append(&mut self.seq.to_le_bytes().to_vec());
+        merged.append(&mut self.mac.clone());
+        merged.append(&mut self.chunk_size.to_le_bytes().to_vec());
+        merged.append(&mut FileManifestMsg::serialize_entries(&self.entries));
+
+        return Modes::SendFileManifest.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let uuid = uuid_from_vec(&mut data)?;
+        let sender = uuid_from_vec(&mut data)?;
+        let receiver = uuid_from_vec(&mut data)?;
+        let seq = u64_from_vec(&mut data)?;
+        let mac = extract_vec(0..MSG_DIGEST.size(), &mut data)?;
+        let chunk_size = u64_from_vec(&mut data)?;
+        if !is_valid_chunk_size(chunk_size) {
+            return Err(anyhow!("Invalid chunk size {}.", chunk_size));
+        }
+
+        let count = vec_to_usize(&mut data)?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let uuid = uuid_from_vec(&mut data)?;
+            let size = u64_from_vec(&mut data)?;
+            if size > MAX_FILE_SIZE {
+                return Err(anyhow!("Invalid size of manifest entry ({}).", size));
+            }
+
+            let permissions = u64_from_vec(&mut data)? as u32;
+            let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
+            let hash = extract_vec(0..MSG_DIGEST.size(), &mut data)?;
+
+            let chunk_count = get_max_chunks(size, chunk_size) as usize;
+            if chunk_count > data.len() / MSG_DIGEST.size() {
+                return Err(anyhow!("Invalid size of manifest entry ({}).", size));
+            }
+
+            let mut chunk_hashes = Vec::with_capacity(chunk_count);
+            for _ in 0..chunk_count {
+                chunk_hashes.push(extract_vec(0..MSG_DIGEST.size(), &mut data)?);
+            }
+
+            let path_len = vec_to_usize(&mut data)?;
+            if path_len > data.len() {
+                return Err(anyhow!("Invalid path length of manifest entry ({}).", path_len));
+            }
+
+            let path = String::from_utf8(extract_vec(0..path_len, &mut data)?)?;
+            entries.push(ManifestEntry { uuid, path, size, permissions, hash, chunk_hashes, compression });
+        }
+
+        if !data.is_empty() {
+            return Err(anyhow!("Trailing bytes after manifest entries."));
+        }
+
+        let msg = FileManifestMsg {
+            uuid,
+            sender,
+            receiver,
+            entries,
+            chunk_size,
+            seq,
+            mac
+        };
+
+        trace!("FileManifest info is {:?}", msg);
+        return Ok(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{consts::{MAX_FILE_SIZE, MIN_CHUNK_SIZE, MSG_DIGEST}, types::ByteMessage, util::compression::Compression};
+
+    use super::{FileManifestMsg, ManifestEntry};
+
+    // Offset of the entry count in the body: uuids, seq, mac and chunk size
+    const ENTRY_COUNT_OFFSET: usize = 16 * 3 + 8 + 32 + 8;
+
+    fn get_entry(size: u64, chunk_count: usize) -> ManifestEntry {
+        return ManifestEntry {
+            uuid: Uuid::from_bytes([4; 16]),
+            path: "dir/file.txt".to_string(),
+            size,
+            permissions: 0o644,
+            hash: vec![5; MSG_DIGEST.size()],
+            chunk_hashes: vec![vec![6; MSG_DIGEST.size()]; chunk_count],
+            compression: Compression::None,
+        };
+    }
+
+    fn get_manifest(entries: Vec<ManifestEntry>) -> FileManifestMsg {
+        return FileManifestMsg {
+            uuid: Uuid::from_bytes([1; 16]),
+            sender: Uuid::from_bytes([2; 16]),
+            receiver: Uuid::from_bytes([3; 16]),
+            entries,
+            chunk_size: MIN_CHUNK_SIZE,
+            seq: 1,
+            mac: vec![7; MSG_DIGEST.size()],
+        };
+    }
+
+    // Body of the packet without the mode
+    fn get_body(msg: &FileManifestMsg) -> Vec<u8> {
+        return msg.serialize()[1..].to_vec();
+    }
+
+    #[test]
+    fn accepts_a_valid_manifest() {
+        let msg = get_manifest(vec![get_entry(MIN_CHUNK_SIZE + 1, 2), get_entry(0, 0)]);
+        let parsed = FileManifestMsg::deserialize(&get_body(&msg)).unwrap();
+
+        assert_eq!(parsed.entries.len(), 2);
+        assert_eq!(parsed.entries[0].chunk_hashes, msg.entries[0].chunk_hashes);
+        assert_eq!(parsed.entries[0].path, msg.entries[0].path);
+    }
+
+    #[test]
+    fn rejects_entry_count_larger_than_packet() {
+        let msg = get_manifest(vec![get_entry(1, 1)]);
+        let mut body = get_body(&msg);
+
+        body.splice(ENTRY_COUNT_OFFSET..ENTRY_COUNT_OFFSET + 8, (u64::MAX).to_le_bytes());
+        assert!(FileManifestMsg::deserialize(&body).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_entries() {
+        let msg = get_manifest(vec![get_entry(MIN_CHUNK_SIZE + 1, 2)]);
+        let body = get_body(&msg);
+
+        assert!(FileManifestMsg::deserialize(&body[..body.len() - 1].to_vec()).is_err());
+        assert!(FileManifestMsg::deserialize(&body[..ENTRY_COUNT_OFFSET + 8 + 16].to_vec()).is_err());
+    }
+
+    #[test]
+    fn rejects_chunk_count_larger_than_packet() {
+        // The chunk hashes of the entry follow from its size
+        let msg = get_manifest(vec![get_entry(MAX_FILE_SIZE, 1)]);
+
+        let err = FileManifestMsg::deserialize(&get_body(&msg)).unwrap_err();
+        assert!(err.to_string().contains("Invalid size of manifest entry"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_oversized_entries() {
+        let msg = get_manifest(vec![get_entry(MAX_FILE_SIZE + 1, 1)]);
+        assert!(FileManifestMsg::deserialize(&get_body(&msg)).is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let msg = get_manifest(vec![get_entry(1, 1)]);
+        let mut body = get_body(&msg);
+        body.push(0);
+
+        assert!(FileManifestMsg::deserialize(&body).is_err());
+    }
+}
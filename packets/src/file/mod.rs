@@ -1,4 +1,6 @@
 pub mod question;
 pub mod types;
 pub mod processing;
-pub mod chunk;
\ No newline at end of file
+pub mod chunk;
+pub mod journal;
+pub mod manifest;
@@ -2,4 +2,5 @@ pub mod start;
 pub mod tools;
 pub mod downloaded;
 pub mod ready;
-pub mod abort;
\ No newline at end of file
+pub mod abort;
+pub mod resume;
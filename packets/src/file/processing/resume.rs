@@ -0,0 +1,83 @@
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}}};
+
+// Sent by the client to resume transfer `uuid` after a reconnect, `done` are the chunks finished locally.
+// The server answers to both sides with the current connection ids (nil if offline),
+// `done` as the chunks which don't have to be transferred anymore and
+// `ready` as the chunks stored on the server the receiver still has to download.
+// The sender gets every connected receiver of the transfer, a receiver only itself.
+#[derive(Debug, Clone)]
+pub struct FileResumeMsg {
+    pub uuid: Uuid,
+    pub sender: Uuid,
+    pub receivers: Vec<Uuid>,
+    pub done: Vec<u64>,
+    pub ready: Vec<u64>,
+}
+
+impl ByteMessage for FileResumeMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.sender.as_bytes().to_vec());
+
+        merged.append(&mut usize_to_vec(self.receivers.len()).unwrap());
+        for receiver in &self.receivers {
+            merged.append(&mut receiver.as_bytes().to_vec());
+        }
+
+        merged.append(&mut usize_to_vec(self.done.len()).unwrap());
+        for chunk in &self.done {
+            merged.append(&mut chunk.to_le_bytes().to_vec());
+        }
+
+        for chunk in &self.ready {
+            merged.append(&mut chunk.to_le_bytes().to_vec());
+        }
+
+        return Modes::SendFileResume.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let uuid = uuid_from_vec(&mut data)?;
+        let sender = uuid_from_vec(&mut data)?;
+
+        let receiver_count = vec_to_usize(&mut data)?;
+        if receiver_count > data.len() / 16 {
+            return Err(anyhow!("Invalid amount of receivers ({}).", receiver_count));
+        }
+
+        let mut receivers = Vec::with_capacity(receiver_count);
+        for _ in 0..receiver_count {
+            receivers.push(uuid_from_vec(&mut data)?);
+        }
+
+        let done_len = vec_to_usize(&mut data)?;
+        if done_len > data.len() / 8 {
+            return Err(anyhow!("Invalid amount of finished chunks ({}).", done_len));
+        }
+
+        let mut done = Vec::with_capacity(done_len);
+        for _ in 0..done_len {
+            done.push(u64_from_vec(&mut data)?);
+        }
+
+        let mut ready = Vec::new();
+        while !data.is_empty() {
+            ready.push(u64_from_vec(&mut data)?);
+        }
+
+        return Ok(FileResumeMsg {
+            uuid,
+            sender,
+            receivers,
+            done,
+            ready
+        });
+    }
+}
@@ -3,10 +3,14 @@ use std::collections::VecDeque;
 use log::trace;
 use uuid::Uuid;
 
-use crate::{types::ByteMessage, util::{modes::Modes, tools::uuid_from_vec, converter::uuid_to_decque, vec::decque_to_vec}};
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{uuid_from_vec, u64_from_vec}, converter::uuid_to_decque, vec::{decque_to_vec, vec_to_decque}}};
 
 pub struct FileStartProcessing {
     pub uuid: Uuid,
+    // Chunk size the server accepted for this transfer
+    pub chunk_size: u64,
+    // Receivers which accepted the file, every chunk has to be stored for each of them
+    pub receivers: Vec<Uuid>
     //pub threads: u64
 }
 
@@ -14,9 +18,14 @@ impl ByteMessage for FileStartProcessing {
     fn serialize(&self) -> Vec<u8> {
         let mut merged = VecDeque::new();
         let mut b_uuid = uuid_to_decque(&self.uuid);
+        let mut b_chunk_size = vec_to_decque(self.chunk_size.to_le_bytes().to_vec());
         //let mut b_threads = vec_to_decque(self.threads.to_le_bytes().to_vec());
 
         merged.append(&mut b_uuid);
+        merged.append(&mut b_chunk_size);
+        for receiver in &self.receivers {
+            merged.append(&mut uuid_to_decque(receiver));
+        }
         //merged.append(&mut b_threads);
 
         return Modes::SendFileStartProcessing.get_send(&decque_to_vec(merged));
@@ -28,10 +37,21 @@ impl ByteMessage for FileStartProcessing {
         trace!("Parsing FileStartProcessing UUID...");
         let uuid = uuid_from_vec(&mut data)?;
 
+        trace!("Parsing FileStartProcessing chunk size...");
+        let chunk_size = u64_from_vec(&mut data)?;
+
+        trace!("Parsing FileStartProcessing receivers...");
+        let mut receivers = Vec::new();
+        while !data.is_empty() {
+            receivers.push(uuid_from_vec(&mut data)?);
+        }
+
         //trace!("Parsing FileStartProcessing Threads...");
         //let threads = u64_from_vec(&mut data)?;
         return Ok(FileStartProcessing {
             uuid,
+            chunk_size,
+            receivers
             //threads
         });
     }
@@ -1,28 +1,38 @@
-use crate::consts::CHUNK_SIZE;
+use crate::consts::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE, MSG_DIGEST};
+use openssl::hash::hash;
 use anyhow::anyhow;
 use log::trace;
 
 
-pub fn get_max_chunks(size: u64) -> u64 {
+pub fn get_max_chunks(size: u64, chunk_size: u64) -> u64 {
     let size_float = size as f64;
-    let chunk = CHUNK_SIZE as f64;
+    let chunk = chunk_size as f64;
 
     let threads = ((size_float / chunk).ceil()) as u64;
     return threads;
 }
 
-pub fn get_chunk_size(chunk_index: u64, size: u64) -> anyhow::Result<u64> {
-    let left = size % CHUNK_SIZE;
-    let max_threads  =get_max_chunks(size);
+pub fn get_chunk_size(chunk_index: u64, size: u64, chunk_size: u64) -> anyhow::Result<u64> {
+    let max_threads  =get_max_chunks(size, chunk_size);
 
-    if chunk_index > max_threads {
+    if chunk_index >= max_threads {
         trace!("Invalid chunk index {} with size {} and max_threads {}", chunk_index, size, max_threads);
         return Err(anyhow!("Invalid chunk index with given size"));
     }
 
     if chunk_index == max_threads -1 {
-        return Ok(left);
+        return Ok(size - chunk_size * chunk_index);
     }
 
-    return Ok(CHUNK_SIZE);
-}
\ No newline at end of file
+    return Ok(chunk_size);
+}
+
+// Chunk sizes every side has to accept, the server may narrow this range further
+pub fn is_valid_chunk_size(chunk_size: u64) -> bool {
+    return chunk_size >= MIN_CHUNK_SIZE && chunk_size <= MAX_CHUNK_SIZE;
+}
+
+pub fn get_chunk_hash(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let hash = hash(*MSG_DIGEST, data)?;
+    return Ok(hash.to_vec());
+}
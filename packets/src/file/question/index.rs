@@ -1,16 +1,19 @@
 use std::collections::VecDeque;
 
+use anyhow::anyhow;
 use log::trace;
 use uuid::Uuid;
 
 use crate::{
     types::ByteMessage,
     util::{
-        converter::{str_to_decque, uuid_to_decque},
+        converter::{pop_front_vec, str_to_decque, uuid_to_decque},
         modes::Modes,
-        tools::{u64_from_vec, uuid_from_vec},
+        compression::Compression,
+        tools::{u64_from_vec, uuid_from_vec, usize_to_vec, vec_to_usize},
         vec::{decque_to_vec, vec_to_decque, extract_vec},
-    }, consts::MSG_DIGEST,
+    }, consts::{MAX_FILE_SIZE, MAX_RECEIVERS, MSG_DIGEST},
+    file::processing::tools::{get_max_chunks, is_valid_chunk_size},
 };
 
 #[derive(Debug, Clone)]
@@ -18,10 +21,46 @@ pub struct FileQuestionMsg {
     pub filename: String,
     pub sender: Uuid,
     pub receiver: Uuid,
+    // Everyone the file is offered to in this transfer, including `receiver`
+    pub receivers: Vec<Uuid>,
     pub uuid: Uuid,
     // Uses sha256 so 32 bytes
     pub hash: Vec<u8>,
-    pub size: u64
+    // Sha256 of every chunk, so the receiver knows which chunk is corrupt
+    pub chunk_hashes: Vec<Vec<u8>>,
+    pub size: u64,
+    pub chunk_size: u64,
+    // Compression of the chunks, the sender turns it off for already compressed files
+    pub compression: Compression,
+    // Sequence number and mac of the sender's session, see `Ratchet::authenticate`
+    pub seq: u64,
+    pub mac: Vec<u8>
+}
+
+impl FileQuestionMsg {
+    // Everything the mac is computed over
+    pub fn get_auth_data(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.uuid.as_bytes().to_vec());
+        merged.append(&mut self.receiver.as_bytes().to_vec());
+        merged.append(&mut self.sender.as_bytes().to_vec());
+        merged.append(&mut self.size.to_le_bytes().to_vec());
+        merged.append(&mut self.chunk_size.to_le_bytes().to_vec());
+        merged.push(self.compression.get_indicator());
+        merged.append(&mut self.hash.clone());
+        for hash in &self.chunk_hashes {
+            merged.append(&mut hash.clone());
+        }
+
+        for receiver in &self.receivers {
+            merged.append(&mut receiver.as_bytes().to_vec());
+        }
+
+        merged.append(&mut self.filename.as_bytes().to_vec());
+
+        return merged;
+    }
 }
 
 impl ByteMessage for FileQuestionMsg {
@@ -33,13 +72,31 @@ impl ByteMessage for FileQuestionMsg {
         let mut b_receiver = uuid_to_decque(&self.receiver);
         let mut b_sender = uuid_to_decque(&self.sender);
         let mut b_size = vec_to_decque(self.size.to_le_bytes().to_vec());
+        let mut b_chunk_size = vec_to_decque(self.chunk_size.to_le_bytes().to_vec());
         let mut b_hash = vec_to_decque(self.hash.clone());
+        let mut b_seq = vec_to_decque(self.seq.to_le_bytes().to_vec());
+        let mut b_mac = vec_to_decque(self.mac.clone());
+        let mut b_chunk_hashes = vec_to_decque(usize_to_vec(self.chunk_hashes.len()).unwrap());
+        for hash in &self.chunk_hashes {
+            b_chunk_hashes.append(&mut vec_to_decque(hash.clone()));
+        }
+
+        let mut b_receivers = vec_to_decque(usize_to_vec(self.receivers.len()).unwrap());
+        for receiver in &self.receivers {
+            b_receivers.append(&mut uuid_to_decque(receiver));
+        }
 
         merged.append(&mut b_uuid);
         merged.append(&mut b_receiver);
         merged.append(&mut b_sender);
         merged.append(&mut b_size);
+        merged.append(&mut b_chunk_size);
+        merged.push_back(self.compression.get_indicator());
         merged.append(&mut b_hash);
+        merged.append(&mut b_seq);
+        merged.append(&mut b_mac);
+        merged.append(&mut b_chunk_hashes);
+        merged.append(&mut b_receivers);
         merged.append(&mut b_filename);
 
         return Modes::SendFileQuestion.get_send(&decque_to_vec(merged));
@@ -53,7 +110,48 @@ impl ByteMessage for FileQuestionMsg {
         let receiver = uuid_from_vec(&mut data)?;
         let sender = uuid_from_vec(&mut data)?;
         let size = u64_from_vec(&mut data)?;
+        if size > MAX_FILE_SIZE {
+            return Err(anyhow!("Invalid file size {}.", size));
+        }
+
+        let chunk_size = u64_from_vec(&mut data)?;
+        if !is_valid_chunk_size(chunk_size) {
+            return Err(anyhow!("Invalid chunk size {}.", chunk_size));
+        }
+
+        let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
         let hash = extract_vec(0..MSG_DIGEST.size(), &mut data)?;
+        let seq = u64_from_vec(&mut data)?;
+        let mac = extract_vec(0..MSG_DIGEST.size(), &mut data)?;
+
+        let chunk_count = vec_to_usize(&mut data)?;
+        let max_chunks = get_max_chunks(size, chunk_size);
+        if chunk_count as u64 != max_chunks {
+            return Err(anyhow!("Expected {} chunk hashes, got {}.", max_chunks, chunk_count));
+        }
+
+        if chunk_count > data.len() / MSG_DIGEST.size() {
+            return Err(anyhow!("Invalid amount of chunk hashes ({}).", chunk_count));
+        }
+
+        let mut chunk_hashes = Vec::with_capacity(chunk_count);
+        for _ in 0..chunk_count {
+            chunk_hashes.push(extract_vec(0..MSG_DIGEST.size(), &mut data)?);
+        }
+
+        let receiver_count = vec_to_usize(&mut data)?;
+        if receiver_count == 0 || receiver_count > MAX_RECEIVERS {
+            return Err(anyhow!("Invalid amount of receivers {}.", receiver_count));
+        }
+
+        let mut receivers = Vec::with_capacity(receiver_count);
+        for _ in 0..receiver_count {
+            receivers.push(uuid_from_vec(&mut data)?);
+        }
+
+        if !receivers.contains(&receiver) {
+            return Err(anyhow!("Receiver {} is not part of the transfer.", receiver));
+        }
 
         let filename = String::from_utf8(data)?;
 
@@ -62,11 +160,109 @@ impl ByteMessage for FileQuestionMsg {
             uuid,
             sender,
             receiver,
+            receivers,
             size,
-            hash
+            chunk_size,
+            compression,
+            hash,
+            chunk_hashes,
+            seq,
+            mac
         };
 
         trace!("FileQuestion info is {:?}", msg);
         return Ok(msg);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{consts::{MAX_FILE_SIZE, MAX_RECEIVERS, MIN_CHUNK_SIZE, MSG_DIGEST}, file::processing::tools::get_max_chunks, types::ByteMessage, util::compression::Compression};
+
+    use super::FileQuestionMsg;
+
+    // Offset of the chunk hash count in the body: uuids, size, chunk size, compression, hash, seq and mac
+    const CHUNK_COUNT_OFFSET: usize = 16 * 3 + 8 * 2 + 1 + 32 + 8 + 32;
+
+    fn get_question(size: u64, chunk_count: usize) -> FileQuestionMsg {
+        let receiver = Uuid::from_bytes([2; 16]);
+        return FileQuestionMsg {
+            filename: "file.txt".to_string(),
+            sender: Uuid::from_bytes([1; 16]),
+            receiver,
+            receivers: vec![receiver],
+            uuid: Uuid::from_bytes([3; 16]),
+            hash: vec![4; MSG_DIGEST.size()],
+            chunk_hashes: vec![vec![5; MSG_DIGEST.size()]; chunk_count],
+            size,
+            chunk_size: MIN_CHUNK_SIZE,
+            compression: Compression::None,
+            seq: 1,
+            mac: vec![6; MSG_DIGEST.size()],
+        };
+    }
+
+    // Body of the packet without the mode
+    fn get_body(msg: &FileQuestionMsg) -> Vec<u8> {
+        return msg.serialize()[1..].to_vec();
+    }
+
+    #[test]
+    fn accepts_a_valid_question() {
+        let msg = get_question(MIN_CHUNK_SIZE * 2 + 1, 3);
+        let parsed = FileQuestionMsg::deserialize(&get_body(&msg)).unwrap();
+
+        assert_eq!(parsed.chunk_hashes, msg.chunk_hashes);
+        assert_eq!(parsed.receivers, msg.receivers);
+        assert_eq!(parsed.filename, msg.filename);
+    }
+
+    #[test]
+    fn rejects_wrong_chunk_count() {
+        let msg = get_question(MIN_CHUNK_SIZE * 2 + 1, 2);
+        assert!(FileQuestionMsg::deserialize(&get_body(&msg)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_chunk_hashes() {
+        let msg = get_question(MIN_CHUNK_SIZE * 2 + 1, 3);
+        let body = get_body(&msg);
+
+        let truncated = body[..CHUNK_COUNT_OFFSET + 8 + MSG_DIGEST.size() * 2 + 1].to_vec();
+        assert!(FileQuestionMsg::deserialize(&truncated).is_err());
+    }
+
+    #[test]
+    fn rejects_chunk_count_larger_than_packet() {
+        // Matches the size, but the hashes are not in the packet
+        let msg = get_question(MAX_FILE_SIZE, 1);
+        let mut body = get_body(&msg);
+
+        let max_chunks = get_max_chunks(MAX_FILE_SIZE, MIN_CHUNK_SIZE);
+        body.splice(CHUNK_COUNT_OFFSET..CHUNK_COUNT_OFFSET + 8, max_chunks.to_le_bytes());
+
+        let err = FileQuestionMsg::deserialize(&body).unwrap_err();
+        assert!(err.to_string().contains("Invalid amount of chunk hashes"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_oversized_files() {
+        let msg = get_question(MAX_FILE_SIZE + 1, 1);
+        assert!(FileQuestionMsg::deserialize(&get_body(&msg)).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_receivers() {
+        let mut msg = get_question(1, 1);
+        msg.receivers = Vec::new();
+        assert!(FileQuestionMsg::deserialize(&get_body(&msg)).is_err());
+
+        msg.receivers = (0..=MAX_RECEIVERS).map(|e| Uuid::from_bytes([e as u8; 16])).collect();
+        assert!(FileQuestionMsg::deserialize(&get_body(&msg)).is_err());
+
+        msg.receivers = vec![msg.sender];
+        assert!(FileQuestionMsg::deserialize(&get_body(&msg)).is_err());
+    }
+}
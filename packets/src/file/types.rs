@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use uuid::Uuid;
 
+use crate::util::compression::Compression;
+
 #[derive(Debug, Clone)]
 pub struct FileRequest {
     pub filename: String,
@@ -14,7 +16,12 @@ pub struct FileInfo {
     pub path: Option<PathBuf>,
     pub filename: String,
     pub size: u64,
+    // Bytes per chunk, chosen by the sender within the bounds of the server
+    pub chunk_size: u64,
+    // Compression offered by the sender, single chunks may still be uncompressed
+    pub compression: Compression,
     pub receiver: Uuid,
     pub sender: Uuid,
-    pub hash: Vec<u8>
+    pub hash: Vec<u8>,
+    pub chunk_hashes: Vec<Vec<u8>>
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+use anyhow::anyhow;
+
+use crate::{
+    consts::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+    encryption::aead::CipherSuite,
+    file::processing::tools::is_valid_chunk_size,
+    types::ByteMessage,
+    util::{converter::pop_front_vec, modes::Modes, tools::u64_from_vec},
+};
+
+pub const FEATURE_RATCHET: u64 = 1 << 0;
+pub const FEATURE_REPLAY_PROTECTION: u64 = 1 << 1;
+pub const FEATURE_ROOMS: u64 = 1 << 2;
+pub const FEATURE_MANIFEST: u64 = 1 << 3;
+
+pub const SUPPORTED_FEATURES: u64 = FEATURE_RATCHET | FEATURE_REPLAY_PROTECTION | FEATURE_ROOMS | FEATURE_MANIFEST;
+// Clients without these can not talk to clients of this version at all
+pub const REQUIRED_FEATURES: u64 = FEATURE_RATCHET | FEATURE_REPLAY_PROTECTION;
+
+// Supported cipher suites, most preferred first
+pub const SUPPORTED_CIPHER_SUITES: [CipherSuite; 1] = [CipherSuite::Aes256Gcm];
+
+// First packet of the client after connecting to /chat
+#[derive(Debug, Clone)]
+pub struct HelloMsg {
+    pub version: u64,
+    pub min_version: u64,
+    pub features: u64,
+    pub cipher_suites: Vec<CipherSuite>,
+}
+
+// Answer of the server, contains what both sides agreed on
+#[derive(Debug, Clone)]
+pub struct HelloAckMsg {
+    pub version: u64,
+    pub features: u64,
+    // Chunk sizes the server accepts for file transfers
+    pub min_chunk_size: u64,
+    pub max_chunk_size: u64,
+    pub cipher_suite: CipherSuite,
+}
+
+impl HelloMsg {
+    pub fn current() -> Self {
+        return HelloMsg {
+            version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+            features: SUPPORTED_FEATURES,
+            cipher_suites: SUPPORTED_CIPHER_SUITES.to_vec(),
+        };
+    }
+
+    // Err contains a message for the other side on why the versions are incompatible
+    pub fn negotiate(&self) -> anyhow::Result<HelloAckMsg> {
+        let version = self.version.min(PROTOCOL_VERSION);
+        if version < self.min_version.max(MIN_PROTOCOL_VERSION) {
+            return Err(anyhow!(
+                "Incompatible protocol version. Client speaks {}-{}, server speaks {}-{}. Please update the outdated side.",
+                self.min_version, self.version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ));
+        }
+
+        let missing = REQUIRED_FEATURES & !self.features;
+        if missing != 0 {
+            return Err(anyhow!("Client is missing required features (flags {:#b}). Please update your client.", missing));
+        }
+
+        let cipher_suite = self.cipher_suites.iter().find(|e| SUPPORTED_CIPHER_SUITES.contains(e));
+        if cipher_suite.is_none() {
+            return Err(anyhow!("No common cipher suite, server supports {:?}.", SUPPORTED_CIPHER_SUITES));
+        }
+
+        return Ok(HelloAckMsg {
+            version,
+            features: self.features & SUPPORTED_FEATURES,
+            min_chunk_size: MIN_CHUNK_SIZE,
+            max_chunk_size: MAX_CHUNK_SIZE,
+            cipher_suite: cipher_suite.unwrap().to_owned(),
+        });
+    }
+}
+
+impl HelloAckMsg {
+    // Checks the answer of the server against what we sent
+    pub fn verify(&self, hello: &HelloMsg) -> anyhow::Result<()> {
+        if self.version > hello.version || self.version < hello.min_version {
+            return Err(anyhow!("Server selected unsupported protocol version {}.", self.version));
+        }
+
+        if !hello.cipher_suites.contains(&self.cipher_suite) {
+            return Err(anyhow!("Server selected unsupported cipher suite {:?}.", self.cipher_suite));
+        }
+
+        let missing = REQUIRED_FEATURES & !self.features;
+        if missing != 0 {
+            return Err(anyhow!("Server is missing required features (flags {:#b}).", missing));
+        }
+
+        if self.min_chunk_size > self.max_chunk_size || !is_valid_chunk_size(self.min_chunk_size) || !is_valid_chunk_size(self.max_chunk_size) {
+            return Err(anyhow!("Server sent invalid chunk size bounds {}-{}.", self.min_chunk_size, self.max_chunk_size));
+        }
+
+        return Ok(());
+    }
+}
+
+impl ByteMessage for HelloMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.version.to_le_bytes().to_vec());
+        merged.append(&mut self.min_version.to_le_bytes().to_vec());
+        merged.append(&mut self.features.to_le_bytes().to_vec());
+        merged.append(&mut self.cipher_suites.iter().map(|e| e.get_indicator()).collect());
+
+        return Modes::Hello.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let version = u64_from_vec(&mut data)?;
+        let min_version = u64_from_vec(&mut data)?;
+        let features = u64_from_vec(&mut data)?;
+
+        // Suites of newer clients we do not know are skipped
+        let cipher_suites = data.iter().filter_map(|e| CipherSuite::from_indicator(*e).ok()).collect();
+
+        return Ok(HelloMsg {
+            version,
+            min_version,
+            features,
+            cipher_suites,
+        });
+    }
+}
+
+impl ByteMessage for HelloAckMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.version.to_le_bytes().to_vec());
+        merged.append(&mut self.features.to_le_bytes().to_vec());
+        merged.append(&mut self.min_chunk_size.to_le_bytes().to_vec());
+        merged.append(&mut self.max_chunk_size.to_le_bytes().to_vec());
+        merged.push(self.cipher_suite.get_indicator());
+
+        return Modes::HelloAck.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let version = u64_from_vec(&mut data)?;
+        let features = u64_from_vec(&mut data)?;
+        let min_chunk_size = u64_from_vec(&mut data)?;
+        let max_chunk_size = u64_from_vec(&mut data)?;
+        let cipher_suite = CipherSuite::from_indicator(pop_front_vec(&mut data)?)?;
+
+        return Ok(HelloAckMsg {
+            version,
+            features,
+            min_chunk_size,
+            max_chunk_size,
+            cipher_suite,
+        });
+    }
+}
@@ -1,3 +1,5 @@
 pub mod uid_reply;
 pub mod name;
-pub mod pubkey;
\ No newline at end of file
+pub mod pubkey;
+pub mod hello;
+
@@ -5,4 +5,6 @@ pub mod util;
 pub mod communication;
 pub mod initialize;
 pub mod encryption;
-pub mod other;
\ No newline at end of file
+pub mod other;
+pub mod packet;
+pub mod room;
\ No newline at end of file
@@ -0,0 +1,22 @@
+use std::fmt::{Display, Formatter};
+
+use crate::util::modes::Modes;
+
+#[derive(Debug)]
+pub enum PacketError {
+    Empty,
+    UnknownMode(u8),
+    Malformed(Modes, anyhow::Error),
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Packet is empty."),
+            Self::UnknownMode(mode) => write!(f, "Unknown packet mode {}.", mode),
+            Self::Malformed(mode, err) => write!(f, "Malformed {:?} packet: {}", mode, err),
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
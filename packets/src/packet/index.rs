@@ -0,0 +1,339 @@
+use crate::{
+    communication::{error::ErrorMsg, from::FromMsg, from_offline::FromOfflineMsg, left::UserLeftMsg, to_offline::ToOfflineMsg, key_reply::SymmKeyReplyMsg, key_request::WantSymmKeyMsg, to::ToMsg},
+    file::{
+        manifest::index::FileManifestMsg,
+        processing::{abort::ChunkAbortMsg, downloaded::ChunkDownloadedMsg, ready::ChunkReadyMsg, resume::FileResumeMsg, start::FileStartProcessing},
+        question::{index::FileQuestionMsg, reply::FileQuestionReplyMsg},
+    },
+    initialize::{hello::{HelloAckMsg, HelloMsg}, name::NameMsg, pubkey::PubkeyMsg, uid_reply::UidReplyMsg},
+    room::{action::RoomActionMsg, key::RoomKeyMsg, list::RoomListMsg, msg::RoomMsg, roster::RoomRosterMsg},
+    types::ByteMessage,
+    util::modes::Modes,
+};
+
+use super::error::PacketError;
+
+// Wire format: every packet is `mode (1 byte) | body`, integers are u64 little endian, uuids 16 bytes.
+//
+// | Mode                         | Body                                                                     |
+// |------------------------------|--------------------------------------------------------------------------|
+// | SetPubkey (0)                | pem of rsa public key                                                    |
+// | To (1)                       | receiver | ratchet message                                               |
+// | From (2)                     | sender | ratchet message                                                 |
+// | Name (3)                     | utf8 name (4-20 bytes)                                                   |
+// | WantUid (4)                  | -                                                                        |
+// | UidReply (5)                 | uuid                                                                     |
+// | Error (6)                    | utf8 message                                                             |
+// | SendFileQuestion (7)         | file question, see below                                                 |
+// | SendFileQuestionReply (8)    | file | accepted (1 byte)                                                 |
+// | SendFileChunkReady (9)       | file | chunk index                                                       |
+// | SendFileChunkDownloaded (10) | file | chunk index                                                       |
+// | SendFileStartProcessing (11) | file | chunk | receiver*                                                 |
+// | SendFileAbort (12)           | file                                                                     |
+// | SymmKey (13)                 | user | x25519 ephemeral (32) | signature                                 |
+// | WantSymmKey (14)             | user | x25519 ephemeral (32) | nonce (32) | rekey (1 byte) | signature   |
+// | Hello (15)                   | version | min version | features | suites (1 byte each)                  |
+// | HelloAck (16)                | version | features | min chunk | max chunk | cipher suite (1 byte)       |
+// | RoomAction (17)              | action (1 byte) | room                                                   |
+// | RoomList (18)                | (members | name length | name)*, empty as request                        |
+// | RoomRoster (19)              | epoch | member count | members | room                                    |
+// | RoomKey (20)                 | user | epoch | key len | rsa encrypted key | sig len | sig | room        |
+// | RoomMsg (21)                 | user | epoch | room len | room | suite | nonce | tag | ciphertext        |
+// | SendFileResume (22)          | file | sender | receiver count | receiver* | done count | done* | ready* |
+// | SendFileManifest (23)        | manifest | sender | receiver | seq | mac | chunk | entry count | entry*  |
+// | ToOffline (24)               | receiver | sealed message                                                |
+// | FromOffline (25)             | key len | pem of sender key | sealed message                             |
+// | UserLeft (26)                | user                                                                     |
+//
+// Hello has to be the first packet of a client, its layout must never change.
+//
+// `chunk` is the chunk size in bytes, `compression` is one byte (see `util::compression`).
+// A file question is `file | rec | sender | size | chunk | compression | sha256 | seq | mac | hashes | receivers | name`,
+// its hashes are `chunk count | sha256 of every chunk` and its receivers `receiver count | receiver*`.
+// Every receiver gets its own copy of the question, `rec` is the receiver of that copy and the mac is bound to it.
+// The receivers of SendFileStartProcessing are the ones which accepted the file, until the end of the packet.
+// A manifest entry is `file | size | permissions | compression | sha256 | chunk hashes | path len | path`.
+//
+// A ratchet message is `ratchet header | cipher suite | nonce | tag | ciphertext`, see `encryption::ratchet`.
+// A sealed message is `rsa encrypted key | sig len | signature | cipher suite | nonce | tag | ciphertext`,
+// see `encryption::sealed`. Its plaintext is `message id | sent at | compression | data`, `sent at` in unix seconds.
+// The server only queues sealed messages, ratchet messages are dropped with the connection.
+// Chat and room plaintexts are `compression | data` before they are encrypted.
+pub enum Packet {
+    SetPubkey(PubkeyMsg),
+    To(ToMsg),
+    From(FromMsg),
+    Name(NameMsg),
+    WantUid,
+    UidReply(UidReplyMsg),
+    Error(ErrorMsg),
+    SendFileQuestion(FileQuestionMsg),
+    SendFileQuestionReply(FileQuestionReplyMsg),
+    SendFileChunkReady(ChunkReadyMsg),
+    SendFileChunkDownloaded(ChunkDownloadedMsg),
+    SendFileStartProcessing(FileStartProcessing),
+    SendFileAbort(ChunkAbortMsg),
+    SymmKey(SymmKeyReplyMsg),
+    WantSymmKey(WantSymmKeyMsg),
+    Hello(HelloMsg),
+    HelloAck(HelloAckMsg),
+    RoomAction(RoomActionMsg),
+    RoomList(RoomListMsg),
+    RoomRoster(RoomRosterMsg),
+    RoomKey(RoomKeyMsg),
+    RoomMsg(RoomMsg),
+    SendFileResume(FileResumeMsg),
+    SendFileManifest(FileManifestMsg),
+    ToOffline(ToOfflineMsg),
+    FromOffline(FromOfflineMsg),
+    UserLeft(UserLeftMsg),
+}
+
+impl Packet {
+    pub fn get_mode(&self) -> Modes {
+        match self {
+            Self::SetPubkey(_) => Modes::SetPubkey,
+            Self::To(_) => Modes::To,
+            Self::From(_) => Modes::From,
+            Self::Name(_) => Modes::Name,
+            Self::WantUid => Modes::WantUid,
+            Self::UidReply(_) => Modes::UidReply,
+            Self::Error(_) => Modes::Error,
+            Self::SendFileQuestion(_) => Modes::SendFileQuestion,
+            Self::SendFileQuestionReply(_) => Modes::SendFileQuestionReply,
+            Self::SendFileChunkReady(_) => Modes::SendFileChunkReady,
+            Self::SendFileChunkDownloaded(_) => Modes::SendFileChunkDownloaded,
+            Self::SendFileStartProcessing(_) => Modes::SendFileStartProcessing,
+            Self::SendFileAbort(_) => Modes::SendFileAbort,
+            Self::SymmKey(_) => Modes::SymmKey,
+            Self::WantSymmKey(_) => Modes::WantSymmKey,
+            Self::Hello(_) => Modes::Hello,
+            Self::HelloAck(_) => Modes::HelloAck,
+            Self::RoomAction(_) => Modes::RoomAction,
+            Self::RoomList(_) => Modes::RoomList,
+            Self::RoomRoster(_) => Modes::RoomRoster,
+            Self::RoomKey(_) => Modes::RoomKey,
+            Self::RoomMsg(_) => Modes::RoomMsg,
+            Self::SendFileResume(_) => Modes::SendFileResume,
+            Self::SendFileManifest(_) => Modes::SendFileManifest,
+            Self::ToOffline(_) => Modes::ToOffline,
+            Self::FromOffline(_) => Modes::FromOffline,
+            Self::UserLeft(_) => Modes::UserLeft,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::SetPubkey(msg) => msg.serialize(),
+            Self::To(msg) => msg.serialize(),
+            Self::From(msg) => msg.serialize(),
+            Self::Name(msg) => msg.serialize(),
+            Self::WantUid => Modes::WantUid.get_send(&Vec::new()),
+            Self::UidReply(msg) => msg.serialize(),
+            Self::Error(msg) => msg.serialize(),
+            Self::SendFileQuestion(msg) => msg.serialize(),
+            Self::SendFileQuestionReply(msg) => msg.serialize(),
+            Self::SendFileChunkReady(msg) => msg.serialize(),
+            Self::SendFileChunkDownloaded(msg) => msg.serialize(),
+            Self::SendFileStartProcessing(msg) => msg.serialize(),
+            Self::SendFileAbort(msg) => msg.serialize(),
+            Self::SymmKey(msg) => msg.serialize(),
+            Self::WantSymmKey(msg) => msg.serialize(),
+            Self::Hello(msg) => msg.serialize(),
+            Self::HelloAck(msg) => msg.serialize(),
+            Self::RoomAction(msg) => msg.serialize(),
+            Self::RoomList(msg) => msg.serialize(),
+            Self::RoomRoster(msg) => msg.serialize(),
+            Self::RoomKey(msg) => msg.serialize(),
+            Self::RoomMsg(msg) => msg.serialize(),
+            Self::SendFileResume(msg) => msg.serialize(),
+            Self::SendFileManifest(msg) => msg.serialize(),
+            Self::ToOffline(msg) => msg.serialize(),
+            Self::FromOffline(msg) => msg.serialize(),
+            Self::UserLeft(msg) => msg.serialize(),
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        if data.is_empty() {
+            return Err(PacketError::Empty);
+        }
+
+        let mode = Modes::from_indicator(&data[0]);
+        if mode.is_none() {
+            return Err(PacketError::UnknownMode(data[0]));
+        }
+
+        let mode = mode.unwrap();
+        let body = data[1..].to_vec();
+
+        let packet = match mode {
+            Modes::SetPubkey => PubkeyMsg::deserialize(&body).map(Self::SetPubkey),
+            Modes::To => ToMsg::deserialize(&body).map(Self::To),
+            Modes::From => FromMsg::deserialize(&body).map(Self::From),
+            Modes::Name => NameMsg::deserialize(&body).map(Self::Name),
+            Modes::WantUid => Ok(Self::WantUid),
+            Modes::UidReply => UidReplyMsg::deserialize(&body).map(Self::UidReply),
+            Modes::Error => ErrorMsg::deserialize(&body).map(Self::Error),
+            Modes::SendFileQuestion => FileQuestionMsg::deserialize(&body).map(Self::SendFileQuestion),
+            Modes::SendFileQuestionReply => FileQuestionReplyMsg::deserialize(&body).map(Self::SendFileQuestionReply),
+            Modes::SendFileChunkReady => ChunkReadyMsg::deserialize(&body).map(Self::SendFileChunkReady),
+            Modes::SendFileChunkDownloaded => ChunkDownloadedMsg::deserialize(&body).map(Self::SendFileChunkDownloaded),
+            Modes::SendFileStartProcessing => FileStartProcessing::deserialize(&body).map(Self::SendFileStartProcessing),
+            Modes::SendFileAbort => ChunkAbortMsg::deserialize(&body).map(Self::SendFileAbort),
+            Modes::SymmKey => SymmKeyReplyMsg::deserialize(&body).map(Self::SymmKey),
+            Modes::WantSymmKey => WantSymmKeyMsg::deserialize(&body).map(Self::WantSymmKey),
+            Modes::Hello => HelloMsg::deserialize(&body).map(Self::Hello),
+            Modes::HelloAck => HelloAckMsg::deserialize(&body).map(Self::HelloAck),
+            Modes::RoomAction => RoomActionMsg::deserialize(&body).map(Self::RoomAction),
+            Modes::RoomList => RoomListMsg::deserialize(&body).map(Self::RoomList),
+            Modes::RoomRoster => RoomRosterMsg::deserialize(&body).map(Self::RoomRoster),
+            Modes::RoomKey => RoomKeyMsg::deserialize(&body).map(Self::RoomKey),
+            Modes::RoomMsg => RoomMsg::deserialize(&body).map(Self::RoomMsg),
+            Modes::SendFileResume => FileResumeMsg::deserialize(&body).map(Self::SendFileResume),
+            Modes::SendFileManifest => FileManifestMsg::deserialize(&body).map(Self::SendFileManifest),
+            Modes::ToOffline => ToOfflineMsg::deserialize(&body).map(Self::ToOffline),
+            Modes::FromOffline => FromOfflineMsg::deserialize(&body).map(Self::FromOffline),
+            Modes::UserLeft => UserLeftMsg::deserialize(&body).map(Self::UserLeft),
+        };
+
+        return packet.map_err(|e| PacketError::Malformed(mode, e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use openssl::rsa::Rsa;
+    use uuid::Uuid;
+
+    use crate::{
+        communication::{error::{ErrorCode, ErrorMsg}, from::FromMsg, from_offline::FromOfflineMsg, key_reply::SymmKeyReplyMsg, key_request::WantSymmKeyMsg, left::UserLeftMsg, to::ToMsg, to_offline::ToOfflineMsg},
+        consts::{MIN_CHUNK_SIZE, MSG_DIGEST},
+        encryption::aead::CipherSuite,
+        file::{
+            manifest::index::{FileManifestMsg, ManifestEntry},
+            processing::{abort::ChunkAbortMsg, downloaded::ChunkDownloadedMsg, ready::ChunkReadyMsg, resume::FileResumeMsg, start::FileStartProcessing},
+            question::{index::FileQuestionMsg, reply::FileQuestionReplyMsg},
+        },
+        initialize::{hello::{HelloAckMsg, HelloMsg}, name::NameMsg, pubkey::PubkeyMsg, uid_reply::UidReplyMsg},
+        room::{action::{RoomAction, RoomActionMsg}, key::RoomKeyMsg, list::{RoomListMsg, RoomSummary}, msg::RoomMsg, roster::RoomRosterMsg},
+        util::{compression::Compression, modes::Modes},
+    };
+
+    use super::{Packet, PacketError};
+
+    fn get_id(i: u8) -> Uuid {
+        return Uuid::from_bytes([i; 16]);
+    }
+
+    fn get_hash(i: u8) -> Vec<u8> {
+        return vec![i; MSG_DIGEST.size()];
+    }
+
+    // One packet of every mode
+    fn get_packets() -> Vec<Packet> {
+        let key = Rsa::generate(2048).unwrap();
+        let pubkey = Rsa::from_public_components(key.n().to_owned().unwrap(), key.e().to_owned().unwrap()).unwrap();
+
+        return vec![
+            Packet::SetPubkey(PubkeyMsg { pubkey: pubkey.clone() }),
+            Packet::To(ToMsg { receiver: get_id(1), msg: vec![1, 2, 3] }),
+            Packet::From(FromMsg { sender: get_id(2), msg: vec![4, 5, 6] }),
+            Packet::Name(NameMsg { name: "alice".to_string() }),
+            Packet::WantUid,
+            Packet::UidReply(UidReplyMsg { uuid: get_id(3) }),
+            Packet::Error(ErrorMsg { code: ErrorCode::FileTooLarge, error: "too large".to_string() }),
+            Packet::SendFileQuestion(FileQuestionMsg {
+                filename: "file.txt".to_string(),
+                sender: get_id(4),
+                receiver: get_id(5),
+                receivers: vec![get_id(5), get_id(6)],
+                uuid: get_id(7),
+                hash: get_hash(1),
+                chunk_hashes: vec![get_hash(2), get_hash(3)],
+                size: MIN_CHUNK_SIZE + 1,
+                chunk_size: MIN_CHUNK_SIZE,
+                compression: Compression::Zstd,
+                seq: 1,
+                mac: get_hash(4),
+            }),
+            Packet::SendFileQuestionReply(FileQuestionReplyMsg { uuid: get_id(8), accepted: true }),
+            Packet::SendFileChunkReady(ChunkReadyMsg { uuid: get_id(9), chunk_index: 2 }),
+            Packet::SendFileChunkDownloaded(ChunkDownloadedMsg { uuid: get_id(10), chunk_index: 3 }),
+            Packet::SendFileStartProcessing(FileStartProcessing { uuid: get_id(11), chunk_size: MIN_CHUNK_SIZE, receivers: vec![get_id(12), get_id(13)] }),
+            Packet::SendFileAbort(ChunkAbortMsg { uuid: get_id(14) }),
+            Packet::SymmKey(SymmKeyReplyMsg { user: get_id(15), ephemeral: vec![1; 32], signature: vec![2; 256] }),
+            Packet::WantSymmKey(WantSymmKeyMsg { user: get_id(16), ephemeral: vec![3; 32], nonce: vec![4; 32], rekey: true, signature: vec![5; 256] }),
+            Packet::Hello(HelloMsg { version: 3, min_version: 2, features: 1, cipher_suites: vec![CipherSuite::Aes256Gcm] }),
+            Packet::HelloAck(HelloAckMsg { version: 3, features: 1, min_chunk_size: MIN_CHUNK_SIZE, max_chunk_size: MIN_CHUNK_SIZE * 2, cipher_suite: CipherSuite::Aes256Gcm }),
+            Packet::RoomAction(RoomActionMsg { action: RoomAction::Join, room: "room".to_string() }),
+            Packet::RoomList(RoomListMsg { rooms: vec![RoomSummary { name: "room".to_string(), members: 2 }] }),
+            Packet::RoomRoster(RoomRosterMsg { room: "room".to_string(), epoch: 4, members: vec![get_id(17), get_id(18)] }),
+            Packet::RoomKey(RoomKeyMsg { room: "room".to_string(), epoch: 4, user: get_id(19), encrypted_key: vec![5; 256], signature: vec![6; 256] }),
+            Packet::RoomMsg(RoomMsg { room: "room".to_string(), epoch: 4, user: get_id(20), msg: vec![7, 8] }),
+            Packet::SendFileResume(FileResumeMsg { uuid: get_id(21), sender: get_id(22), receivers: vec![get_id(23)], done: vec![0, 2], ready: vec![1] }),
+            Packet::SendFileManifest(FileManifestMsg {
+                uuid: get_id(24),
+                sender: get_id(25),
+                receiver: get_id(26),
+                entries: vec![ManifestEntry {
+                    uuid: get_id(27),
+                    path: "dir/file.txt".to_string(),
+                    size: 10,
+                    permissions: 0o644,
+                    hash: get_hash(5),
+                    chunk_hashes: vec![get_hash(6)],
+                    compression: Compression::None,
+                }],
+                chunk_size: MIN_CHUNK_SIZE,
+                seq: 2,
+                mac: get_hash(7),
+            }),
+            Packet::ToOffline(ToOfflineMsg { receiver: get_id(28), msg: vec![9, 10] }),
+            Packet::FromOffline(FromOfflineMsg { sender_key: pubkey, msg: vec![11, 12] }),
+            Packet::UserLeft(UserLeftMsg { user: get_id(29) }),
+        ];
+    }
+
+    #[test]
+    fn round_trips_every_mode() {
+        let packets = get_packets();
+
+        // New modes have to be added to `get_packets`
+        let modes: HashSet<u8> = packets.iter().map(|e| e.get_mode().get_indicator()).collect();
+        for indicator in 0..=u8::MAX {
+            if Modes::from_indicator(&indicator).is_some() {
+                assert!(modes.contains(&indicator), "No packet of mode {}", indicator);
+            }
+        }
+
+        for packet in packets {
+            let encoded = packet.encode();
+            assert_eq!(encoded[0], packet.get_mode().get_indicator());
+
+            let decoded = Packet::decode(&encoded);
+            assert!(decoded.is_ok(), "{:?} could not be decoded", packet.get_mode());
+
+            let decoded = decoded.unwrap();
+            assert_eq!(decoded.get_mode(), packet.get_mode());
+            assert_eq!(decoded.encode(), encoded, "{:?} changed after a round trip", packet.get_mode());
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_unknown_packets() {
+        assert!(matches!(Packet::decode(&[]), Err(PacketError::Empty)));
+        assert!(matches!(Packet::decode(&[255]), Err(PacketError::UnknownMode(255))));
+    }
+
+    #[test]
+    fn rejects_truncated_packets() {
+        let packet = Packet::SendFileChunkReady(ChunkReadyMsg { uuid: get_id(1), chunk_index: 1 });
+        let encoded = packet.encode();
+
+        let res = Packet::decode(&encoded[..encoded.len() - 1]);
+        assert!(matches!(res, Err(PacketError::Malformed(Modes::SendFileChunkReady, _))));
+    }
+}
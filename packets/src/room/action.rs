@@ -0,0 +1,55 @@
+use anyhow::anyhow;
+
+use crate::{types::ByteMessage, util::{converter::pop_front_vec, modes::Modes}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomAction {
+    Create,
+    Join,
+    Leave,
+}
+
+impl RoomAction {
+    pub fn get_indicator(self) -> u8 {
+        match self {
+            Self::Create => 0,
+            Self::Join => 1,
+            Self::Leave => 2,
+        }
+    }
+
+    pub fn from_indicator(ind: u8) -> anyhow::Result<Self> {
+        match ind {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::Join),
+            2 => Ok(Self::Leave),
+            _ => Err(anyhow!("Unknown room action {}.", ind)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomActionMsg {
+    pub action: RoomAction,
+    pub room: String,
+}
+
+impl ByteMessage for RoomActionMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.push(self.action.get_indicator());
+        merged.append(&mut self.room.as_bytes().to_vec());
+
+        return Modes::RoomAction.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let action = RoomAction::from_indicator(pop_front_vec(&mut data)?)?;
+        let room = String::from_utf8(data)?;
+
+        return Ok(RoomActionMsg { action, room });
+    }
+}
@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}, vec::extract_vec}};
+
+// Group key of an epoch, encrypted with the rsa key of one member and signed by the distributing member.
+// `user` is the receiving member when sent to the server and the distributing member when sent by the server.
+#[derive(Debug, Clone)]
+pub struct RoomKeyMsg {
+    pub room: String,
+    pub epoch: u64,
+    pub user: Uuid,
+    pub encrypted_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl ByteMessage for RoomKeyMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.user.as_bytes().to_vec());
+        merged.append(&mut self.epoch.to_le_bytes().to_vec());
+        merged.append(&mut usize_to_vec(self.encrypted_key.len()).unwrap());
+        merged.append(&mut self.encrypted_key.clone());
+        merged.append(&mut usize_to_vec(self.signature.len()).unwrap());
+        merged.append(&mut self.signature.clone());
+        merged.append(&mut self.room.as_bytes().to_vec());
+
+        return Modes::RoomKey.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let user = uuid_from_vec(&mut data)?;
+        let epoch = u64_from_vec(&mut data)?;
+
+        let key_size = vec_to_usize(&mut data)?;
+        let encrypted_key = extract_vec(0..key_size, &mut data)?;
+
+        let signature_size = vec_to_usize(&mut data)?;
+        let signature = extract_vec(0..signature_size, &mut data)?;
+
+        let room = String::from_utf8(data)?;
+        return Ok(RoomKeyMsg { room, epoch, user, encrypted_key, signature });
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{u64_from_vec, usize_to_vec, vec_to_usize}, vec::extract_vec}};
+
+#[derive(Debug, Clone)]
+pub struct RoomSummary {
+    pub name: String,
+    pub members: u64,
+}
+
+// Sent empty by the client to request the list of rooms, the server answers with all rooms
+#[derive(Debug, Clone)]
+pub struct RoomListMsg {
+    pub rooms: Vec<RoomSummary>,
+}
+
+impl ByteMessage for RoomListMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+        for room in &self.rooms {
+            merged.append(&mut room.members.to_le_bytes().to_vec());
+            merged.append(&mut usize_to_vec(room.name.len()).unwrap());
+            merged.append(&mut room.name.as_bytes().to_vec());
+        }
+
+        return Modes::RoomList.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+        let mut rooms = Vec::new();
+
+        while !data.is_empty() {
+            let members = u64_from_vec(&mut data)?;
+            let name_size = vec_to_usize(&mut data)?;
+            let name = String::from_utf8(extract_vec(0..name_size, &mut data)?)?;
+
+            rooms.push(RoomSummary { name, members });
+        }
+
+        return Ok(RoomListMsg { rooms });
+    }
+}
@@ -0,0 +1,6 @@
+pub mod action;
+pub mod list;
+pub mod roster;
+pub mod key;
+pub mod msg;
+pub mod tools;
@@ -0,0 +1,39 @@
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}, vec::extract_vec}};
+
+// Chat message to a room, encrypted once with the group key of `epoch`.
+// `user` is ignored when sent to the server and is the sender when sent by the server.
+#[derive(Debug, Clone)]
+pub struct RoomMsg {
+    pub room: String,
+    pub epoch: u64,
+    pub user: Uuid,
+    pub msg: Vec<u8>,
+}
+
+impl ByteMessage for RoomMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.user.as_bytes().to_vec());
+        merged.append(&mut self.epoch.to_le_bytes().to_vec());
+        merged.append(&mut usize_to_vec(self.room.len()).unwrap());
+        merged.append(&mut self.room.as_bytes().to_vec());
+        merged.append(&mut self.msg.clone());
+
+        return Modes::RoomMsg.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let user = uuid_from_vec(&mut data)?;
+        let epoch = u64_from_vec(&mut data)?;
+
+        let room_size = vec_to_usize(&mut data)?;
+        let room = String::from_utf8(extract_vec(0..room_size, &mut data)?)?;
+
+        return Ok(RoomMsg { room, epoch, user, msg: data });
+    }
+}
@@ -0,0 +1,42 @@
+use uuid::Uuid;
+
+use crate::{types::ByteMessage, util::{modes::Modes, tools::{u64_from_vec, uuid_from_vec}}};
+
+// Sent by the server to every member when the members of a room change.
+// `members` is ordered by join time, the first member distributes the key of the new epoch.
+#[derive(Debug, Clone)]
+pub struct RoomRosterMsg {
+    pub room: String,
+    pub epoch: u64,
+    pub members: Vec<Uuid>,
+}
+
+impl ByteMessage for RoomRosterMsg {
+    fn serialize(&self) -> Vec<u8> {
+        let mut merged = Vec::new();
+
+        merged.append(&mut self.epoch.to_le_bytes().to_vec());
+        merged.append(&mut (self.members.len() as u64).to_le_bytes().to_vec());
+        for member in &self.members {
+            merged.append(&mut member.as_bytes().to_vec());
+        }
+
+        merged.append(&mut self.room.as_bytes().to_vec());
+        return Modes::RoomRoster.get_send(&merged);
+    }
+
+    fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let epoch = u64_from_vec(&mut data)?;
+        let count = u64_from_vec(&mut data)?;
+
+        let mut members = Vec::new();
+        for _ in 0..count {
+            members.push(uuid_from_vec(&mut data)?);
+        }
+
+        let room = String::from_utf8(data)?;
+        return Ok(RoomRosterMsg { room, epoch, members });
+    }
+}
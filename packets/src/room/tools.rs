@@ -0,0 +1,76 @@
+use anyhow::anyhow;
+use openssl::{pkey::{Private, Public}, rand::rand_bytes, rsa::Rsa};
+use uuid::Uuid;
+
+use crate::{
+    consts::{AES_KEYSIZE_BYTES, MAX_ROOM_NAME_LENGTH},
+    encryption::{aead::{open, seal}, replay::{prepend_sequence, split_sequence}, sign::{get_signature, validate_signature}},
+    other::key_iv::KeyIVPair,
+    util::rsa::{decrypt_rsa, encrypt_rsa},
+};
+
+pub fn is_valid_room_name(name: &str) -> bool {
+    if name.is_empty() || name.len() > MAX_ROOM_NAME_LENGTH {
+        return false;
+    }
+
+    return name.chars().all(|e| e.is_ascii_alphanumeric() || e == '-' || e == '_');
+}
+
+pub fn generate_group_key() -> anyhow::Result<Vec<u8>> {
+    let mut key = [0 as u8; AES_KEYSIZE_BYTES];
+    rand_bytes(&mut key)?;
+
+    return Ok(key.to_vec());
+}
+
+fn get_key_sign_data(room: &str, epoch: u64, member: &Uuid, encrypted_key: &[u8]) -> Vec<u8> {
+    let mut merged = Vec::new();
+
+    merged.append(&mut epoch.to_le_bytes().to_vec());
+    merged.append(&mut member.as_bytes().to_vec());
+    merged.append(&mut encrypted_key.to_vec());
+    merged.append(&mut room.as_bytes().to_vec());
+
+    return merged;
+}
+
+// Returns the encrypted key and the signature for `member`
+pub fn wrap_group_key(key: &[u8], room: &str, epoch: u64, member: &Uuid, member_key: &Rsa<Public>, own_keypair: &Rsa<Private>) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let encrypted_key = encrypt_rsa(member_key, key)?;
+    let signature = get_signature(&get_key_sign_data(room, epoch, member, &encrypted_key), own_keypair)?;
+
+    return Ok((encrypted_key, signature));
+}
+
+pub fn unwrap_group_key(encrypted_key: &[u8], signature: &[u8], room: &str, epoch: u64, curr_id: &Uuid, distributor_key: &Rsa<Public>, own_keypair: &Rsa<Private>) -> anyhow::Result<Vec<u8>> {
+    let data = get_key_sign_data(room, epoch, curr_id, encrypted_key);
+    if !validate_signature(&data, &signature.to_vec(), distributor_key)? {
+        return Err(anyhow!("Invalid signature of key for room '{}'.", room));
+    }
+
+    return decrypt_rsa(own_keypair, encrypted_key);
+}
+
+fn get_room_aad(room: &str, epoch: u64, sender: &Uuid) -> Vec<u8> {
+    let mut merged = Vec::new();
+
+    merged.append(&mut epoch.to_le_bytes().to_vec());
+    merged.append(&mut sender.as_bytes().to_vec());
+    merged.append(&mut room.as_bytes().to_vec());
+
+    return merged;
+}
+
+pub fn seal_room_msg(key: &[u8], room: &str, epoch: u64, sender: &Uuid, seq: u64, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let key = KeyIVPair { key: key.to_vec(), iv: Vec::new() };
+    return seal(&key, &prepend_sequence(seq, data), &get_room_aad(room, epoch, sender));
+}
+
+// Returns sequence number and plaintext
+pub fn open_room_msg(key: &[u8], room: &str, epoch: u64, sender: &Uuid, data: &[u8]) -> anyhow::Result<(u64, Vec<u8>)> {
+    let key = KeyIVPair { key: key.to_vec(), iv: Vec::new() };
+    let decrypted = open(&key, data, &get_room_aad(room, epoch, sender))?;
+
+    return split_sequence(&decrypted);
+}
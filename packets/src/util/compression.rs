@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+
+use crate::{
+    consts::{MAX_DECOMPRESSED_MSG_SIZE, MIN_COMPRESS_MSG_SIZE, ZSTD_LEVEL},
+    util::converter::pop_front_vec,
+};
+
+// Extensions of formats which are compressed already, compressing them again only costs time
+const COMPRESSED_EXTENSIONS: [&str; 24] = [
+    "gz", "tgz", "zst", "zip", "xz", "txz", "bz2", "tbz2", "7z", "rar", "lz4", "br",
+    "jpg", "jpeg", "png", "gif", "webp", "mp3", "mp4", "mkv", "webm", "avi", "jar", "apk",
+];
+
+// Magic bytes of the same formats, in case the file has no or a wrong extension
+const COMPRESSED_MAGIC: [&[u8]; 10] = [
+    &[0x1f, 0x8b],                         // gzip
+    &[0x28, 0xb5, 0x2f, 0xfd],             // zstd
+    &[0x50, 0x4b, 0x03, 0x04],             // zip
+    &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00], // xz
+    &[0x42, 0x5a, 0x68],                   // bzip2
+    &[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c], // 7z
+    &[0x52, 0x61, 0x72, 0x21],             // rar
+    &[0x04, 0x22, 0x4d, 0x18],             // lz4
+    &[0x89, 0x50, 0x4e, 0x47],             // png
+    &[0xff, 0xd8, 0xff],                   // jpeg
+];
+
+// Compression of a file transfer, chosen by the sender in the offer.
+// Every chunk header says again whether that chunk is compressed, as incompressible chunks are sent as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    Zstd,
+}
+
+impl Compression {
+    pub fn get_indicator(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+        }
+    }
+
+    pub fn from_indicator(ind: u8) -> anyhow::Result<Self> {
+        match ind {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Zstd),
+            _ => Err(anyhow!("Unknown compression {}. The other client is probably outdated.", ind)),
+        }
+    }
+
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name.to_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "zstd" | "on" => Ok(Self::Zstd),
+            _ => Err(anyhow!("Unknown compression '{}', expected zstd or none.", name)),
+        }
+    }
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+// True if `head`, the first bytes of the file, or its name look like compressed data
+pub fn is_compressed_format(filename: &str, head: &[u8]) -> bool {
+    let ext = Path::new(filename).extension().and_then(|e| e.to_str()).unwrap_or("");
+    if COMPRESSED_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
+        return true;
+    }
+
+    return COMPRESSED_MAGIC.iter().any(|magic| head.starts_with(magic));
+}
+
+// Guesses from the first bytes of a file whether compressing it is worth it,
+// the sample has to shrink by at least 10%
+pub fn is_worth_compressing(filename: &str, sample: &[u8]) -> anyhow::Result<bool> {
+    if sample.is_empty() || is_compressed_format(filename, sample) {
+        return Ok(false);
+    }
+
+    let compressed = zstd::bulk::compress(sample, ZSTD_LEVEL)?;
+    return Ok(compressed.len() * 10 <= sample.len() * 9);
+}
+
+// Layout: compression (1 byte) | data. Empty data stays empty, ratchet heartbeats rely on that
+pub fn compress_msg(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut merged = Vec::with_capacity(data.len() + 1);
+    if data.len() >= MIN_COMPRESS_MSG_SIZE {
+        let mut compressed = zstd::bulk::compress(data, ZSTD_LEVEL)?;
+        if compressed.len() < data.len() {
+            merged.push(Compression::Zstd.get_indicator());
+            merged.append(&mut compressed);
+
+            return Ok(merged);
+        }
+    }
+
+    merged.push(Compression::None.get_indicator());
+    merged.extend_from_slice(data);
+
+    return Ok(merged);
+}
+
+pub fn decompress_msg(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut data = data.to_vec();
+    let compression = Compression::from_indicator(pop_front_vec(&mut data)?)?;
+    if compression == Compression::None {
+        return Ok(data);
+    }
+
+    let decompressed = zstd::bulk::decompress(&data, MAX_DECOMPRESSED_MSG_SIZE);
+    if decompressed.is_err() {
+        return Err(anyhow!("Could not decompress message: {}", decompressed.unwrap_err()));
+    }
+
+    return Ok(decompressed.unwrap());
+}
@@ -2,4 +2,6 @@ pub mod modes;
 pub mod tools;
 pub mod vec;
 pub mod converter;
-pub mod rsa;
\ No newline at end of file
+pub mod rsa;
+pub mod rate;
+pub mod compression;
\ No newline at end of file
@@ -1,4 +1,4 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Modes {
     SetPubkey,
     To,
@@ -19,7 +19,24 @@ pub enum Modes {
     SendFileChunkDownloaded,
     // Sent from server to sending client, to retrieve file size etc
     SendFileStartProcessing,
-    SendFileAbort
+    SendFileAbort,
+    // First packet of the client, carries protocol version and features
+    Hello,
+    HelloAck,
+    RoomAction,
+    RoomList,
+    RoomRoster,
+    RoomKey,
+    RoomMsg,
+    // Renegotiates an interrupted transfer, see `file::journal`
+    SendFileResume,
+    // Offers multiple files at once, e.g. a directory
+    SendFileManifest,
+    // Chat message sealed to the identity key of an offline user, queued by the server
+    ToOffline,
+    FromOffline,
+    // A user disconnected, clients queue messages for it from now on
+    UserLeft
 }
 
 impl Modes {
@@ -39,10 +56,57 @@ impl Modes {
             Self::SendFileStartProcessing => 11,
             Self::SendFileAbort => 12,
             Self::SymmKey => 13,
-            Self::WantSymmKey => 14
+            Self::WantSymmKey => 14,
+            Self::Hello => 15,
+            Self::HelloAck => 16,
+            Self::RoomAction => 17,
+            Self::RoomList => 18,
+            Self::RoomRoster => 19,
+            Self::RoomKey => 20,
+            Self::RoomMsg => 21,
+            Self::SendFileResume => 22,
+            Self::SendFileManifest => 23,
+            Self::ToOffline => 24,
+            Self::FromOffline => 25,
+            Self::UserLeft => 26
         }
     }
 
+    pub fn from_indicator(b: &u8) -> Option<Self> {
+        let mode = match b {
+            0 => Self::SetPubkey,
+            1 => Self::To,
+            2 => Self::From,
+            3 => Self::Name,
+            4 => Self::WantUid,
+            5 => Self::UidReply,
+            6 => Self::Error,
+            7 => Self::SendFileQuestion,
+            8 => Self::SendFileQuestionReply,
+            9 => Self::SendFileChunkReady,
+            10 => Self::SendFileChunkDownloaded,
+            11 => Self::SendFileStartProcessing,
+            12 => Self::SendFileAbort,
+            13 => Self::SymmKey,
+            14 => Self::WantSymmKey,
+            15 => Self::Hello,
+            16 => Self::HelloAck,
+            17 => Self::RoomAction,
+            18 => Self::RoomList,
+            19 => Self::RoomRoster,
+            20 => Self::RoomKey,
+            21 => Self::RoomMsg,
+            22 => Self::SendFileResume,
+            23 => Self::SendFileManifest,
+            24 => Self::ToOffline,
+            25 => Self::FromOffline,
+            26 => Self::UserLeft,
+            _ => return None
+        };
+
+        return Some(mode);
+    }
+
     pub fn is_indicator(self, b: &u8) -> bool {
         let ind = self.get_indicator();
         return ind.eq(b);
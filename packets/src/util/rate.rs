@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+use crate::consts::ONE_MB_SIZE;
+
+// Token bucket, `rate` is in bytes per second and 0 means unlimited.
+// Taking more than is available puts the bucket in debt, the caller has to wait the returned duration.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64) -> Self {
+        return TokenBucket {
+            rate,
+            tokens: rate as f64,
+            last: Instant::now(),
+        };
+    }
+
+    pub fn get_rate(&self) -> u64 {
+        return self.rate;
+    }
+
+    pub fn is_limited(&self) -> bool {
+        return self.rate != 0;
+    }
+
+    pub fn set_rate(&mut self, rate: u64) {
+        self.rate = rate;
+        self.tokens = rate as f64;
+        self.last = Instant::now();
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+
+        // At most one second of burst
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+    }
+
+    pub fn take(&mut self, amount: u64) -> Duration {
+        if !self.is_limited() {
+            return Duration::ZERO;
+        }
+
+        self.refill();
+        self.tokens -= amount as f64;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+
+        return Duration::from_secs_f64(-self.tokens / self.rate as f64);
+    }
+
+    // Size of the pieces data should be sent in, so waiting is spread evenly
+    pub fn get_piece_size(&self, default: usize) -> usize {
+        if !self.is_limited() {
+            return default;
+        }
+
+        let piece = (self.rate / 10).max(1024) as usize;
+        return piece.min(default);
+    }
+}
+
+// Parses rates like `500K`, `2M`, `1.5MB/s` or `off` (units are powers of 1000 like `ONE_MB_SIZE`)
+pub fn parse_rate(rate: &str) -> anyhow::Result<u64> {
+    let rate = rate.trim().to_uppercase();
+    if rate == "OFF" || rate == "0" || rate == "UNLIMITED" {
+        return Ok(0);
+    }
+
+    let size = parse_size(rate.trim_end_matches("/S"));
+    if size.is_err() {
+        return Err(anyhow!("Invalid rate '{}', use e.g. 500K, 2M or off.", rate));
+    }
+
+    return size;
+}
+
+// Parses sizes like `512K`, `10M` or `1.5MB`
+pub fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let size = size.trim().to_uppercase();
+
+    let number = size.trim_end_matches("B");
+    let (number, factor) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], ONE_MB_SIZE / 1000),
+        Some('M') => (&number[..number.len() - 1], ONE_MB_SIZE),
+        Some('G') => (&number[..number.len() - 1], ONE_MB_SIZE * 1000),
+        _ => (number, 1),
+    };
+
+    let number = number.trim().parse::<f64>();
+    if number.is_err() || !number.as_ref().unwrap().is_finite() || *number.as_ref().unwrap() < 0.0 {
+        return Err(anyhow!("Invalid size '{}', use e.g. 512K or 10M.", size));
+    }
+
+    return Ok((number.unwrap() * factor as f64) as u64);
+}
\ No newline at end of file
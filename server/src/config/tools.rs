@@ -0,0 +1,201 @@
+use std::{net::{IpAddr, Ipv4Addr}, path::{Path, PathBuf}, time::Duration};
+
+use anyhow::anyhow;
+use packets::{file::processing::tools::is_valid_chunk_size, util::rate::{parse_rate, parse_size}};
+
+use crate::{file::{consts::{CHUNK_BOUNDS, CHUNK_DIR, JANITOR_CONFIG, QUOTA_CONFIG, RATE_CONFIG}, types::{ChunkBounds, JanitorConfig, QuotaConfig, RateConfig, DEFAULT_CHUNK_DIR, DEFAULT_DOWNLOADED_TTL_SECS, DEFAULT_IDLE_TTL_SECS, DEFAULT_PENDING_TTL_SECS}}, queue::{consts::QUEUE_CONFIG, types::{QueueConfig, DEFAULT_QUEUE_MAX_SIZE, DEFAULT_QUEUE_TTL_SECS}}, utils::types::Args};
+
+use super::types::{FileConfig, Settings, SizeValue, TlsConfig, DEFAULT_PORT, DEFAULT_STORAGE};
+
+pub fn read_config_file(path: &Path) -> anyhow::Result<FileConfig> {
+    let content = std::fs::read_to_string(path);
+    if content.is_err() {
+        return Err(anyhow!("Could not read config file '{}': {}", path.display(), content.unwrap_err()));
+    }
+
+    let config = toml::from_str::<FileConfig>(&content.unwrap());
+    if config.is_err() {
+        return Err(anyhow!("Could not parse '{}': {}", path.display(), config.unwrap_err()));
+    }
+
+    return Ok(config.unwrap());
+}
+
+// Sizes of the config file, `key` is only used to point at the invalid value
+fn get_size(key: &str, value: &Option<SizeValue>, is_rate: bool) -> anyhow::Result<Option<u64>> {
+    let text = match value {
+        None => return Ok(None),
+        Some(SizeValue::Bytes(e)) => return Ok(Some(*e)),
+        Some(SizeValue::Text(e)) => e,
+    };
+
+    let size = if is_rate { parse_rate(text) } else { parse_size(text) };
+    if size.is_err() {
+        return Err(anyhow!("{}: {}", key, size.unwrap_err()));
+    }
+
+    return Ok(Some(size.unwrap()));
+}
+
+fn get_tls(cert: Option<PathBuf>, key: Option<PathBuf>, redirect_port: Option<u16>) -> anyhow::Result<Option<TlsConfig>> {
+    if cert.is_none() && key.is_none() {
+        if redirect_port.is_some() {
+            return Err(anyhow!("tls.redirect_port needs tls.cert and tls.key."));
+        }
+
+        return Ok(None);
+    }
+
+    if cert.is_none() || key.is_none() {
+        return Err(anyhow!("tls.cert and tls.key have to be given together."));
+    }
+
+    return Ok(Some(TlsConfig { cert: cert.unwrap(), key: key.unwrap(), redirect_port }));
+}
+
+// 0 disables the ttl
+fn get_ttl(secs: u64) -> Option<Duration> {
+    return if secs == 0 { None } else { Some(Duration::from_secs(secs)) };
+}
+
+// Command line and environment (both parsed by clap) win over the config file, which wins over the defaults
+pub fn resolve_settings(args: Args) -> anyhow::Result<Settings> {
+    let file = match &args.config {
+        Some(path) => read_config_file(path)?,
+        None => FileConfig::default(),
+    };
+
+    let limits = &file.limits;
+    let queue_max_size = args.queue_max_size.or(get_size("limits.queue_max_size", &limits.queue_max_size, false)?);
+    let upload_rate = args.max_user_upload_rate.or(get_size("limits.max_user_upload_rate", &limits.max_user_upload_rate, true)?);
+    let download_rate = args.max_user_download_rate.or(get_size("limits.max_user_download_rate", &limits.max_user_download_rate, true)?);
+    let min_chunk_size = args.min_chunk_size.or(get_size("limits.min_chunk_size", &limits.min_chunk_size, false)?);
+    let max_chunk_size = args.max_chunk_size.or(get_size("limits.max_chunk_size", &limits.max_chunk_size, false)?);
+    let max_file_size = args.max_file_size.or(get_size("limits.max_file_size", &limits.max_file_size, false)?);
+    let user_quota = args.user_quota.or(get_size("limits.user_quota", &limits.user_quota, false)?);
+    let reserved_quota = args.reserved_quota.or(get_size("limits.reserved_quota", &limits.reserved_quota, false)?);
+
+    let queue_max_size = queue_max_size.map(|e| e as usize).unwrap_or(DEFAULT_QUEUE_MAX_SIZE);
+    let default_bounds = ChunkBounds::default();
+    let ttl = &file.ttl;
+    let tls = get_tls(args.tls_cert.or(file.tls.cert), args.tls_key.or(file.tls.key), args.tls_redirect_port.or(file.tls.redirect_port))?;
+
+    let settings = Settings {
+        bind: args.bind.or(file.bind).unwrap_or(IpAddr::V4(Ipv4Addr::new(127,0,0,1))),
+        port: args.port.or(file.port).unwrap_or(DEFAULT_PORT),
+        chunk_dir: args.chunk_dir.or(file.chunk_dir).unwrap_or(PathBuf::from(DEFAULT_CHUNK_DIR)),
+        storage: args.storage.or(file.storage).unwrap_or(DEFAULT_STORAGE.to_string()),
+        log: args.log.or(file.log),
+        tls,
+        queue: QueueConfig {
+            ttl: Duration::from_secs(args.queue_ttl.or(ttl.queue).unwrap_or(DEFAULT_QUEUE_TTL_SECS)),
+            max_size: queue_max_size,
+        },
+        rate: RateConfig {
+            upload: upload_rate.unwrap_or(0),
+            download: download_rate.unwrap_or(0),
+        },
+        chunk_bounds: ChunkBounds {
+            min: min_chunk_size.unwrap_or(default_bounds.min),
+            max: max_chunk_size.unwrap_or(default_bounds.max),
+        },
+        quota: QuotaConfig {
+            max_file_size,
+            max_transfers: args.max_transfers_per_user.or(limits.max_transfers_per_user),
+            user_quota,
+            reserved_quota,
+        },
+        janitor: JanitorConfig {
+            pending: get_ttl(args.pending_ttl.or(ttl.pending).unwrap_or(DEFAULT_PENDING_TTL_SECS)),
+            idle: get_ttl(args.idle_ttl.or(ttl.idle).unwrap_or(DEFAULT_IDLE_TTL_SECS)),
+            downloaded: get_ttl(args.downloaded_ttl.or(ttl.downloaded).unwrap_or(DEFAULT_DOWNLOADED_TTL_SECS)),
+        },
+    };
+
+    validate_settings(&settings)?;
+    return Ok(settings);
+}
+
+fn validate_settings(settings: &Settings) -> anyhow::Result<()> {
+    let default_bounds = ChunkBounds::default();
+    let bounds = &settings.chunk_bounds;
+    if bounds.min > bounds.max || !is_valid_chunk_size(bounds.min) || !is_valid_chunk_size(bounds.max) {
+        return Err(anyhow!("Invalid chunk size bounds {}-{}, they have to be between {} and {}.", bounds.min, bounds.max, default_bounds.min, default_bounds.max));
+    }
+
+    if settings.queue.max_size == 0 {
+        return Err(anyhow!("queue_max_size has to be larger than 0."));
+    }
+
+    let quota = &settings.quota;
+    if quota.max_transfers == Some(0) {
+        return Err(anyhow!("max_transfers_per_user has to be larger than 0, leave it out for no limit."));
+    }
+
+    if quota.max_file_size.is_some() && quota.user_quota.is_some() && quota.max_file_size.unwrap() > quota.user_quota.unwrap() {
+        return Err(anyhow!("max_file_size can not be larger than user_quota."));
+    }
+
+    if quota.user_quota.is_some() && quota.reserved_quota.is_some() && quota.user_quota.unwrap() > quota.reserved_quota.unwrap() {
+        return Err(anyhow!("user_quota can not be larger than reserved_quota."));
+    }
+
+    if settings.chunk_dir.exists() && !settings.chunk_dir.is_dir() {
+        return Err(anyhow!("chunk_dir '{}' is not a directory.", settings.chunk_dir.display()));
+    }
+
+    let storage = Path::new(&settings.storage);
+    if settings.storage != "memory" && storage.exists() && !storage.is_dir() {
+        return Err(anyhow!("storage '{}' is not a directory.", settings.storage));
+    }
+
+    if settings.tls.is_some() {
+        let tls = settings.tls.as_ref().unwrap();
+        if !tls.cert.is_file() {
+            return Err(anyhow!("tls.cert '{}' does not exist.", tls.cert.display()));
+        }
+
+        if !tls.key.is_file() {
+            return Err(anyhow!("tls.key '{}' does not exist.", tls.key.display()));
+        }
+
+        if tls.redirect_port == Some(settings.port) {
+            return Err(anyhow!("tls.redirect_port has to differ from port {}.", settings.port));
+        }
+    }
+
+    return Ok(());
+}
+
+// Has to be called before transfers are restored, they read the chunk directory
+pub async fn apply_settings(settings: &Settings) {
+    let mut state = CHUNK_DIR.write().await;
+    *state = settings.chunk_dir.clone();
+
+    drop(state);
+
+    let mut state = QUEUE_CONFIG.write().await;
+    *state = settings.queue.clone();
+
+    drop(state);
+
+    let mut state = RATE_CONFIG.write().await;
+    *state = settings.rate.clone();
+
+    drop(state);
+
+    let mut state = CHUNK_BOUNDS.write().await;
+    *state = settings.chunk_bounds.clone();
+
+    drop(state);
+
+    let mut state = QUOTA_CONFIG.write().await;
+    *state = settings.quota.clone();
+
+    drop(state);
+
+    let mut state = JANITOR_CONFIG.write().await;
+    *state = settings.janitor.clone();
+
+    drop(state);
+}
@@ -0,0 +1,88 @@
+use std::{net::IpAddr, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{file::types::{ChunkBounds, JanitorConfig, QuotaConfig, RateConfig}, queue::types::QueueConfig};
+
+pub const DEFAULT_PORT: u16 = 3030;
+pub const DEFAULT_STORAGE: &str = "state";
+
+// Sizes can be given in bytes or as text like "512K" or "2G"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SizeValue {
+    Bytes(u64),
+    Text(String),
+}
+
+// Layout of the file given with `--config`, every key is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub bind: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub chunk_dir: Option<PathBuf>,
+    pub storage: Option<String>,
+    pub log: Option<String>,
+    pub limits: LimitsConfig,
+    pub ttl: TtlConfig,
+    pub tls: TlsFileConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsFileConfig {
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub redirect_port: Option<u16>,
+}
+
+// Both files are PEM encoded
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    // Plain http on this port redirects to https
+    pub redirect_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub queue_max_size: Option<SizeValue>,
+    pub max_user_upload_rate: Option<SizeValue>,
+    pub max_user_download_rate: Option<SizeValue>,
+    pub min_chunk_size: Option<SizeValue>,
+    pub max_chunk_size: Option<SizeValue>,
+    pub max_file_size: Option<SizeValue>,
+    pub max_transfers_per_user: Option<usize>,
+    pub user_quota: Option<SizeValue>,
+    pub reserved_quota: Option<SizeValue>,
+}
+
+// In seconds, 0 keeps things forever (except for the queue)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TtlConfig {
+    pub queue: Option<u64>,
+    pub pending: Option<u64>,
+    pub idle: Option<u64>,
+    pub downloaded: Option<u64>,
+}
+
+// Settings the server runs with after merging command line, environment, config file and defaults
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub chunk_dir: PathBuf,
+    pub storage: String,
+    pub log: Option<String>,
+    // Plain http if not given
+    pub tls: Option<TlsConfig>,
+    pub queue: QueueConfig,
+    pub rate: RateConfig,
+    pub chunk_bounds: ChunkBounds,
+    pub quota: QuotaConfig,
+    pub janitor: JanitorConfig,
+}
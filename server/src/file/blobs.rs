@@ -0,0 +1,247 @@
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
+
+use anyhow::anyhow;
+use log::trace;
+use packets::consts::MSG_DIGEST;
+use tokio::fs::{create_dir_all, read, read_dir, remove_file, rename, write};
+use uuid::Uuid;
+
+use crate::storage::consts::STORAGE;
+
+use super::{consts::BLOBS, tools::{get_chunk_dir, get_chunk_file}, types::BlobInfo};
+
+// The encrypted data of a chunk is stored once in `<chunk dir>/blobs/<sha256>.bin`.
+// Chunk files `<uuid>-<chunk index>-<receiver>.bin` only hold the header for the receiver followed by the hash of their blob.
+
+pub async fn get_blob_dir() -> anyhow::Result<PathBuf> {
+    let dir = get_chunk_dir().await.join("blobs");
+    if !dir.is_dir() { create_dir_all(dir.clone()).await?; }
+
+    return Ok(dir);
+}
+
+pub async fn get_blob_file(hash: &[u8]) -> anyhow::Result<PathBuf> {
+    let mut file = get_blob_dir().await?;
+    file.push(format!("{}.bin", hex::encode(hash)));
+
+    return Ok(file);
+}
+
+// Blobs are written here while they are uploaded and moved once their signature has been checked
+pub async fn get_temp_blob_file(uuid: &Uuid, chunk_index: u64, receiver: usize) -> anyhow::Result<PathBuf> {
+    let mut file = get_blob_dir().await?;
+    file.push(format!("{}-{}-{}.tmp", uuid, chunk_index, receiver));
+
+    return Ok(file);
+}
+
+// Returns the header of the chunk and the blob with its data
+pub async fn read_chunk(uuid: &Uuid, chunk_index: u64, receiver: usize) -> anyhow::Result<(Vec<u8>, PathBuf)> {
+    let mut header = read(get_chunk_file(uuid, chunk_index, receiver).await?).await?;
+    if header.len() < MSG_DIGEST.size() {
+        return Err(anyhow!("Chunk file of {} is too short.", chunk_index));
+    }
+
+    let hash = header.split_off(header.len() - MSG_DIGEST.size());
+    return Ok((header, get_blob_file(&hash).await?));
+}
+
+async fn read_blob_hash(uuid: &Uuid, chunk_index: u64, receiver: usize) -> Option<Vec<u8>> {
+    let (_, blob) = read_chunk(uuid, chunk_index, receiver).await.ok()?;
+    let name = blob.file_stem()?.to_str()?;
+
+    return hex::decode(name).ok();
+}
+
+// Persists the index entry of the blob, removes it from the storage once the blob is gone
+async fn save_blob(state: &HashMap<String, BlobInfo>, key: &str) -> anyhow::Result<()> {
+    let storage = STORAGE.read().await;
+    let info = state.get(key);
+    let res = if info.is_some() { storage.put_blob(key, info.unwrap()) } else { storage.remove_blob(key) };
+
+    drop(storage);
+    return res;
+}
+
+// Drops the reference of the chunk, blobs without references are removed
+async fn unref_blob(state: &mut HashMap<String, BlobInfo>, hash: &[u8], uuid: &Uuid, chunk_index: u64, receiver: usize) -> anyhow::Result<()> {
+    let key = hex::encode(hash);
+    let info = state.get_mut(&key);
+    if info.is_none() {
+        return Ok(());
+    }
+
+    let info = info.unwrap();
+    info.refs.remove(&(uuid.clone(), chunk_index, receiver));
+    if !info.refs.is_empty() {
+        return save_blob(state, &key).await;
+    }
+
+    trace!("Removing blob {}", key);
+    state.remove(&key);
+    save_blob(state, &key).await?;
+
+    let path = get_blob_file(hash).await?;
+    if path.is_file() {
+        remove_file(path).await?;
+    }
+
+    return Ok(());
+}
+
+// Points the chunk at the blob, a chunk uploaded before (e.g. by a retry) is replaced
+async fn write_chunk(state: &mut HashMap<String, BlobInfo>, uuid: &Uuid, chunk_index: u64, receiver: usize, header: &[u8], hash: &[u8]) -> anyhow::Result<()> {
+    let old_hash = read_blob_hash(uuid, chunk_index, receiver).await;
+
+    let mut merged = header.to_vec();
+    merged.extend_from_slice(hash);
+    write(get_chunk_file(uuid, chunk_index, receiver).await?, merged).await?;
+
+    if old_hash.is_some() && old_hash.as_ref().unwrap() != hash {
+        unref_blob(state, &old_hash.unwrap(), uuid, chunk_index, receiver).await?;
+    }
+
+    return Ok(());
+}
+
+// Stores an uploaded chunk. If an identical blob exists already, the uploaded one at `temp` is dropped.
+pub async fn store_chunk(uuid: &Uuid, chunk_index: u64, receiver: usize, header: &[u8], temp: &Path, hash: &[u8], owner: &Vec<u8>) -> anyhow::Result<()> {
+    let key = hex::encode(hash);
+    let path = get_blob_file(hash).await?;
+
+    let mut state = BLOBS.write().await;
+    if state.contains_key(&key) && path.is_file() {
+        trace!("Blob {} exists already", key);
+        remove_file(temp).await?;
+    } else {
+        rename(temp, &path).await?;
+        state.insert(key.clone(), BlobInfo { owner: owner.clone(), refs: HashSet::new() });
+    }
+
+    state.get_mut(&key).unwrap().refs.insert((uuid.clone(), chunk_index, receiver));
+    let mut res = write_chunk(&mut state, uuid, chunk_index, receiver, header, hash).await;
+    if res.is_ok() {
+        res = save_blob(&state, &key).await;
+    }
+
+    drop(state);
+    return res;
+}
+
+// Stores a chunk whose blob has been uploaded before. False if there is no such blob of the sender
+// or it does not have `blob_size` bytes, the sender has to upload it again then.
+pub async fn link_chunk(uuid: &Uuid, chunk_index: u64, receiver: usize, header: &[u8], hash: &[u8], owner: &Vec<u8>, blob_size: u64) -> anyhow::Result<bool> {
+    let key = hex::encode(hash);
+    let path = get_blob_file(hash).await?;
+
+    let mut state = BLOBS.write().await;
+    let info = state.get_mut(&key);
+    let is_valid = info.is_some()
+        && &info.as_ref().unwrap().owner == owner
+        && path.metadata().map(|e| e.len() == blob_size).unwrap_or(false);
+
+    if !is_valid {
+        drop(state);
+        return Ok(false);
+    }
+
+    info.unwrap().refs.insert((uuid.clone(), chunk_index, receiver));
+    let mut res = write_chunk(&mut state, uuid, chunk_index, receiver, header, hash).await;
+    if res.is_ok() {
+        res = save_blob(&state, &key).await;
+    }
+
+    drop(state);
+    res?;
+
+    return Ok(true);
+}
+
+pub async fn remove_chunk(uuid: &Uuid, chunk_index: u64, receiver: usize) -> anyhow::Result<()> {
+    let hash = read_blob_hash(uuid, chunk_index, receiver).await;
+
+    let path = get_chunk_file(uuid, chunk_index, receiver).await?;
+    if path.is_file() {
+        remove_file(path).await?;
+    }
+
+    if hash.is_none() {
+        return Ok(());
+    }
+
+    let mut state = BLOBS.write().await;
+    let res = unref_blob(&mut state, &hash.unwrap(), uuid, chunk_index, receiver).await;
+
+    drop(state);
+    return res;
+}
+
+// True if the chunk of a restored transfer is in the index and its blob still exists
+pub async fn is_chunk_indexed(uuid: &Uuid, chunk_index: u64, receiver: usize) -> bool {
+    let hash = read_blob_hash(uuid, chunk_index, receiver).await;
+    if hash.is_none() {
+        return false;
+    }
+
+    let hash = hash.unwrap();
+    let path = get_blob_file(&hash).await;
+    if path.is_err() || !path.unwrap().is_file() {
+        return false;
+    }
+
+    let state = BLOBS.read().await;
+    let is_indexed = state.get(&hex::encode(hash))
+        .map(|e| e.refs.contains(&(uuid.clone(), chunk_index, receiver)))
+        .unwrap_or(false);
+
+    drop(state);
+    return is_indexed;
+}
+
+// Drops references to chunks which have not been restored, blobs without references
+// and blob files which are not in the index (e.g. unfinished uploads). Called after restoring the chunks.
+pub async fn remove_orphaned_blobs(chunks: &HashSet<(Uuid, u64, usize)>) -> anyhow::Result<()> {
+    let mut state = BLOBS.write().await;
+    let keys: Vec<String> = state.keys().cloned().collect();
+    for key in keys {
+        let info = state.get_mut(&key).unwrap();
+        let ref_count = info.refs.len();
+        info.refs.retain(|e| chunks.contains(e));
+
+        if info.refs.is_empty() {
+            state.remove(&key);
+        } else if info.refs.len() == ref_count {
+            continue;
+        }
+
+        save_blob(&state, &key).await?;
+    }
+
+    let mut files = read_dir(get_blob_dir().await?).await?;
+    while let Some(file) = files.next_entry().await? {
+        let name = file.file_name();
+        let key = name.to_str().and_then(|e| e.strip_suffix(".bin"));
+
+        if key.is_none() || !state.contains_key(key.unwrap()) {
+            trace!("Removing orphaned blob {:?}", name);
+            remove_file(file.path()).await?;
+        }
+    }
+
+    drop(state);
+    return Ok(());
+}
+
+// Loads the chunk index of the last run
+pub async fn load_blobs() -> anyhow::Result<()> {
+    let storage = STORAGE.read().await;
+    let blobs = storage.get_blobs();
+
+    drop(storage);
+
+    let mut state = BLOBS.write().await;
+    *state = blobs?;
+
+    drop(state);
+    return Ok(());
+}
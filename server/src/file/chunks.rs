@@ -0,0 +1,21 @@
+use packets::consts::ONE_MB_SIZE;
+
+use super::{consts::CHUNK_BOUNDS, types::ChunkBounds};
+
+pub async fn get_chunk_bounds() -> ChunkBounds {
+    let state = CHUNK_BOUNDS.read().await;
+    let bounds = state.clone();
+
+    drop(state);
+    return bounds;
+}
+
+pub async fn is_allowed_chunk_size(chunk_size: u64) -> bool {
+    let bounds = get_chunk_bounds().await;
+    return chunk_size >= bounds.min && chunk_size <= bounds.max;
+}
+
+// Size of an upload request for one chunk, leaves room for the signature, key and iv
+pub fn get_max_upload_size(chunk_size: u64) -> u64 {
+    return chunk_size + 3 * ONE_MB_SIZE;
+}
\ No newline at end of file
@@ -1,6 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{path::{Path, PathBuf}, sync::Arc};
 
 use lazy_static::lazy_static;
+use tokio::sync::RwLock;
 
 use crate::utils::types::{Users, UsersList};
 
@@ -11,5 +12,13 @@ lazy_static! {
     pub static ref UPLOADING_FILES: FileControllers = FileControllers::default();
     pub static ref USERS: Users = Users::default();
     pub static ref USERS_LIST: UsersList = UsersList::default();
-    pub static ref CHUNK_DIR: PathBuf = Path::new("chunks").to_path_buf();
+    pub static ref CHUNK_DIR: ChunkDirArc = Arc::new(RwLock::new(PathBuf::from(DEFAULT_CHUNK_DIR)));
+    pub static ref BLOBS: Blobs = Blobs::default();
+    pub static ref JOURNAL_DIR: PathBuf = Path::new("transfers").to_path_buf();
+    pub static ref TRANSFER_JOURNALS: TransferJournals = TransferJournals::default();
+    pub static ref RATE_CONFIG: RateConfigArc = RateConfigArc::default();
+    pub static ref USER_RATE_LIMITS: UserRateLimits = UserRateLimits::default();
+    pub static ref CHUNK_BOUNDS: ChunkBoundsArc = ChunkBoundsArc::default();
+    pub static ref JANITOR_CONFIG: JanitorConfigArc = JanitorConfigArc::default();
+    pub static ref QUOTA_CONFIG: QuotaConfigArc = QuotaConfigArc::default();
 }
\ No newline at end of file
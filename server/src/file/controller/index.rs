@@ -1,35 +1,177 @@
+use std::{collections::{BTreeSet, VecDeque}, time::Instant};
+
 use log::trace;
-use packets::{file::{types::FileInfo, processing::start::FileStartProcessing}, types::ByteMessage};
+use packets::{file::{types::FileInfo, processing::{start::FileStartProcessing, tools::get_max_chunks}, journal::TransferJournal}, types::ByteMessage};
 use uuid::Uuid;
 use warp::ws::Message;
 
 use crate::utils::tools::send_msg_specific;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceiverState {
+    pub id: Uuid,
+    pub status: ReceiverStatus,
+    // Chunks this receiver has written to its file
+    pub downloaded: BTreeSet<u64>,
+}
+
+// A file offered to one or more receivers. Receivers are addressed by their position,
+// chunk files of the server are stored per position as connection ids change on reconnects.
 #[readonly::make]
+#[derive(Debug, Clone)]
 pub struct Controller {
+    // `receiver` is the first receiver of the transfer
     #[readonly]
     pub file: FileInfo,
 
-    uuid: Uuid
+    pub receivers: Vec<ReceiverState>,
+    // Chunks every accepted receiver has downloaded, the sender has been told about them
+    pub finished: BTreeSet<u64>,
+    // Finished chunks whose files are still stored, oldest first. Removed by the janitor.
+    pub finished_at: VecDeque<(u64, Instant)>,
+
+    // When the file has been offered
+    pub created: Instant,
+    // Last time a chunk has been up- or downloaded
+    pub last_activity: Instant
 }
 
 impl Controller {
-    pub async fn new(id: &Uuid, file: FileInfo) -> anyhow::Result<Self> {
-        //let threads = get_max_chunks(file.size);
-        let id = id.clone();
+    pub fn new(file: FileInfo, receivers: &Vec<Uuid>) -> Self {
+        let receivers = receivers.iter().map(|e| ReceiverState {
+            id: e.clone(),
+            status: ReceiverStatus::Pending,
+            downloaded: BTreeSet::new()
+        }).collect();
+
+        return Controller {
+            file,
+            receivers,
+            finished: BTreeSet::new(),
+            finished_at: VecDeque::new(),
+            created: Instant::now(),
+            last_activity: Instant::now()
+        };
+    }
+
+    // Restores an accepted transfer. `receivers` are the connection ids of the receivers by position,
+    // receivers which are not connected are nil. Positions which are not in the journal have rejected the file.
+    pub fn from_journal(journal: &TransferJournal, sender: &Uuid, receivers: &Vec<(usize, Uuid)>) -> Self {
+        let count = journal.receivers.iter().map(|e| e.position + 1).max().unwrap_or(0);
+        let mut states: Vec<ReceiverState> = (0..count).map(|_| ReceiverState {
+            id: Uuid::nil(),
+            status: ReceiverStatus::Rejected,
+            downloaded: BTreeSet::new()
+        }).collect();
 
+        for receiver in &journal.receivers {
+            let id = receivers.iter().find(|e| e.0 == receiver.position).map(|e| e.1).unwrap_or(Uuid::nil());
+            states[receiver.position] = ReceiverState {
+                id,
+                status: ReceiverStatus::Accepted,
+                downloaded: receiver.downloaded.clone()
+            };
+        }
+
+        let first = states.iter().find(|e| e.status == ReceiverStatus::Accepted).map(|e| e.id).unwrap_or(Uuid::nil());
+        let finished = journal.get_finished();
+
+        let now = Instant::now();
+        return Controller {
+            file: journal.to_file_info(sender, &first),
+            receivers: states,
+            finished_at: finished.iter().map(|e| (e.clone(), now)).collect(),
+            finished,
+            created: now,
+            last_activity: now
+        };
+    }
+
+    pub fn get_position(&self, receiver: &Uuid) -> Option<usize> {
+        return self.receivers.iter().position(|e| e.id == *receiver);
+    }
+
+    // Position of `receiver` if it accepted the file
+    pub fn get_accepted_position(&self, receiver: &Uuid) -> Option<usize> {
+        let pos = self.get_position(receiver)?;
+        if self.receivers[pos].status != ReceiverStatus::Accepted {
+            return None;
+        }
+
+        return Some(pos);
+    }
+
+    pub fn get_accepted(&self) -> Vec<Uuid> {
+        return self.receivers.iter()
+            .filter(|e| e.status == ReceiverStatus::Accepted)
+            .map(|e| e.id)
+            .collect();
+    }
+
+    pub fn is_answered(&self) -> bool {
+        return self.receivers.iter().all(|e| e.status != ReceiverStatus::Pending);
+    }
+
+    pub fn set_status(&mut self, receiver: &Uuid, status: ReceiverStatus) -> bool {
+        let pos = self.get_position(receiver);
+        if pos.is_none() {
+            return false;
+        }
+
+        self.receivers[pos.unwrap()].status = status;
+        return true;
+    }
+
+    // Chunks every accepted receiver has downloaded which have not been marked as finished yet
+    pub fn take_finished(&mut self) -> Vec<u64> {
+        let accepted: Vec<&ReceiverState> = self.receivers.iter()
+            .filter(|e| e.status == ReceiverStatus::Accepted)
+            .collect();
+
+        if accepted.is_empty() {
+            return Vec::new();
+        }
+
+        let done: Vec<u64> = accepted[0].downloaded.iter()
+            .filter(|e| !self.finished.contains(e))
+            .filter(|e| accepted.iter().all(|r| r.downloaded.contains(e)))
+            .cloned()
+            .collect();
+
+        let now = Instant::now();
+        self.finished.extend(done.iter());
+        self.finished_at.extend(done.iter().map(|e| (e.clone(), now)));
+
+        return done;
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    // True once every accepted receiver has the whole file
+    pub fn is_done(&self) -> bool {
+        let max_chunks = get_max_chunks(self.file.size, self.file.chunk_size);
+        return self.finished.len() as u64 >= max_chunks;
+    }
+
+    // Tells the sender to start uploading for the accepted receivers
+    pub async fn start(&self, uuid: &Uuid) -> anyhow::Result<()> {
         trace!("Sending start processing packet...");
         let to_send = FileStartProcessing {
-            uuid: id,
-            //threads
+            uuid: uuid.clone(),
+            chunk_size: self.file.chunk_size,
+            receivers: self.get_accepted()
         }.serialize();
 
-        send_msg_specific(file.sender, Message::binary(to_send)).await?;
-
-        trace!("Returning");
-        return Ok(Controller {
-            file,
-            uuid: id
-        });
+        send_msg_specific(self.file.sender, Message::binary(to_send)).await?;
+        return Ok(());
     }
-}
\ No newline at end of file
+}
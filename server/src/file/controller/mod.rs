@@ -1 +1,2 @@
-pub mod index;
\ No newline at end of file
+pub mod index;
+pub mod tools;
\ No newline at end of file
@@ -0,0 +1,130 @@
+use log::trace;
+use packets::{file::processing::{abort::ChunkAbortMsg, downloaded::ChunkDownloadedMsg}, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{
+    file::{consts::{PENDING_UPLOADS, UPLOADING_FILES}, journal::{create_journal, remove_chunks, remove_transfer, update_journal}},
+    utils::tools::{send_error, send_msg_specific},
+};
+
+use super::index::ReceiverStatus;
+
+// Starts the transfer once every receiver answered the offer
+pub async fn try_start(uuid: &Uuid) -> anyhow::Result<()> {
+    let mut state = PENDING_UPLOADS.write().await;
+    let is_answered = state.get(uuid).map(|e| e.is_answered()).unwrap_or(false);
+    if !is_answered {
+        drop(state);
+        return Ok(());
+    }
+
+    trace!("Removing pending upload from {}", uuid);
+    let controller = state.remove(uuid).unwrap();
+    drop(state);
+
+    if controller.get_accepted().is_empty() {
+        trace!("Every receiver rejected {}", uuid);
+        let _ = send_error(controller.file.sender, &format!("Nobody accepted the file '{}'.", controller.file.filename)).await;
+        return Ok(());
+    }
+
+    let res = create_journal(uuid, &controller).await;
+    if res.is_err() {
+        eprintln!("Transfer {} will not be resumable: {}", uuid, res.unwrap_err());
+    }
+
+    // Has to be stored before the sender starts uploading
+    trace!("Adding controller to uploading files {}", uuid);
+    let mut state = UPLOADING_FILES.write().await;
+    state.insert(uuid.clone(), controller.clone());
+
+    drop(state);
+    let res = controller.start(uuid).await;
+    if res.is_err() {
+        remove_transfer(uuid).await?;
+        return res;
+    }
+
+    return Ok(());
+}
+
+// Tells the sender about chunks every receiver has and removes the transfer if it is done
+pub async fn notify_finished(uuid: &Uuid, sender: &Uuid, chunks: Vec<u64>, is_done: bool) -> anyhow::Result<()> {
+    for chunk_index in chunks {
+        let msg = ChunkDownloadedMsg { uuid: uuid.clone(), chunk_index };
+        let res = send_msg_specific(sender.clone(), Message::binary(msg.serialize())).await;
+        if res.is_err() {
+            // Sender is offline, it gets the finished chunks once it resumes
+            trace!("Could not forward downloaded chunk of {}: {}", uuid, res.unwrap_err());
+        }
+    }
+
+    if is_done {
+        trace!("Transfer {} is done", uuid);
+        remove_transfer(uuid).await?;
+    }
+
+    return Ok(());
+}
+
+// Removes a receiver which aborted from the transfer, the others keep receiving the file
+pub async fn drop_receiver(uuid: &Uuid, receiver: &Uuid) -> anyhow::Result<()> {
+    let pending = PENDING_UPLOADS.read().await.get(uuid).and_then(|e| e.get_position(receiver));
+    let uploading = UPLOADING_FILES.read().await.get(uuid).and_then(|e| e.get_position(receiver));
+
+    let pos = pending.or(uploading);
+    if pos.is_none() {
+        return Ok(());
+    }
+
+    return drop_position(uuid, pos.unwrap()).await;
+}
+
+// Same as `drop_receiver`, but by position as receivers of a restored transfer may not be connected yet
+pub async fn drop_position(uuid: &Uuid, pos: usize) -> anyhow::Result<()> {
+    let mut state = PENDING_UPLOADS.write().await;
+    let pending = state.get_mut(uuid);
+    if pending.is_some() {
+        let pending = pending.unwrap();
+        if pos < pending.receivers.len() {
+            pending.receivers[pos].status = ReceiverStatus::Rejected;
+        }
+
+        drop(state);
+        return try_start(uuid).await;
+    }
+
+    drop(state);
+
+    let mut state = UPLOADING_FILES.write().await;
+    let controller = state.get_mut(uuid);
+    if controller.is_none() || pos >= controller.as_ref().unwrap().receivers.len() {
+        drop(state);
+        return Ok(());
+    }
+
+    let controller = controller.unwrap();
+    controller.receivers[pos].status = ReceiverStatus::Rejected;
+
+    let sender = controller.file.sender;
+    let finished = controller.take_finished();
+    let is_done = controller.is_done();
+    let is_empty = controller.get_accepted().is_empty();
+
+    drop(state);
+    trace!("Dropping receiver {} of {}", pos, uuid);
+    remove_chunks(uuid, Some(pos)).await?;
+
+    if is_empty {
+        let _ = send_msg_specific(sender, Message::binary(ChunkAbortMsg { uuid: uuid.clone() }.serialize())).await;
+        return remove_transfer(uuid).await;
+    }
+
+    let res = update_journal(uuid, |j| j.receivers.retain(|e| e.position != pos)).await;
+    if res.is_err() {
+        trace!("Could not remove receiver {} from the journal of {}: {}", pos, uuid, res.unwrap_err());
+    }
+
+    return notify_finished(uuid, &sender, finished, is_done).await;
+}
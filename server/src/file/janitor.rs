@@ -0,0 +1,103 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use log::trace;
+use packets::{file::processing::abort::ChunkAbortMsg, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::utils::tools::send_msg_specific;
+
+use super::{blobs::remove_chunk, consts::{JANITOR_CONFIG, PENDING_UPLOADS, UPLOADING_FILES}, controller::index::Controller, journal::remove_transfer, types::{JanitorConfig, JANITOR_INTERVAL_SECS}};
+
+pub async fn get_janitor_config() -> JanitorConfig {
+    let state = JANITOR_CONFIG.read().await;
+    let config = state.clone();
+
+    drop(state);
+    return config;
+}
+
+// Removes offers nobody answered, transfers nobody works on anymore and chunks every receiver has
+pub async fn run_janitor() {
+    let mut interval = tokio::time::interval(Duration::from_secs(JANITOR_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+
+        let res = collect_garbage().await;
+        if res.is_err() {
+            eprintln!("Could not clean up transfers: {}", res.unwrap_err());
+        }
+    }
+}
+
+async fn collect_garbage() -> anyhow::Result<()> {
+    let config = get_janitor_config().await;
+
+    let state = PENDING_UPLOADS.read().await;
+    let mut expired = get_expired(&state, config.pending, |e| e.created);
+
+    drop(state);
+
+    let state = UPLOADING_FILES.read().await;
+    expired.append(&mut get_expired(&state, config.idle, |e| e.last_activity));
+
+    drop(state);
+    for (uuid, controller) in expired {
+        expire_transfer(&uuid, &controller).await?;
+    }
+
+    return remove_downloaded_chunks(config.downloaded).await;
+}
+
+fn get_expired(state: &HashMap<Uuid, Controller>, ttl: Option<Duration>, since: fn(&Controller) -> Instant) -> Vec<(Uuid, Controller)> {
+    if ttl.is_none() {
+        return Vec::new();
+    }
+
+    let ttl = ttl.unwrap();
+    return state.iter()
+        .filter(|(_, e)| since(e).elapsed() >= ttl)
+        .map(|(k, e)| (k.clone(), e.clone()))
+        .collect();
+}
+
+// Tells everyone involved that the transfer is gone
+async fn expire_transfer(uuid: &Uuid, controller: &Controller) -> anyhow::Result<()> {
+    trace!("Transfer {} of '{}' expired", uuid, controller.file.filename);
+
+    let msg = ChunkAbortMsg { uuid: uuid.clone() }.serialize();
+    for receiver in &controller.receivers {
+        let _ = send_msg_specific(receiver.id, Message::binary(msg.clone())).await;
+    }
+
+    let _ = send_msg_specific(controller.file.sender, Message::binary(msg)).await;
+    return remove_transfer(uuid).await;
+}
+
+// Chunks every receiver confirmed are removed once the ttl is over instead of when the whole transfer is done
+async fn remove_downloaded_chunks(ttl: Option<Duration>) -> anyhow::Result<()> {
+    if ttl.is_none() {
+        return Ok(());
+    }
+
+    let ttl = ttl.unwrap();
+    let mut expired = Vec::new();
+
+    let mut state = UPLOADING_FILES.write().await;
+    for (uuid, controller) in state.iter_mut() {
+        while controller.finished_at.front().map(|e| e.1.elapsed() >= ttl).unwrap_or(false) {
+            let (chunk_index, _) = controller.finished_at.pop_front().unwrap();
+            expired.push((uuid.clone(), chunk_index, controller.receivers.len()));
+        }
+    }
+
+    drop(state);
+    for (uuid, chunk_index, receivers) in expired {
+        trace!("Removing downloaded chunk {} of {}", chunk_index, uuid);
+        for receiver in 0..receivers {
+            remove_chunk(&uuid, chunk_index, receiver).await?;
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anyhow::anyhow;
+use log::trace;
+use packets::file::journal::{JournalReceiver, TransferJournal};
+use tokio::fs::{read, read_dir, remove_dir, remove_file};
+use uuid::Uuid;
+
+use crate::{queue::tools::get_user_fingerprint, storage::{consts::STORAGE, tools::get_identity_name}};
+
+use super::blobs::{is_chunk_indexed, load_blobs, remove_chunk, remove_orphaned_blobs};
+use super::controller::index::{Controller, ReceiverStatus};
+use super::consts::{JOURNAL_DIR, PENDING_UPLOADS, TRANSFER_JOURNALS, UPLOADING_FILES};
+use super::tools::get_chunk_dir;
+
+async fn save_journal(journal: &TransferJournal) -> anyhow::Result<()> {
+    let storage = STORAGE.read().await;
+    let res = storage.put_transfer(journal);
+
+    drop(storage);
+    return res;
+}
+
+pub async fn get_journal(uuid: &Uuid) -> Option<TransferJournal> {
+    let state = TRANSFER_JOURNALS.read().await;
+    let journal = state.get(uuid).cloned();
+
+    drop(state);
+    return journal;
+}
+
+// Journals every receiver which accepted the file by its position in the offer
+pub async fn create_journal(uuid: &Uuid, controller: &Controller) -> anyhow::Result<()> {
+    let sender = get_user_fingerprint(&controller.file.sender).await;
+    if sender.is_none() {
+        return Err(anyhow!("Sender of {} did not send a public key.", uuid));
+    }
+
+    let mut receivers = Vec::new();
+    for (position, receiver) in controller.receivers.iter().enumerate() {
+        if receiver.status != ReceiverStatus::Accepted {
+            continue;
+        }
+
+        let fingerprint = get_user_fingerprint(&receiver.id).await;
+        if fingerprint.is_none() {
+            return Err(anyhow!("Receiver {} of {} did not send a public key.", receiver.id, uuid));
+        }
+
+        receivers.push(JournalReceiver::new(position, fingerprint.unwrap()));
+    }
+
+    let journal = TransferJournal::new(uuid, &controller.file, sender.unwrap(), receivers);
+
+    let mut state = TRANSFER_JOURNALS.write().await;
+    let res = save_journal(&journal).await;
+    state.insert(uuid.clone(), journal);
+
+    drop(state);
+    return res;
+}
+
+// True if the receiver at `position` has the chunk already or it is stored for it.
+// Resumed uploads to multiple receivers contain chunks only some of them are missing.
+pub async fn has_chunk(uuid: &Uuid, chunk_index: u64, position: usize) -> bool {
+    let state = TRANSFER_JOURNALS.read().await;
+    let has_chunk = state.get(uuid)
+        .and_then(|e| e.receivers.iter().find(|r| r.position == position))
+        .map(|e| e.uploaded.contains(&chunk_index) || e.downloaded.contains(&chunk_index))
+        .unwrap_or(false);
+
+    drop(state);
+    return has_chunk;
+}
+
+// Applies `f` to the journal of the transfer, persists and returns it
+pub async fn update_journal<F>(uuid: &Uuid, f: F) -> anyhow::Result<TransferJournal>
+where
+    F: FnOnce(&mut TransferJournal),
+{
+    let mut state = TRANSFER_JOURNALS.write().await;
+    let journal = state.get_mut(uuid);
+    if journal.is_none() {
+        drop(state);
+        return Err(anyhow!("No journal for transfer {}.", uuid));
+    }
+
+    let journal = journal.unwrap();
+    f(journal);
+
+    let journal = journal.clone();
+    let res = save_journal(&journal).await;
+
+    drop(state);
+    res?;
+
+    return Ok(journal);
+}
+
+// Chunk files are named `<uuid>-<chunk index>-<receiver>.bin`
+fn parse_chunk_name(name: &str) -> Option<(Uuid, u64, usize)> {
+    let name = name.strip_suffix(".bin")?;
+    let (name, receiver) = name.rsplit_once("-")?;
+    let (uuid, index) = name.rsplit_once("-")?;
+
+    let uuid = Uuid::from_str(uuid).ok()?;
+    let index = u64::from_str(index).ok()?;
+    let receiver = usize::from_str(receiver).ok()?;
+
+    return Some((uuid, index, receiver));
+}
+
+// Removes the chunks of the transfer, only the ones stored for `receiver` if given
+pub async fn remove_chunks(uuid: &Uuid, receiver: Option<usize>) -> anyhow::Result<()> {
+    let chunk_dir = get_chunk_dir().await;
+    if !chunk_dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut files = read_dir(chunk_dir).await?;
+    while let Some(file) = files.next_entry().await? {
+        let name = file.file_name();
+        let parsed = name.to_str().and_then(|e| parse_chunk_name(e));
+
+        if parsed.is_none() {
+            continue;
+        }
+
+        let (chunk_uuid, index, pos) = parsed.unwrap();
+        if chunk_uuid == *uuid && receiver.unwrap_or(pos) == pos {
+            trace!("Removing chunk file {:?}", name);
+            remove_chunk(uuid, index, pos).await?;
+        }
+    }
+
+    return Ok(());
+}
+
+// Removes everything stored for the transfer, used when it has been finished or aborted
+pub async fn remove_transfer(uuid: &Uuid) -> anyhow::Result<()> {
+    trace!("Removing transfer {}", uuid);
+    PENDING_UPLOADS.write().await.remove(uuid);
+    UPLOADING_FILES.write().await.remove(uuid);
+
+    let mut state = TRANSFER_JOURNALS.write().await;
+    state.remove(uuid);
+
+    let storage = STORAGE.read().await;
+    let res = storage.remove_transfer(uuid);
+
+    drop(storage);
+    drop(state);
+    res?;
+
+    return remove_chunks(uuid, None).await;
+}
+
+// Journals were stored as files before there was a storage, they are moved into it
+async fn migrate_journal_dir() -> anyhow::Result<()> {
+    if !JOURNAL_DIR.is_dir() {
+        return Ok(());
+    }
+
+    let mut files = read_dir(JOURNAL_DIR.as_path()).await?;
+    while let Some(file) = files.next_entry().await? {
+        let journal = TransferJournal::deserialize(&read(file.path()).await?);
+        if journal.is_err() {
+            eprintln!("Could not read transfer journal {:?}: {}", file.path(), journal.unwrap_err());
+            continue;
+        }
+
+        save_journal(&journal.unwrap()).await?;
+        remove_file(file.path()).await?;
+    }
+
+    return Ok(remove_dir(JOURNAL_DIR.as_path()).await?);
+}
+
+// Restores the transfers of the last run. Nobody is connected yet, so the connection
+// ids of the transfers are nil until both sides resumed it.
+pub async fn load_journals() -> anyhow::Result<()> {
+    migrate_journal_dir().await?;
+    load_blobs().await?;
+
+    let storage = STORAGE.read().await;
+    let journals = storage.get_transfers();
+
+    drop(storage);
+
+    let mut state = TRANSFER_JOURNALS.write().await;
+    let mut uploading = UPLOADING_FILES.write().await;
+    for journal in journals? {
+        let mut receivers = Vec::new();
+        for receiver in &journal.receivers {
+            receivers.push(get_identity_name(&receiver.fingerprint).await);
+        }
+
+        trace!("Restoring transfer {} from {} to {}", journal.uuid, get_identity_name(&journal.sender).await, receivers.join(", "));
+
+        uploading.insert(journal.uuid, Controller::from_journal(&journal, &Uuid::nil(), &Vec::new()));
+        state.insert(journal.uuid, journal);
+    }
+
+    drop(uploading);
+    println!("Restored {} transfer(s)", state.len());
+
+    // Chunks which are not in the index are either orphaned or have not been written completely
+    let mut restored = HashSet::new();
+    let chunk_dir = get_chunk_dir().await;
+    if chunk_dir.is_dir() {
+        let mut files = read_dir(chunk_dir).await?;
+        while let Some(file) = files.next_entry().await? {
+            let name = file.file_name();
+            let parsed = name.to_str().and_then(|e| parse_chunk_name(e));
+
+            if file.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let keep = parsed.is_some() && {
+                let (uuid, index, receiver) = parsed.unwrap();
+                state.get(&uuid)
+                    .and_then(|e| e.receivers.iter().find(|r| r.position == receiver))
+                    .map(|e| e.uploaded.contains(&index))
+                    .unwrap_or(false)
+                    && is_chunk_indexed(&uuid, index, receiver).await
+            };
+
+            if !keep {
+                trace!("Removing orphaned chunk file {:?}", name);
+                remove_file(file.path()).await?;
+                continue;
+            }
+
+            restored.insert(parsed.unwrap());
+        }
+    }
+
+    // Chunks which are gone have to be uploaded again, unless the receiver has them already
+    for journal in state.values_mut() {
+        let uuid = journal.uuid;
+        let mut missing = 0;
+        for receiver in journal.receivers.iter_mut() {
+            let uploaded = receiver.uploaded.len();
+            let downloaded = receiver.downloaded.clone();
+            let position = receiver.position;
+            receiver.uploaded.retain(|e| downloaded.contains(e) || restored.contains(&(uuid, *e, position)));
+
+            missing += uploaded - receiver.uploaded.len();
+        }
+
+        if missing > 0 {
+            trace!("{} chunk(s) of {} are missing", missing, uuid);
+            save_journal(journal).await?;
+        }
+    }
+
+    drop(state);
+    return remove_orphaned_blobs(&restored).await;
+}
@@ -1,4 +1,10 @@
 pub mod consts;
 pub mod types;
 pub mod controller;
-pub mod tools;
\ No newline at end of file
+pub mod tools;
+pub mod journal;
+pub mod rate;
+pub mod chunks;
+pub mod blobs;
+pub mod janitor;
+pub mod quota;
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use packets::communication::error::ErrorCode;
+use uuid::Uuid;
+
+use super::consts::{QUOTA_CONFIG, UPLOADING_FILES};
+use super::controller::index::Controller;
+use super::types::QuotaConfig;
+
+pub async fn get_quota_config() -> QuotaConfig {
+    let state = QUOTA_CONFIG.read().await;
+    let config = state.clone();
+
+    drop(state);
+    return config;
+}
+
+// Bytes reserved by the transfers of `sender` and by all transfers, every transfer reserves the full size of its file.
+// This is not what is on disk: blobs are shared between transfers and downloaded chunks are removed early.
+async fn get_usage(pending: &HashMap<Uuid, Controller>, sender: &Uuid) -> (usize, u64, u64) {
+    let mut transfers = 0;
+    let mut user = 0;
+    let mut total = 0;
+
+    let uploading = UPLOADING_FILES.read().await;
+    for controller in pending.values().chain(uploading.values()) {
+        total += controller.file.size;
+        if controller.file.sender == *sender {
+            transfers += 1;
+            user += controller.file.size;
+        }
+    }
+
+    drop(uploading);
+    return (transfers, user, total);
+}
+
+// Checks new files of `sender` before they are offered to anyone. Returns the reason if one of them is not allowed.
+// `pending` is the locked `PENDING_UPLOADS`, the files have to be inserted before it is unlocked so parallel offers see them.
+pub async fn check_quota(pending: &HashMap<Uuid, Controller>, sender: &Uuid, sizes: &Vec<u64>) -> Option<(ErrorCode, String)> {
+    let config = get_quota_config().await;
+
+    let largest = sizes.iter().max().cloned().unwrap_or(0);
+    if config.max_file_size.is_some() && largest > config.max_file_size.unwrap() {
+        return Some((ErrorCode::FileTooLarge, format!("Files may have at most {} bytes.", config.max_file_size.unwrap())));
+    }
+
+    let (transfers, user, total) = get_usage(pending, sender).await;
+    let size: u64 = sizes.iter().sum();
+
+    if config.max_transfers.is_some() && transfers + sizes.len() > config.max_transfers.unwrap() {
+        return Some((ErrorCode::TooManyTransfers, format!("You may only send {} files at the same time.", config.max_transfers.unwrap())));
+    }
+
+    if config.user_quota.is_some() && user + size > config.user_quota.unwrap() {
+        return Some((ErrorCode::UserQuotaExceeded, format!("Your transfers may take at most {} bytes on the server, {} are in use.", config.user_quota.unwrap(), user)));
+    }
+
+    if config.reserved_quota.is_some() && total + size > config.reserved_quota.unwrap() {
+        return Some((ErrorCode::ReservedQuotaExceeded, format!("The server does not take more files right now, {} of {} bytes are reserved by other transfers.", total, config.reserved_quota.unwrap())));
+    }
+
+    return None;
+}
@@ -0,0 +1,58 @@
+use log::trace;
+use packets::util::rate::TokenBucket;
+use uuid::Uuid;
+
+use super::{consts::{RATE_CONFIG, USER_RATE_LIMITS}, types::{RateConfig, UserRateLimit}};
+
+pub async fn get_rate_config() -> RateConfig {
+    let state = RATE_CONFIG.read().await;
+    let config = state.clone();
+
+    drop(state);
+    return config;
+}
+
+// Waits until the user may upload `bytes` more
+pub async fn throttle_upload(user: &Uuid, bytes: u64) {
+    throttle(user, bytes, true).await;
+}
+
+// Waits until the user may download `bytes` more
+pub async fn throttle_download(user: &Uuid, bytes: u64) {
+    throttle(user, bytes, false).await;
+}
+
+async fn throttle(user: &Uuid, bytes: u64, upload: bool) {
+    let config = get_rate_config().await;
+    let rate = if upload { config.upload } else { config.download };
+    if rate == 0 {
+        return;
+    }
+
+    let mut state = USER_RATE_LIMITS.write().await;
+    let limit = state.entry(user.clone()).or_insert_with(|| UserRateLimit {
+        upload: TokenBucket::new(config.upload),
+        download: TokenBucket::new(config.download),
+    });
+
+    // The config may have changed since the bucket has been created
+    let bucket = if upload { &mut limit.upload } else { &mut limit.download };
+    if bucket.get_rate() != rate {
+        bucket.set_rate(rate);
+    }
+
+    let wait = bucket.take(bytes);
+
+    drop(state);
+    if !wait.is_zero() {
+        trace!("Throttling {} for {:?}", user, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+pub async fn remove_rate_limit(user: &Uuid) {
+    let mut state = USER_RATE_LIMITS.write().await;
+    state.remove(user);
+
+    drop(state);
+}
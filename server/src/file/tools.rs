@@ -1,13 +1,12 @@
 use std::path::PathBuf;
 
 use anyhow::anyhow;
-use packets::file::types::FileInfo;
-use tokio::fs::create_dir;
+use tokio::fs::create_dir_all;
 use uuid::Uuid;
 
-use super::consts::{PENDING_UPLOADS, CHUNK_DIR, UPLOADING_FILES};
+use super::{consts::{PENDING_UPLOADS, CHUNK_DIR, UPLOADING_FILES}, controller::index::Controller};
 
-pub async fn get_pending_file(uuid: &Uuid) -> anyhow::Result<FileInfo> {
+pub async fn get_pending_controller(uuid: &Uuid) -> anyhow::Result<Controller> {
     let state = PENDING_UPLOADS.read().await;
     let info = state.get(&uuid);
     let out = if info.is_none() { Err(anyhow!("Could not find upload")) } else { Ok(info.unwrap().clone()) };
@@ -17,7 +16,7 @@ pub async fn get_pending_file(uuid: &Uuid) -> anyhow::Result<FileInfo> {
 }
 
 
-pub async fn get_uploading_file(uuid: &Uuid) -> anyhow::Result<FileInfo> {
+pub async fn get_uploading_controller(uuid: &Uuid) -> anyhow::Result<Controller> {
     let state = UPLOADING_FILES.read().await;
     let info = state.get(&uuid);
     let out = if info.is_none() { Err(anyhow!("Could not find upload on uploading files")) } else { Ok(info.unwrap().clone()) };
@@ -27,10 +26,30 @@ pub async fn get_uploading_file(uuid: &Uuid) -> anyhow::Result<FileInfo> {
 }
 
 
-pub async fn get_chunk_file(uuid: &Uuid, chunk_index: u64) -> anyhow::Result<PathBuf> {
-    let mut file = CHUNK_DIR.to_path_buf().clone();
-    if !CHUNK_DIR.is_dir() { create_dir(file.clone()).await?; }
+// Keeps the janitor from removing a transfer somebody is still working on
+pub async fn touch_transfer(uuid: &Uuid) {
+    let mut state = UPLOADING_FILES.write().await;
+    let controller = state.get_mut(uuid);
+    if controller.is_some() {
+        controller.unwrap().touch();
+    }
 
-    file.push(format!("{}-{}.bin", uuid.to_string(), chunk_index));
+    drop(state);
+}
+
+pub async fn get_chunk_dir() -> PathBuf {
+    let state = CHUNK_DIR.read().await;
+    let dir = state.clone();
+
+    drop(state);
+    return dir;
+}
+
+// Chunk files are stored per receiver position of the transfer
+pub async fn get_chunk_file(uuid: &Uuid, chunk_index: u64, receiver: usize) -> anyhow::Result<PathBuf> {
+    let mut file = get_chunk_dir().await;
+    if !file.is_dir() { create_dir_all(file.clone()).await?; }
+
+    file.push(format!("{}-{}-{}.bin", uuid.to_string(), chunk_index, receiver));
     return Ok(file);
 }
\ No newline at end of file
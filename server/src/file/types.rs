@@ -1,8 +1,145 @@
-use std::{sync::Arc, collections::HashMap};
-use packets::file::types::FileInfo;
+use std::{sync::Arc, collections::{HashMap, HashSet}, path::PathBuf, time::Duration};
+use anyhow::anyhow;
+use packets::{file::journal::TransferJournal, util::{rate::TokenBucket, tools::{u64_from_vec, usize_to_vec, uuid_from_vec, vec_to_usize}, vec::extract_vec}, consts::{MIN_CHUNK_SIZE, MAX_CHUNK_SIZE}};
 
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-pub type FileControllers = Arc<RwLock<HashMap<Uuid, FileInfo>>>;
-pub type PendingUploads = Arc<RwLock<HashMap<Uuid, FileInfo>>>;
\ No newline at end of file
+use super::controller::index::Controller;
+
+pub type FileControllers = Arc<RwLock<HashMap<Uuid, Controller>>>;
+pub type PendingUploads = Arc<RwLock<HashMap<Uuid, Controller>>>;
+pub type TransferJournals = Arc<RwLock<HashMap<Uuid, TransferJournal>>>;
+
+// Encrypted data of a chunk, shared by every transfer that sends the same chunk with the same key
+#[derive(Debug, Clone)]
+pub struct BlobInfo {
+    // Fingerprint of the sender, only they may link the blob to other transfers
+    pub owner: Vec<u8>,
+    // Chunks (transfer uuid, chunk index and receiver position) that point to this blob
+    pub refs: HashSet<(Uuid, u64, usize)>,
+}
+
+impl BlobInfo {
+    // Layout: `owner size | owner | ref count | (uuid | chunk index | receiver)...`
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let mut merged = usize_to_vec(self.owner.len())?;
+        merged.append(&mut self.owner.clone());
+        merged.append(&mut usize_to_vec(self.refs.len())?);
+
+        for (uuid, chunk_index, receiver) in &self.refs {
+            merged.append(&mut uuid.as_bytes().to_vec());
+            merged.append(&mut chunk_index.to_le_bytes().to_vec());
+            merged.append(&mut usize_to_vec(*receiver)?);
+        }
+
+        return Ok(merged);
+    }
+
+    pub fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let owner_size = vec_to_usize(&mut data)?;
+        let owner = extract_vec(0..owner_size, &mut data)?;
+
+        let mut refs = HashSet::new();
+        let count = vec_to_usize(&mut data)?;
+        for _ in 0..count {
+            let uuid = uuid_from_vec(&mut data)?;
+            let chunk_index = u64_from_vec(&mut data)?;
+            let receiver = vec_to_usize(&mut data)?;
+
+            refs.insert((uuid, chunk_index, receiver));
+        }
+
+        if !data.is_empty() {
+            return Err(anyhow!("Trailing bytes after blob info."));
+        }
+
+        return Ok(BlobInfo { owner, refs });
+    }
+}
+
+// Key is the hex encoded sha256 of the blob
+pub type Blobs = Arc<RwLock<HashMap<String, BlobInfo>>>;
+
+// Bytes per second each user may up- / download, 0 is unlimited
+#[derive(Debug, Clone, Default)]
+pub struct RateConfig {
+    pub upload: u64,
+    pub download: u64,
+}
+
+#[derive(Debug)]
+pub struct UserRateLimit {
+    pub upload: TokenBucket,
+    pub download: TokenBucket,
+}
+
+pub type RateConfigArc = Arc<RwLock<RateConfig>>;
+pub type UserRateLimits = Arc<RwLock<HashMap<Uuid, UserRateLimit>>>;
+
+// Chunk sizes clients may use, sent to them in the hello ack
+#[derive(Debug, Clone)]
+pub struct ChunkBounds {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Default for ChunkBounds {
+    fn default() -> Self {
+        return ChunkBounds {
+            min: MIN_CHUNK_SIZE,
+            max: MAX_CHUNK_SIZE,
+        };
+    }
+}
+
+pub type ChunkBoundsArc = Arc<RwLock<ChunkBounds>>;
+
+pub const DEFAULT_PENDING_TTL_SECS: u64 = 10 * 60;
+pub const DEFAULT_IDLE_TTL_SECS: u64 = 24 * 60 * 60;
+pub const DEFAULT_DOWNLOADED_TTL_SECS: u64 = 5 * 60;
+pub const JANITOR_INTERVAL_SECS: u64 = 30;
+
+// How long the janitor keeps what nobody uses anymore, None keeps it forever
+#[derive(Debug, Clone)]
+pub struct JanitorConfig {
+    // Offers nobody answered
+    pub pending: Option<Duration>,
+    // Accepted transfers without any up- or downloads, e.g. waiting for a resume that never comes
+    pub idle: Option<Duration>,
+    // Chunks every receiver has downloaded
+    pub downloaded: Option<Duration>,
+}
+
+impl Default for JanitorConfig {
+    fn default() -> Self {
+        return JanitorConfig {
+            pending: Some(Duration::from_secs(DEFAULT_PENDING_TTL_SECS)),
+            idle: Some(Duration::from_secs(DEFAULT_IDLE_TTL_SECS)),
+            downloaded: Some(Duration::from_secs(DEFAULT_DOWNLOADED_TTL_SECS)),
+        };
+    }
+}
+
+pub type JanitorConfigArc = Arc<RwLock<JanitorConfig>>;
+
+// Limits of what users may store on the server, None is unlimited
+#[derive(Debug, Clone, Default)]
+pub struct QuotaConfig {
+    pub max_file_size: Option<u64>,
+    // Pending and running transfers a user may send at the same time
+    pub max_transfers: Option<usize>,
+    // Sum of the file sizes of all transfers a user sends at the same time
+    pub user_quota: Option<u64>,
+    // Sum of the file sizes of all pending and running transfers on the server
+    pub reserved_quota: Option<u64>,
+}
+
+pub type QuotaConfigArc = Arc<RwLock<QuotaConfig>>;
+
+pub const DEFAULT_CHUNK_DIR: &str = "chunks";
+
+// Set once on startup, before any transfer is restored
+pub type ChunkDirArc = Arc<RwLock<PathBuf>>;
\ No newline at end of file
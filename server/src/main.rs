@@ -1,33 +1,57 @@
-
-use std::net::{IpAddr, Ipv4Addr};
+use std::process::exit;
 
 use clap::Parser;
-use file::consts::CHUNK_DIR;
+use config::tools::{apply_settings, resolve_settings};
+use file::{janitor::run_janitor, journal::load_journals};
 use routes::router::serve_routes;
-use tokio::fs::remove_dir_all;
+use storage::tools::{open_storage, set_storage};
 use crate::utils::types::*;
 
 mod utils;
 mod routes;
 mod file;
+mod queue;
+mod room;
+mod storage;
+mod config;
+mod tls;
 
 #[tokio::main]
 async fn main() {
-    pretty_env_logger::init();
-    let p = CHUNK_DIR.as_path();
-    if p.is_dir() {
-        let e = remove_dir_all(p).await;
-        if e.is_err() {
-            eprintln!("Could not remove chunk dir: {}", e.unwrap_err());
-        }
-    }
-
     // Keep track of all connected users, key is usize, value
     // is a websocket sender.
     let args = Args::parse();
 
-    let addr = args.bind.unwrap_or(IpAddr::V4(Ipv4Addr::new(127,0,0,1)));
-    let port = args.port;
+    let settings = resolve_settings(args);
+    if settings.is_err() {
+        eprintln!("Invalid configuration: {}", settings.unwrap_err());
+        exit(1);
+    }
+
+    let settings = settings.unwrap();
+    if settings.log.is_some() {
+        pretty_env_logger::formatted_builder()
+            .parse_filters(settings.log.as_ref().unwrap())
+            .init();
+    } else {
+        pretty_env_logger::init();
+    }
+
+    apply_settings(&settings).await;
+
+    let storage = open_storage(&settings.storage);
+    if storage.is_err() {
+        eprintln!("Could not open storage at '{}': {}", settings.storage, storage.err().unwrap());
+        exit(1);
+    }
+
+    set_storage(storage.unwrap()).await;
+    let e = load_journals().await;
+    if e.is_err() {
+        eprintln!("Could not restore transfers: {}", e.unwrap_err());
+    }
+
+    tokio::spawn(run_janitor());
 
-    serve_routes((addr, port)).await;
+    serve_routes((settings.bind, settings.port), settings.tls.clone()).await;
 }
@@ -0,0 +1,10 @@
+use lazy_static::lazy_static;
+
+use super::types::*;
+
+lazy_static! {
+    pub static ref OFFLINE_QUEUE: OfflineQueue = OfflineQueue::default();
+    // Kept after users disconnect (until the ttl is over) so messages to them can still be queued
+    pub static ref USER_FINGERPRINTS: UserFingerprints = UserFingerprints::default();
+    pub static ref QUEUE_CONFIG: QueueConfigArc = QueueConfigArc::default();
+}
@@ -0,0 +1,117 @@
+use std::time::Instant;
+
+use anyhow::anyhow;
+use log::trace;
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::{communication::from_offline::FromOfflineMsg, encryption::fingerprint::get_fingerprint, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::utils::tools::send_msg_specific;
+
+use super::{consts::{OFFLINE_QUEUE, QUEUE_CONFIG, USER_FINGERPRINTS}, types::{FingerprintEntry, QueueConfig, QueuedMsg}};
+
+pub async fn get_queue_config() -> QueueConfig {
+    let state = QUEUE_CONFIG.read().await;
+    let config = state.clone();
+
+    drop(state);
+    return config;
+}
+
+pub async fn set_user_fingerprint(user: &Uuid, pubkey: &Rsa<Public>) -> anyhow::Result<Vec<u8>> {
+    let fingerprint = get_fingerprint(pubkey)?;
+
+    let mut state = USER_FINGERPRINTS.write().await;
+    state.insert(user.clone(), FingerprintEntry { fingerprint: fingerprint.clone(), disconnected: None });
+
+    drop(state);
+    return Ok(fingerprint);
+}
+
+pub async fn mark_disconnected(user: &Uuid) {
+    let mut state = USER_FINGERPRINTS.write().await;
+    let entry = state.get_mut(user);
+    if entry.is_some() {
+        entry.unwrap().disconnected = Some(Instant::now());
+    }
+
+    drop(state);
+}
+
+pub async fn get_user_fingerprint(user: &Uuid) -> Option<Vec<u8>> {
+    let ttl = get_queue_config().await.ttl;
+
+    let mut state = USER_FINGERPRINTS.write().await;
+    state.retain(|_, e| e.disconnected.is_none() || e.disconnected.unwrap().elapsed() < ttl);
+
+    let fingerprint = state.get(user).map(|e| e.fingerprint.clone());
+
+    drop(state);
+    return fingerprint;
+}
+
+// Connected user with the given key, if any
+pub async fn get_connected_user(fingerprint: &Vec<u8>) -> Option<Uuid> {
+    let state = USER_FINGERPRINTS.read().await;
+    let user = state.iter()
+        .find(|(_, e)| e.disconnected.is_none() && e.fingerprint == *fingerprint)
+        .map(|(uuid, _)| uuid.clone());
+
+    drop(state);
+    return user;
+}
+
+pub async fn queue_msg(fingerprint: &Vec<u8>, sender_key: &Rsa<Public>, msg: Vec<u8>) -> anyhow::Result<()> {
+    let config = get_queue_config().await;
+
+    let mut state = OFFLINE_QUEUE.write().await;
+    state.retain(|_, queue| {
+        queue.retain(|e| e.queued_at.elapsed() < config.ttl);
+        return !queue.is_empty();
+    });
+
+    let queue = state.entry(fingerprint.clone()).or_default();
+    let size: usize = queue.iter().map(|e| e.msg.len()).sum();
+
+    if size + msg.len() > config.max_size {
+        if queue.is_empty() {
+            state.remove(fingerprint);
+        }
+
+        drop(state);
+        return Err(anyhow!("Offline queue of the receiver is full."));
+    }
+
+    queue.push_back(QueuedMsg { sender_key: sender_key.clone(), msg, queued_at: Instant::now() });
+    trace!("Queued message for {} ({} queued)", hex::encode(fingerprint), queue.len());
+
+    drop(state);
+    return Ok(());
+}
+
+pub async fn deliver_queued(user: &Uuid, fingerprint: &Vec<u8>) -> anyhow::Result<()> {
+    let ttl = get_queue_config().await.ttl;
+
+    let mut state = OFFLINE_QUEUE.write().await;
+    let queue = state.remove(fingerprint);
+
+    drop(state);
+    if queue.is_none() {
+        return Ok(());
+    }
+
+    let queue = queue.unwrap();
+    trace!("Delivering {} queued messages to {}", queue.len(), user);
+
+    for QueuedMsg { sender_key, msg, queued_at } in queue {
+        if queued_at.elapsed() >= ttl {
+            continue;
+        }
+
+        let packet = FromOfflineMsg { msg, sender_key }.serialize();
+        send_msg_specific(user.clone(), Message::binary(packet)).await?;
+    }
+
+    return Ok(());
+}
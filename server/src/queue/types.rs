@@ -0,0 +1,42 @@
+use std::{sync::Arc, collections::{HashMap, VecDeque}, time::{Duration, Instant}};
+
+use openssl::{pkey::Public, rsa::Rsa};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub const DEFAULT_QUEUE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+pub const DEFAULT_QUEUE_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+pub struct QueuedMsg {
+    // Identity of the sender, its uuid is useless once it reconnects
+    pub sender_key: Rsa<Public>,
+    // Sealed to the identity key of the recipient, the server can not read it
+    pub msg: Vec<u8>,
+    pub queued_at: Instant,
+}
+
+pub struct FingerprintEntry {
+    pub fingerprint: Vec<u8>,
+    pub disconnected: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    pub ttl: Duration,
+    // Max bytes queued per recipient
+    pub max_size: usize,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        return QueueConfig {
+            ttl: Duration::from_secs(DEFAULT_QUEUE_TTL_SECS),
+            max_size: DEFAULT_QUEUE_MAX_SIZE,
+        };
+    }
+}
+
+// Key is the fingerprint of the recipient's public key, as uuids change on every connect
+pub type OfflineQueue = Arc<RwLock<HashMap<Vec<u8>, VecDeque<QueuedMsg>>>>;
+pub type UserFingerprints = Arc<RwLock<HashMap<Uuid, FingerprintEntry>>>;
+pub type QueueConfigArc = Arc<RwLock<QueueConfig>>;
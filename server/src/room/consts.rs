@@ -0,0 +1,7 @@
+use lazy_static::lazy_static;
+
+use super::types::*;
+
+lazy_static! {
+    pub static ref ROOMS: Rooms = Rooms::default();
+}
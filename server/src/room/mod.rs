@@ -0,0 +1,3 @@
+pub mod consts;
+pub mod types;
+pub mod tools;
\ No newline at end of file
@@ -0,0 +1,83 @@
+use anyhow::anyhow;
+use log::trace;
+use packets::{room::roster::RoomRosterMsg, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::utils::tools::send_msg_specific;
+
+use super::{consts::ROOMS, types::Room};
+
+pub async fn get_room(name: &str) -> anyhow::Result<Room> {
+    let state = ROOMS.read().await;
+    let room = state.get(name).cloned();
+
+    drop(state);
+    if room.is_none() {
+        return Err(anyhow!("Room '{}' does not exist.", name));
+    }
+
+    return Ok(room.unwrap());
+}
+
+pub async fn is_member(name: &str, user: &Uuid) -> bool {
+    let room = get_room(name).await;
+    return room.is_ok() && room.unwrap().members.contains(user);
+}
+
+pub async fn broadcast_roster(name: &str, room: &Room) {
+    let packet = RoomRosterMsg {
+        room: name.to_string(),
+        epoch: room.epoch,
+        members: room.members.clone(),
+    }.serialize();
+
+    for member in &room.members {
+        let res = send_msg_specific(member.clone(), Message::binary(packet.clone())).await;
+        if res.is_err() {
+            trace!("Could not send roster of '{}' to {}: {}", name, member, res.unwrap_err());
+        }
+    }
+}
+
+// Removes the user from the room and returns the room if it still has members
+pub async fn leave_room(name: &str, user: &Uuid) -> anyhow::Result<Option<Room>> {
+    let mut state = ROOMS.write().await;
+    let room = state.get_mut(name);
+    if room.is_none() || !room.as_ref().unwrap().members.contains(user) {
+        drop(state);
+        return Err(anyhow!("You are not a member of room '{}'.", name));
+    }
+
+    let room = room.unwrap();
+    room.members.retain(|e| e != user);
+    room.epoch += 1;
+
+    let room = room.clone();
+    if room.members.is_empty() {
+        trace!("Room '{}' is empty, removing it", name);
+        state.remove(name);
+
+        drop(state);
+        return Ok(None);
+    }
+
+    drop(state);
+    return Ok(Some(room));
+}
+
+pub async fn leave_all_rooms(user: &Uuid) {
+    let state = ROOMS.read().await;
+    let names: Vec<String> = state.iter()
+        .filter(|(_, room)| room.members.contains(user))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    drop(state);
+    for name in names {
+        let room = leave_room(&name, user).await;
+        if room.is_ok() && room.as_ref().unwrap().is_some() {
+            broadcast_roster(&name, &room.unwrap().unwrap()).await;
+        }
+    }
+}
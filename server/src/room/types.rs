@@ -0,0 +1,14 @@
+use std::{sync::Arc, collections::HashMap};
+
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct Room {
+    // Ordered by join time
+    pub members: Vec<Uuid>,
+    // Incremented on every change of members, clients re-key on every epoch
+    pub epoch: u64,
+}
+
+pub type Rooms = Arc<RwLock<HashMap<String, Room>>>;
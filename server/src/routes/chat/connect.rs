@@ -5,7 +5,7 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 use uuid::Uuid;
 use warp::ws::WebSocket;
 
-use crate::{utils::types::{UserInfo}, routes::chat::{disconnect::user_disconnected, messages::index::user_message}, file::consts::{USERS_LIST, USERS}};
+use crate::{utils::types::{UserInfo}, routes::chat::{disconnect::user_disconnected, hello::on_hello, messages::index::user_message}, file::consts::{USERS_LIST, USERS}};
 
 
 pub async fn user_connected(ws: WebSocket) {
@@ -37,6 +37,12 @@ pub async fn user_connected(ws: WebSocket) {
         }
     });
 
+    let hello = on_hello(&user_id, user_ws_rx.next().await, &tx).await;
+    if hello.is_err() {
+        user_disconnected(user_id).await;
+        return;
+    }
+
     // Save the sender in our list of connected users.
     USERS.write().await.insert(
         user_id,
@@ -1,12 +1,43 @@
+use packets::{communication::left::UserLeftMsg, types::ByteMessage};
 use uuid::Uuid;
+use warp::ws::Message;
 
-use crate::file::consts::{USERS_LIST, USERS};
+use crate::{file::{consts::{USERS_LIST, USERS, PENDING_UPLOADS}, rate::remove_rate_limit, controller::tools::drop_receiver}, utils::tools::send_msg_specific, queue::tools::mark_disconnected, room::tools::leave_all_rooms};
 
 pub async fn user_disconnected(my_id: Uuid) {
     eprintln!("good bye user: {}", my_id);
 
     // Stream closed up, so remove from the user list
     USERS.write().await.remove(&my_id);
+    mark_disconnected(&my_id).await;
+
+    // Clients queue messages for this user from now on
+    let left = UserLeftMsg { user: my_id }.serialize();
+    let users: Vec<Uuid> = USERS.read().await.keys().cloned().collect();
+    for user in users {
+        let _ = send_msg_specific(user, Message::binary(left.clone())).await;
+    }
+
+    leave_all_rooms(&my_id).await;
+    remove_rate_limit(&my_id).await;
+
+    // Transfers which have not been accepted yet can not be resumed, running ones are kept in the journal
+    let mut state = PENDING_UPLOADS.write().await;
+    state.retain(|_, e| e.file.sender != my_id);
+
+    let asked: Vec<Uuid> = state.iter()
+        .filter(|(_, e)| e.get_position(&my_id).is_some())
+        .map(|(k, _)| k.clone())
+        .collect();
+
+    drop(state);
+    for uuid in asked {
+        let res = drop_receiver(&uuid, &my_id).await;
+        if res.is_err() {
+            eprintln!("Could not remove {} from transfer {}: {}", my_id, uuid, res.unwrap_err());
+        }
+    }
+
     let mut e = USERS_LIST.write().await;
     let mut i = 0;
     for el in e.clone().iter() {
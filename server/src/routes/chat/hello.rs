@@ -0,0 +1,49 @@
+use anyhow::anyhow;
+use log::debug;
+use packets::{communication::error::{ErrorCode, ErrorMsg}, initialize::hello::HelloAckMsg, packet::index::Packet, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{utils::{tools::send_msg, types::TXChannel}, file::chunks::get_chunk_bounds};
+
+// The first packet of every client has to be a hello. Incompatible clients get an error and are disconnected.
+pub async fn on_hello(user_id: &Uuid, first: Option<Result<Message, warp::Error>>, tx: &TXChannel) -> anyhow::Result<()> {
+    let ack = negotiate(first);
+    if ack.is_err() {
+        let err = ack.unwrap_err();
+        eprintln!("Incompatible client (uid={}): {}", user_id, err);
+
+        let packet = ErrorMsg { code: ErrorCode::Generic, error: err.to_string() }.serialize();
+        send_msg(tx, Message::binary(packet))?;
+        send_msg(tx, Message::close())?;
+
+        return Err(err);
+    }
+
+    let mut ack = ack.unwrap();
+    let bounds = get_chunk_bounds().await;
+
+    ack.min_chunk_size = bounds.min;
+    ack.max_chunk_size = bounds.max;
+    debug!("Negotiated protocol version {} with {} (features {:#b}, {:?})", ack.version, user_id, ack.features, ack.cipher_suite);
+
+    send_msg(tx, Message::binary(ack.serialize()))?;
+    return Ok(());
+}
+
+fn negotiate(first: Option<Result<Message, warp::Error>>) -> anyhow::Result<HelloAckMsg> {
+    if first.is_none() {
+        return Err(anyhow!("Connection closed before hello."));
+    }
+
+    let first = first.unwrap()?;
+    let packet = Packet::decode(first.as_bytes());
+    if packet.is_err() {
+        return Err(anyhow!("Could not read hello packet ({}). Your client is probably outdated, please update it.", packet.err().unwrap()));
+    }
+
+    match packet.unwrap() {
+        Packet::Hello(hello) => hello.negotiate(),
+        _ => Err(anyhow!("Expected hello packet. Your client is probably outdated, please update it.")),
+    }
+}
@@ -1,55 +1,48 @@
-use std::cmp::Ordering;
-
 use anyhow::anyhow;
 use log::trace;
 use packets::{file::processing::abort::ChunkAbortMsg, types::ByteMessage};
-use tokio::fs::{read_dir, remove_file};
 use uuid::Uuid;
 use warp::ws::Message;
 
-use crate::{file::{tools::{get_uploading_file, get_pending_file}, consts::{CHUNK_DIR, PENDING_UPLOADS, UPLOADING_FILES}}, utils::tools::send_msg_specific};
+use crate::{file::{tools::{get_uploading_controller, get_pending_controller}, journal::{get_journal, remove_transfer}, controller::tools::drop_position}, queue::tools::get_user_fingerprint, utils::tools::send_msg_specific};
 
-pub async fn on_chunk_abort(data: &Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
-    let msg = ChunkAbortMsg::deserialize(data)?;
+pub async fn on_chunk_abort(msg: ChunkAbortMsg, my_id: &Uuid) -> anyhow::Result<()> {
     trace!("ChunkAbort: {:?}", msg);
 
-    let file = get_pending_file(&msg.uuid).await.or(get_uploading_file(&msg.uuid).await)?;
-    if my_id.cmp(&file.receiver) != Ordering::Equal && my_id.cmp(&file.sender) != Ordering::Equal {
+    let controller = get_pending_controller(&msg.uuid).await.or(get_uploading_controller(&msg.uuid).await)?;
+    let file = &controller.file;
+
+    // Connection ids of interrupted transfers are outdated, so check the key as well
+    let journal = get_journal(&msg.uuid).await;
+    let fingerprint = get_user_fingerprint(my_id).await;
+    let journal = journal.as_ref().zip(fingerprint.as_ref());
+    let is_sender = journal.map(|(j, f)| j.sender == *f).unwrap_or(false);
+    let journal_position = journal.and_then(|(j, f)| j.get_receiver(f)).map(|e| e.position);
+
+    let position = controller.get_position(my_id).or(journal_position);
+    let is_receiver = position.is_some();
+    if !is_sender && !is_receiver && *my_id != file.sender {
         trace!("Cannot abort upload task if client is not the sender or receiver of it.");
         return Err(anyhow!("Invalid receiver / sender"));
     }
 
-    let chunk_dir = CHUNK_DIR.as_path();
-    let mut files = read_dir(chunk_dir).await?;
-
     let b_msg = msg.serialize();
-    send_msg_specific(file.receiver, Message::binary(b_msg.clone())).await?;
-    send_msg_specific(file.sender, Message::binary(b_msg.clone())).await?;
-
-    while let Ok(file) = files.next_entry().await {
-        if file.is_none() {
-            continue;
-        }
-
-        let file = file.unwrap();
-        let name = file.file_name();
-        let name = name.to_str();
-        if name.is_none() {
-            continue;
-        }
-
-        let name = name.unwrap().to_string();
-        let is_chunk_file = name.starts_with(&msg.uuid.to_string());
-
-        if is_chunk_file {
-            trace!("Aborting. Removing file {} in chunks.", name);
-            remove_file(file.path()).await?;
-        }
+
+    // A receiver leaving only stops the transfer if it was the last one
+    if is_receiver && controller.receivers.len() > 1 {
+        let _ = send_msg_specific(my_id.clone(), Message::binary(b_msg)).await;
+        return drop_position(&msg.uuid, position.unwrap()).await;
+    }
+
+    // The other side may be offline while the transfer is waiting to be resumed
+    for receiver in &controller.receivers {
+        let _ = send_msg_specific(receiver.id, Message::binary(b_msg.clone())).await;
     }
 
+    let _ = send_msg_specific(file.sender, Message::binary(b_msg.clone())).await;
+
     trace!("Removing pending uploads and files...");
-    PENDING_UPLOADS.write().await.remove(&msg.uuid);
-    UPLOADING_FILES.write().await.remove(&msg.uuid);
+    remove_transfer(&msg.uuid).await?;
 
     return Ok(())
 }
@@ -1,23 +1,47 @@
-use std::cmp::Ordering;
-
 use log::trace;
-use packets::{file::processing::downloaded::ChunkDownloadedMsg, types::ByteMessage};
+use packets::{file::processing::{downloaded::ChunkDownloadedMsg, tools::get_max_chunks}};
 use uuid::Uuid;
-use warp::ws::Message;
 
-use crate::{file::tools::get_uploading_file, utils::tools::send_msg_specific};
+use crate::file::{consts::UPLOADING_FILES, controller::tools::notify_finished, journal::update_journal};
 
-pub async fn on_chunk_downloaded(data: &Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
-    let msg = ChunkDownloadedMsg::deserialize(data)?;
+pub async fn on_chunk_downloaded(msg: ChunkDownloadedMsg, my_id: &Uuid) -> anyhow::Result<()> {
     trace!("ChunkDownloaded: {:?}", msg);
 
-    let file = get_uploading_file(&msg.uuid).await?;
+    let mut state = UPLOADING_FILES.write().await;
+    let controller = state.get_mut(&msg.uuid);
+    let pos = controller.as_ref().and_then(|e| e.get_accepted_position(my_id));
 
-    if file.receiver.cmp(my_id) != Ordering::Equal {
-        eprintln!("Could not process chunk download msg, receiver in not equal to current id");
+    if pos.is_none() {
+        drop(state);
+        eprintln!("Could not process chunk download msg, current id is not a receiver of {}", msg.uuid);
         return Ok(());
     }
 
-    send_msg_specific(file.sender, Message::binary(msg.serialize())).await?;
-    return Ok(())
+    let controller = controller.unwrap();
+    controller.touch();
+
+    if msg.chunk_index < get_max_chunks(controller.file.size, controller.file.chunk_size) {
+        controller.receivers[pos.unwrap()].downloaded.insert(msg.chunk_index);
+    }
+
+    // The sender is only told once every receiver has the chunk
+    let sender = controller.file.sender;
+    let finished = controller.take_finished();
+    let is_done = controller.is_done();
+    let pos = pos.unwrap();
+
+    drop(state);
+    let res = update_journal(&msg.uuid, |j| {
+        let is_valid = j.is_valid_chunk(msg.chunk_index);
+        let entry = j.get_receiver_mut(pos);
+        if is_valid && entry.is_some() {
+            entry.unwrap().downloaded.insert(msg.chunk_index);
+        }
+    }).await;
+
+    if res.is_err() {
+        trace!("Could not journal downloaded chunk of {}: {}", msg.uuid, res.unwrap_err());
+    }
+
+    return notify_finished(&msg.uuid, &sender, finished, is_done).await;
 }
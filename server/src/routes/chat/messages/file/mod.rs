@@ -1,2 +1,3 @@
 pub mod downloaded;
-pub mod abort;
\ No newline at end of file
+pub mod abort;
+pub mod resume;
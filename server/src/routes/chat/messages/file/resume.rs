@@ -0,0 +1,100 @@
+use log::trace;
+use packets::{file::{journal::TransferJournal, processing::resume::FileResumeMsg}, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{
+    file::{consts::UPLOADING_FILES, controller::index::Controller, journal::{get_journal, update_journal}},
+    queue::tools::{get_connected_user, get_user_fingerprint},
+    utils::tools::{send_error, send_msg_specific},
+};
+
+pub async fn on_file_resume(msg: FileResumeMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let uuid = msg.uuid;
+    let journal = get_journal(&uuid).await;
+    if journal.is_none() {
+        send_error(my_id.clone(), &format!("Transfer {} can not be resumed, the server does not know it.", uuid)).await?;
+        return Ok(());
+    }
+
+    let journal = journal.unwrap();
+    let fingerprint = get_user_fingerprint(my_id).await;
+
+    let is_sender = fingerprint.as_ref() == Some(&journal.sender);
+    let position = fingerprint.as_ref().and_then(|e| journal.get_receiver(e)).map(|e| e.position);
+    if !is_sender && position.is_none() {
+        send_error(my_id.clone(), &format!("You are not part of transfer {}.", uuid)).await?;
+        return Ok(());
+    }
+
+    // Only the receiver knows which chunks made it into its file
+    let journal = if position.is_some() {
+        update_journal(&uuid, |j| {
+            let done = msg.done.iter().filter(|e| j.is_valid_chunk(**e)).cloned().collect();
+            j.get_receiver_mut(position.unwrap()).unwrap().downloaded = done;
+        }).await?
+    } else {
+        journal
+    };
+
+    let sender = if is_sender { Some(my_id.clone()) } else { get_connected_user(&journal.sender).await };
+    let sender = sender.unwrap_or(Uuid::nil());
+
+    let mut receivers = Vec::new();
+    for receiver in &journal.receivers {
+        let id = if position == Some(receiver.position) { Some(my_id.clone()) } else { get_connected_user(&receiver.fingerprint).await };
+        if id.is_some() {
+            receivers.push((receiver.position, id.unwrap()));
+        }
+    }
+
+    trace!("Resuming transfer {} (sender {}, receivers {:?})", uuid, sender, receivers);
+    let mut state = UPLOADING_FILES.write().await;
+    state.insert(uuid, Controller::from_journal(&journal, &sender, &receivers));
+
+    drop(state);
+
+    // The sender uploads the chunks one of the connected receivers is missing for all of them
+    if !sender.is_nil() {
+        let connected: Vec<usize> = receivers.iter().map(|e| e.0).collect();
+        let done = (0..journal.get_max_chunks())
+            .filter(|e| connected.iter().all(|p| is_transferred(&journal, *p, *e)))
+            .collect();
+
+        let packet = FileResumeMsg {
+            uuid,
+            sender,
+            receivers: receivers.iter().map(|e| e.1).collect(),
+            done,
+            ready: Vec::new(),
+        }.serialize();
+
+        send_msg_specific(sender, Message::binary(packet)).await?;
+    }
+
+    // A receiver resuming does not interrupt the downloads of the others
+    for (pos, receiver) in receivers {
+        if !is_sender && position != Some(pos) {
+            continue;
+        }
+
+        let entry = journal.receivers.iter().find(|e| e.position == pos).unwrap();
+        let packet = FileResumeMsg {
+            uuid,
+            sender,
+            receivers: vec![receiver],
+            done: entry.downloaded.iter().cloned().collect(),
+            ready: entry.uploaded.difference(&entry.downloaded).cloned().collect(),
+        }.serialize();
+
+        send_msg_specific(receiver, Message::binary(packet)).await?;
+    }
+
+    return Ok(());
+}
+
+// True if the receiver at `position` has chunk `chunk_index` or it is stored on the server for it
+fn is_transferred(journal: &TransferJournal, position: usize, chunk_index: u64) -> bool {
+    let receiver = journal.receivers.iter().find(|e| e.position == position);
+    return receiver.map(|e| e.uploaded.contains(&chunk_index) || e.downloaded.contains(&chunk_index)).unwrap_or(false);
+}
@@ -1,64 +1,49 @@
 use anyhow::anyhow;
-use packets::util::modes::Modes;
+use packets::packet::index::Packet;
 use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 use warp::ws::Message;
 
-use crate::utils::vec::{vec_to_decque, decque_to_vec};
-
-use super::{name::on_name, pubkey::on_pubkey, to::on_to, uid::on_uid, question::{reply::on_file_question_reply, question::on_file_question}, file::{downloaded::on_chunk_downloaded, abort::on_chunk_abort}, want_symm::on_want_symm_key, symm_key::on_symm_key};
+use super::{name::on_name, pubkey::on_pubkey, to::on_to, to_offline::on_to_offline, uid::on_uid, question::{reply::on_file_question_reply, question::on_file_question, manifest::on_file_manifest}, file::{downloaded::on_chunk_downloaded, abort::on_chunk_abort, resume::on_file_resume}, want_symm::on_want_symm_key, symm_key::on_symm_key, room::{action::on_room_action, list::on_room_list, key::on_room_key, msg::on_room_msg}};
 
 pub async fn user_message(my_id: Uuid, msg: Message, tx: &UnboundedSender<Message>) -> anyhow::Result<()> {
-    let msg = msg.into_bytes();
-    let mut msg = vec_to_decque(msg);
-    let mode = msg.pop_front();
-
-    if mode.is_none() {
-        eprintln!("Invalid mode.  (is none)");
-        return Err(anyhow!("Invalid mode."));
-    }
-
-    let mode = mode.unwrap();
-    let msg = decque_to_vec(msg);
-
-    if Modes::WantUid.is_indicator(&mode) {
-        return on_uid(&my_id, tx);
-    }
-
-    if Modes::To.is_indicator(&mode) {
-        return on_to(msg, &my_id).await;
-    }
-
-    if Modes::SetPubkey.is_indicator(&mode) {
-        return on_pubkey(&msg, &my_id).await;
-    }
-
-    if Modes::Name.is_indicator(&mode) {
-        return on_name(&msg, &my_id).await;
-    }
-
-    if Modes::SendFileQuestion.is_indicator(&mode) {
-        return on_file_question(&msg).await;
-    }
-
-    if Modes::SendFileQuestionReply.is_indicator(&mode) {
-        return on_file_question_reply(&msg).await;
-    }
-    if Modes::SendFileChunkDownloaded.is_indicator(&mode) {
-        return on_chunk_downloaded(&msg, &my_id).await;
-    }
-
-    if Modes::SendFileAbort.is_indicator(&mode) {
-        return on_chunk_abort(&msg, &my_id).await;
-    }
-
-    if Modes::WantSymmKey.is_indicator(&mode) {
-        return on_want_symm_key(msg, &my_id).await;
-    }
-
-    if Modes::SymmKey.is_indicator(&mode) {
-        return on_symm_key(msg, &my_id).await;
-    }
-
-    Err(anyhow!("Invalid packet mode."))
-}
\ No newline at end of file
+    let packet = Packet::decode(msg.as_bytes());
+    if packet.is_err() {
+        let err = packet.err().unwrap();
+
+        eprintln!("Invalid packet: {}", err);
+        return Err(err.into());
+    }
+
+    match packet.unwrap() {
+        Packet::WantUid => on_uid(&my_id, tx),
+        Packet::To(msg) => on_to(msg, &my_id).await,
+        Packet::ToOffline(msg) => on_to_offline(msg, &my_id).await,
+        Packet::SetPubkey(msg) => on_pubkey(msg, &my_id).await,
+        Packet::Name(msg) => on_name(msg, &my_id).await,
+        Packet::SendFileQuestion(msg) => on_file_question(msg, &my_id).await,
+        Packet::SendFileQuestionReply(msg) => on_file_question_reply(msg, &my_id).await,
+        Packet::SendFileManifest(msg) => on_file_manifest(msg, &my_id).await,
+        Packet::SendFileChunkDownloaded(msg) => on_chunk_downloaded(msg, &my_id).await,
+        Packet::SendFileAbort(msg) => on_chunk_abort(msg, &my_id).await,
+        Packet::SendFileResume(msg) => on_file_resume(msg, &my_id).await,
+        Packet::WantSymmKey(msg) => on_want_symm_key(msg, &my_id).await,
+        Packet::SymmKey(msg) => on_symm_key(msg, &my_id).await,
+        Packet::RoomAction(msg) => on_room_action(msg, &my_id).await,
+        Packet::RoomList(_) => on_room_list(&my_id).await,
+        Packet::RoomKey(msg) => on_room_key(msg, &my_id).await,
+        Packet::RoomMsg(msg) => on_room_msg(msg, &my_id).await,
+        Packet::Hello(_) => Err(anyhow!("Hello has already been sent.")),
+
+        // Only sent by the server
+        Packet::From(_)
+        | Packet::FromOffline(_)
+        | Packet::UserLeft(_)
+        | Packet::UidReply(_)
+        | Packet::Error(_)
+        | Packet::SendFileChunkReady(_)
+        | Packet::SendFileStartProcessing(_)
+        | Packet::HelloAck(_)
+        | Packet::RoomRoster(_) => Err(anyhow!("Invalid packet mode.")),
+    }
+}
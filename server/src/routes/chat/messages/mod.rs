@@ -1,9 +1,11 @@
 pub mod name;
 pub mod pubkey;
 pub mod to;
+pub mod to_offline;
 pub mod uid;
 pub mod index;
 pub mod question;
 pub mod file;
 pub mod want_symm;
-pub mod symm_key;
\ No newline at end of file
+pub mod symm_key;
+pub mod room;
@@ -1,11 +1,11 @@
 use log::debug;
-use packets::{initialize::name::NameMsg, types::ByteMessage};
+use packets::initialize::name::NameMsg;
 use uuid::Uuid;
 
-use crate::file::consts::USERS;
+use crate::{file::consts::USERS, storage::tools::save_identity};
 
-pub async fn on_name(data: &Vec<u8>, curr_id: &Uuid) -> anyhow::Result<()>{
-    let NameMsg { name } = NameMsg::deserialize(data)?;
+pub async fn on_name(msg: NameMsg, curr_id: &Uuid) -> anyhow::Result<()>{
+    let NameMsg { name } = msg;
 
     let mut state = USERS.write().await;
     let info = state.get_mut(&curr_id);
@@ -17,5 +17,7 @@ pub async fn on_name(data: &Vec<u8>, curr_id: &Uuid) -> anyhow::Result<()>{
 
     drop(state);
     debug!("Name set. ({name})");
+
+    save_identity(curr_id).await?;
     Ok(())
 }
@@ -1,10 +1,10 @@
-use packets::{initialize::pubkey::PubkeyMsg, types::ByteMessage};
+use packets::initialize::pubkey::PubkeyMsg;
 use uuid::Uuid;
 
-use crate::{file::consts::USERS};
+use crate::{file::consts::USERS, queue::tools::{deliver_queued, set_user_fingerprint}, storage::tools::save_identity};
 
-pub async fn on_pubkey(data: &Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
-    let PubkeyMsg { pubkey } = PubkeyMsg::deserialize(data)?;
+pub async fn on_pubkey(msg: PubkeyMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let PubkeyMsg { pubkey } = msg;
 
     let mut state = USERS.write().await;
     let info = state.get_mut(&my_id);
@@ -15,5 +15,10 @@ pub async fn on_pubkey(data: &Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
     }
 
     drop(state);
+
+    let fingerprint = set_user_fingerprint(my_id, &pubkey).await?;
+    save_identity(my_id).await?;
+
+    deliver_queued(my_id, &fingerprint).await?;
     Ok(())
 }
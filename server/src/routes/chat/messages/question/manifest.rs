@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use log::trace;
+use packets::{file::{manifest::index::FileManifestMsg, types::FileInfo}, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{utils::tools::{send_error, send_error_code, send_msg_specific}, file::{consts::PENDING_UPLOADS, chunks::is_allowed_chunk_size, quota::check_quota, controller::index::Controller}};
+
+pub async fn on_file_manifest(msg: FileManifestMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    if msg.sender != *my_id {
+        send_error(my_id.clone(), "Invalid sender of manifest.").await?;
+        return Ok(());
+    }
+
+    if msg.entries.is_empty() {
+        send_error(my_id.clone(), "Manifest does not contain any files.").await?;
+        return Ok(());
+    }
+
+    if !is_allowed_chunk_size(msg.chunk_size).await {
+        send_error(my_id.clone(), "Chunk size is not allowed by the server.").await?;
+        return Ok(());
+    }
+
+    let invalid = msg.entries.iter().find(|e| !e.is_valid_path());
+    if invalid.is_some() {
+        trace!("Invalid path in manifest {}: {:?}", msg.uuid, invalid.unwrap().path);
+        send_error(my_id.clone(), &format!("Invalid path in manifest: {}", invalid.unwrap().path)).await?;
+        return Ok(());
+    }
+
+    let uuids: HashSet<Uuid> = msg.entries.iter().map(|e| e.uuid).collect();
+    let mut state = PENDING_UPLOADS.write().await;
+    let has_key = uuids.len() != msg.entries.len() || uuids.iter().any(|e| state.contains_key(e));
+
+    if has_key {
+        drop(state);
+        trace!("Duplicate uuid in manifest.");
+
+        send_error(my_id.clone(), "Invalid uuid of file in manifest. The same uuid is already stored.").await?;
+        return Ok(());
+    }
+
+    let sizes = msg.entries.iter().map(|e| e.size).collect();
+    let rejected = check_quota(&state, my_id, &sizes).await;
+    if rejected.is_some() {
+        drop(state);
+        let (code, error) = rejected.unwrap();
+        trace!("Rejecting manifest {}: {}", msg.uuid, error);
+
+        send_error_code(my_id.clone(), code, &error).await?;
+        return Ok(());
+    }
+
+    // Every entry is answered like a single file question
+    for entry in &msg.entries {
+        let info = FileInfo {
+            filename: entry.path.clone(),
+            receiver: msg.receiver.clone(),
+            sender: my_id.clone(),
+            size: entry.size,
+            chunk_size: msg.chunk_size,
+            compression: entry.compression,
+            path: None,
+            hash: entry.hash.clone(),
+            chunk_hashes: entry.chunk_hashes.clone()
+        };
+
+        state.insert(entry.uuid, Controller::new(info, &vec![msg.receiver]));
+    }
+
+    drop(state);
+    trace!("Stored manifest {} with {} files", msg.uuid, msg.entries.len());
+
+    let to_send = msg.serialize();
+    let res = send_msg_specific(msg.receiver, Message::binary(to_send)).await;
+    if res.is_err() {
+        let mut state = PENDING_UPLOADS.write().await;
+        state.retain(|k, _| !uuids.contains(k));
+
+        drop(state);
+        return res;
+    }
+
+    return Ok(());
+}
@@ -1,2 +1,3 @@
 pub mod question;
-pub mod reply;
\ No newline at end of file
+pub mod reply;
+pub mod manifest;
\ No newline at end of file
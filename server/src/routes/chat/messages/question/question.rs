@@ -1,15 +1,20 @@
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
 use log::trace;
-use packets::{file::{question::{index::FileQuestionMsg}, types::FileInfo}, types::ByteMessage, communication::error::ErrorMsg};
+use packets::{file::{question::{index::FileQuestionMsg}, types::FileInfo}, types::ByteMessage, communication::error::{ErrorCode, ErrorMsg}};
+use uuid::Uuid;
 use warp::ws::Message;
 
-use crate::{utils::tools::send_msg_specific, file::consts::PENDING_UPLOADS};
+use crate::{utils::tools::{send_error, send_error_code, send_msg_specific}, file::{consts::PENDING_UPLOADS, chunks::is_allowed_chunk_size, quota::check_quota, controller::{index::Controller, tools::drop_receiver}}};
 
 pub async fn on_file_question(
-    data: &Vec<u8>
+    msg: FileQuestionMsg,
+    my_id: &Uuid
 ) -> anyhow::Result<()> {
-    let msg = FileQuestionMsg::deserialize(&data)?;
+    if msg.sender != *my_id {
+        send_error(my_id.clone(), "Invalid sender of file.").await?;
+        return Ok(());
+    }
 
     let filename = &msg.filename;
     let sender = msg.sender;
@@ -21,6 +26,7 @@ pub async fn on_file_question(
         trace!("Invalid filename given ({:?})", filename);
 
         let err = ErrorMsg {
+            code: ErrorCode::Generic,
             error: "Invalid filename".to_string()
         }.serialize();
 
@@ -33,6 +39,7 @@ pub async fn on_file_question(
         trace!("Invalid OSString given ({:?})", filename);
 
         let err = ErrorMsg {
+            code: ErrorCode::Generic,
             error: "Invalid OSString".to_string()
         }.serialize();
 
@@ -40,35 +47,75 @@ pub async fn on_file_question(
         return Ok(());
     }
 
-    let state = PENDING_UPLOADS.read().await;
-    let has_key = state.get(&msg.uuid).is_some();
-
-    drop(state);
-    if has_key {
-        trace!("Duplicate uuid of file.");
-        let err = ErrorMsg { error: "Invalid uuid of file. The same uuid is already stored.".to_string() }.serialize();
+    if !is_allowed_chunk_size(msg.chunk_size).await {
+        trace!("Chunk size {} is out of bounds", msg.chunk_size);
+        send_error(sender, "Chunk size is not allowed by the server.").await?;
+        return Ok(());
+    }
 
-        send_msg_specific(sender, Message::binary(err)).await?;
+    let receivers: HashSet<&Uuid> = msg.receivers.iter().collect();
+    if receivers.len() != msg.receivers.len() || receivers.contains(&sender) {
+        trace!("Invalid receivers {:?}", msg.receivers);
+        send_error(sender, "Invalid receivers of file.").await?;
         return Ok(());
     }
 
     let info = FileInfo {
         filename: msg.filename.clone(),
-        receiver: msg.receiver.clone(),
+        receiver: msg.receivers[0].clone(),
         sender,
         size: msg.size,
+        chunk_size: msg.chunk_size,
+        compression: msg.compression,
         path: None,
-        hash: msg.hash.clone()
+        hash: msg.hash.clone(),
+        chunk_hashes: msg.chunk_hashes.clone()
     };
 
-    trace!("Storing file info {:#?}", info);
-
+    // The sender asks every receiver with its own question, all of them use the same uuid
     let mut state = PENDING_UPLOADS.write().await;
-    state.insert(msg.uuid, info);
+    let existing = state.get(&msg.uuid);
+    let is_duplicate = existing.is_some() && {
+        let existing = existing.unwrap();
+        let ids: Vec<Uuid> = existing.receivers.iter().map(|e| e.id).collect();
+
+        existing.file.sender != sender || ids != msg.receivers || existing.file.hash != msg.hash
+    };
+
+    if is_duplicate {
+        drop(state);
+        trace!("Duplicate uuid of file.");
+        let err = ErrorMsg { code: ErrorCode::Generic, error: "Invalid uuid of file. The same uuid is already stored.".to_string() }.serialize();
+
+        send_msg_specific(sender, Message::binary(err)).await?;
+        return Ok(());
+    }
+
+    // Later questions of the same offer (one per receiver) have been checked already
+    if existing.is_none() {
+        let rejected = check_quota(&state, &sender, &vec![msg.size]).await;
+        if rejected.is_some() {
+            drop(state);
+            let (code, error) = rejected.unwrap();
+            trace!("Rejecting file {}: {}", msg.uuid, error);
+
+            send_error_code(sender, code, &error).await?;
+            return Ok(());
+        }
+
+        trace!("Storing file info {:#?}", info);
+        state.insert(msg.uuid, Controller::new(info, &msg.receivers));
+    }
 
     drop(state);
 
     let to_send = msg.serialize();
-    send_msg_specific(msg.receiver, Message::binary(to_send)).await?;
+    let res = send_msg_specific(msg.receiver, Message::binary(to_send)).await;
+    if res.is_err() {
+        // Receivers which are offline can not answer
+        trace!("Could not ask {}: {}", msg.receiver, res.unwrap_err());
+        drop_receiver(&msg.uuid, &msg.receiver).await?;
+    }
+
     Ok(())
 }
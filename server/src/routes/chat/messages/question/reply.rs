@@ -3,48 +3,34 @@ use packets::{
     file::question::reply::FileQuestionReplyMsg,
     types::ByteMessage,
 };
+use uuid::Uuid;
 use warp::ws::Message;
 
 use crate::{
-    file::{consts::{PENDING_UPLOADS, UPLOADING_FILES}, tools::get_pending_file, controller::index::Controller},
-    utils::tools::send_msg_specific,
+    file::{consts::PENDING_UPLOADS, tools::get_pending_controller, controller::{index::ReceiverStatus, tools::try_start}},
+    utils::tools::{send_error, send_msg_specific},
 };
 
-pub async fn on_file_question_reply(data: &Vec<u8>) -> anyhow::Result<()> {
-    let msg = FileQuestionReplyMsg::deserialize(&data)?;
-
-
+pub async fn on_file_question_reply(msg: FileQuestionReplyMsg, my_id: &Uuid) -> anyhow::Result<()> {
     trace!("Getting pending file for file question reply");
-    let file = get_pending_file(&msg.uuid).await?;
+    let controller = get_pending_controller(&msg.uuid).await?;
+    if controller.get_position(my_id).is_none() {
+        send_error(my_id.clone(), "You have not been asked to receive this file.").await?;
+        return Ok(());
+    }
 
     let to_send = msg.serialize();
-    send_msg_specific(file.receiver, Message::binary(to_send)).await?;
-
-    if !msg.accepted {
-        trace!("Deleted rejected file with id {}", msg.uuid);
-        let mut state = PENDING_UPLOADS.write().await;
-        state.remove(&msg.uuid);
-
-        drop(state);
-    } else {
-        let uuid = msg.uuid;
-        trace!("Getting pending file for file question reply else");
-        let file = get_pending_file(&uuid).await?;
+    send_msg_specific(my_id.clone(), Message::binary(to_send)).await?;
 
-        trace!("Removing pending upload from {}", uuid);
-        let mut state = PENDING_UPLOADS.write().await;
-        state.remove(&uuid);
+    let status = if msg.accepted { ReceiverStatus::Accepted } else { ReceiverStatus::Rejected };
+    trace!("Receiver {} answered {} with {:?}", my_id, msg.uuid, status);
 
-        drop(state);
-
-        trace!("Adding controller to uploading files {}", uuid);
-        // TODO maybe useless?
-        Controller::new(&uuid, file.clone()).await?;
-
-        let mut state = UPLOADING_FILES.write().await;
-        state.insert(uuid, file);
-
-        drop(state);
+    let mut state = PENDING_UPLOADS.write().await;
+    let controller = state.get_mut(&msg.uuid);
+    if controller.is_some() {
+        controller.unwrap().set_status(my_id, status);
     }
-    Ok(())
+
+    drop(state);
+    return try_start(&msg.uuid).await;
 }
@@ -0,0 +1,71 @@
+use log::trace;
+use packets::room::{action::{RoomAction, RoomActionMsg}, tools::is_valid_room_name};
+use uuid::Uuid;
+
+use crate::{room::{consts::ROOMS, tools::{broadcast_roster, leave_room}, types::Room}, utils::tools::send_error};
+
+pub async fn on_room_action(msg: RoomActionMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let RoomActionMsg { action, room: name } = msg;
+    if !is_valid_room_name(&name) {
+        send_error(my_id.clone(), "Invalid room name. Only letters, digits, '-' and '_' are allowed.").await?;
+        return Ok(());
+    }
+
+    if action == RoomAction::Leave {
+        let room = leave_room(&name, my_id).await;
+        if room.is_err() {
+            send_error(my_id.clone(), &room.unwrap_err().to_string()).await?;
+            return Ok(());
+        }
+
+        let room = room.unwrap();
+        if room.is_some() {
+            broadcast_roster(&name, &room.unwrap()).await;
+        }
+
+        trace!("{} left room '{}'", my_id, name);
+        return Ok(());
+    }
+
+    let mut state = ROOMS.write().await;
+    let existing = state.get_mut(&name);
+
+    let room = match action {
+        RoomAction::Create => {
+            if existing.is_some() {
+                drop(state);
+                send_error(my_id.clone(), &format!("Room '{}' already exists.", name)).await?;
+                return Ok(());
+            }
+
+            let room = Room {
+                members: vec![my_id.clone()],
+                epoch: 0,
+            };
+
+            state.insert(name.clone(), room.clone());
+            room
+        },
+        _ => {
+            if existing.is_none() {
+                drop(state);
+                send_error(my_id.clone(), &format!("Room '{}' does not exist.", name)).await?;
+                return Ok(());
+            }
+
+            let room = existing.unwrap();
+            if !room.members.contains(my_id) {
+                room.members.push(my_id.clone());
+                room.epoch += 1;
+            }
+
+            room.clone()
+        }
+    };
+
+    drop(state);
+
+    trace!("{} joined room '{}' (epoch {})", my_id, name, room.epoch);
+    broadcast_roster(&name, &room).await;
+    return Ok(());
+}
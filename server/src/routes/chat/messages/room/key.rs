@@ -0,0 +1,21 @@
+use packets::{room::key::RoomKeyMsg, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{room::tools::is_member, utils::tools::{send_error, send_msg_specific}};
+
+pub async fn on_room_key(msg: RoomKeyMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    if !is_member(&msg.room, my_id).await || !is_member(&msg.room, &msg.user).await {
+        send_error(my_id.clone(), &format!("Can not send key of room '{}', both users have to be members.", msg.room)).await?;
+        return Ok(());
+    }
+
+    let receiver = msg.user;
+    let packet = RoomKeyMsg {
+        user: my_id.clone(),
+        ..msg
+    }.serialize();
+
+    send_msg_specific(receiver, Message::binary(packet)).await?;
+    return Ok(());
+}
@@ -0,0 +1,23 @@
+use packets::{room::list::{RoomListMsg, RoomSummary}, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{room::consts::ROOMS, utils::tools::send_msg_specific};
+
+pub async fn on_room_list(my_id: &Uuid) -> anyhow::Result<()> {
+    let state = ROOMS.read().await;
+    let mut rooms: Vec<RoomSummary> = state.iter()
+        .map(|(name, room)| RoomSummary {
+            name: name.clone(),
+            members: room.members.len() as u64,
+        })
+        .collect();
+
+    drop(state);
+    rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let packet = RoomListMsg { rooms }.serialize();
+    send_msg_specific(my_id.clone(), Message::binary(packet)).await?;
+
+    return Ok(());
+}
@@ -0,0 +1,4 @@
+pub mod action;
+pub mod list;
+pub mod key;
+pub mod msg;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use log::trace;
+use packets::{room::msg::RoomMsg, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{room::tools::get_room, utils::tools::{send_error, send_msg_specific}};
+
+pub async fn on_room_msg(msg: RoomMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let room = get_room(&msg.room).await;
+    if room.is_err() {
+        send_error(my_id.clone(), &room.unwrap_err().to_string()).await?;
+        return Ok(());
+    }
+
+    let room = room.unwrap();
+    if !room.members.contains(my_id) {
+        send_error(my_id.clone(), &format!("You are not a member of room '{}'.", msg.room)).await?;
+        return Ok(());
+    }
+
+    let packet = RoomMsg {
+        user: my_id.clone(),
+        ..msg
+    }.serialize();
+
+    for member in room.members.iter().filter(|e| *e != my_id) {
+        let res = send_msg_specific(member.clone(), Message::binary(packet.clone())).await;
+        if res.is_err() {
+            trace!("Could not forward room message to {}: {}", member, res.unwrap_err());
+        }
+    }
+
+    return Ok(());
+}
@@ -1,12 +1,21 @@
-use packets::{communication::{to::ToMsg, from::FromMsg}, types::ByteMessage};
+use packets::{communication::{to::ToMsg, from::FromMsg, error::{ErrorCode, ErrorMsg}}, types::ByteMessage};
 use uuid::Uuid;
 use warp::ws::Message;
 
-use crate::utils::tools::send_msg_specific;
+use crate::{utils::tools::send_msg_specific, file::consts::USERS};
 
 
-pub async fn on_to(data: Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
-    let ToMsg { msg, receiver} = ToMsg::deserialize(&data)?;
+pub async fn on_to(msg: ToMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let ToMsg { msg, receiver} = msg;
+
+    // Ratchet messages are bound to the session of the receiver, offline users get sealed ones instead
+    let connected = USERS.read().await.contains_key(&receiver);
+    if !connected {
+        let err = ErrorMsg { code: ErrorCode::Generic, error: "Receiver is not connected, the message was not delivered.".to_string() }.serialize();
+        send_msg_specific(my_id.clone(), Message::binary(err)).await?;
+        return Ok(());
+    }
+
     let packet = FromMsg {
         msg,
         sender: my_id.clone()
@@ -0,0 +1,47 @@
+use log::trace;
+use packets::{communication::{to_offline::ToOfflineMsg, from_offline::FromOfflineMsg, error::{ErrorCode, ErrorMsg}}, types::ByteMessage};
+use uuid::Uuid;
+use warp::ws::Message;
+
+use crate::{utils::tools::send_msg_specific, file::consts::USERS, queue::tools::{get_user_fingerprint, queue_msg}};
+
+pub async fn on_to_offline(msg: ToOfflineMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let ToOfflineMsg { msg, receiver } = msg;
+
+    let state = USERS.read().await;
+    let sender_key = state.get(my_id).and_then(|e| e.public_key.clone());
+    let connected = state.contains_key(&receiver);
+
+    drop(state);
+    if sender_key.is_none() {
+        let err = ErrorMsg { code: ErrorCode::Generic, error: "Your public key has not been set.".to_string() }.serialize();
+        send_msg_specific(my_id.clone(), Message::binary(err)).await?;
+        return Ok(());
+    }
+
+    let sender_key = sender_key.unwrap();
+
+    // The receiver came back in the meantime, it can open the message all the same
+    if connected {
+        let packet = FromOfflineMsg { msg, sender_key }.serialize();
+        send_msg_specific(receiver, Message::binary(packet)).await?;
+        return Ok(());
+    }
+
+    let fingerprint = get_user_fingerprint(&receiver).await;
+    if fingerprint.is_none() {
+        let err = ErrorMsg { code: ErrorCode::Generic, error: "Receiver is not connected.".to_string() }.serialize();
+        send_msg_specific(my_id.clone(), Message::binary(err)).await?;
+        return Ok(());
+    }
+
+    let res = queue_msg(&fingerprint.unwrap(), &sender_key, msg).await;
+    if res.is_err() {
+        let err = ErrorMsg { code: ErrorCode::Generic, error: res.unwrap_err().to_string() }.serialize();
+        send_msg_specific(my_id.clone(), Message::binary(err)).await?;
+        return Ok(());
+    }
+
+    trace!("Receiver {} is offline, message of {} queued", receiver, my_id);
+    return Ok(());
+}
@@ -5,11 +5,12 @@ use warp::ws::Message;
 use crate::utils::tools::send_msg_specific;
 
 
-pub async fn on_want_symm_key(data: Vec<u8>, my_id: &Uuid) -> anyhow::Result<()> {
-    let WantSymmKeyMsg { user: receiver } = WantSymmKeyMsg::deserialize(&data)?;
-    
+pub async fn on_want_symm_key(msg: WantSymmKeyMsg, my_id: &Uuid) -> anyhow::Result<()> {
+    let receiver = msg.user;
+
     let packet = WantSymmKeyMsg {
-        user: my_id.clone()
+        user: my_id.clone(),
+        ..msg
     }.serialize();
 
     send_msg_specific(receiver, Message::binary(packet)).await?;
@@ -1,3 +1,4 @@
 pub mod disconnect;
 pub mod connect;
-pub mod messages;
\ No newline at end of file
+pub mod messages;
+pub mod hello;
\ No newline at end of file
@@ -1,15 +1,16 @@
 use std::{collections::HashMap, str::FromStr};
 
 use anyhow::anyhow;
+use futures_util::{stream, StreamExt};
 use log::trace;
 use packets::encryption::sign::validate_signature;
 use tokio::fs::File;
 use tokio_util::io::ReaderStream;
 use uuid::Uuid;
-use warp::{hyper::StatusCode, reply::{self, Response}, http::HeaderValue};
+use warp::{hyper::{body::Bytes, StatusCode}, reply::{self, Response}, http::HeaderValue};
 
 use crate::{
-    file::tools::{get_chunk_file, get_uploading_file},
+    file::{blobs::read_chunk, controller::index::ReceiverStatus, tools::{get_uploading_controller, touch_transfer}, rate::throttle_download},
     utils::arcs::get_user
 };
 
@@ -37,29 +38,51 @@ pub async fn on_download(
         let signature = hex::decode(signature)?;
         let index = u64::from_str(index)?;
 
-        let file = get_uploading_file(&uuid).await?;
-        let receiver = get_user(&file.receiver).await?;
-        let pub_key = receiver.public_key;
-
-        if pub_key.is_none() {
-            trace!("No pubkey");
-            return Err(anyhow!("PubKey of receiver is None."));
+        // The signature tells which of the receivers is downloading
+        let controller = get_uploading_controller(&uuid).await?;
+        let mut receiver = None;
+        for (pos, state) in controller.receivers.iter().enumerate() {
+            if state.status != ReceiverStatus::Accepted {
+                continue;
+            }
+
+            let pub_key = get_user(&state.id).await.ok().and_then(|e| e.public_key);
+            if pub_key.is_none() {
+                continue;
+            }
+
+            let is_valid = validate_signature(&uuid.as_bytes().to_vec(), &signature, &pub_key.unwrap())?;
+            if is_valid {
+                receiver = Some(pos);
+                break;
+            }
         }
 
-        let pub_key = pub_key.unwrap();
-        let is_valid = validate_signature(&uuid.as_bytes().to_vec(), &signature, &pub_key)?;
-        if !is_valid {
+        if receiver.is_none() {
             trace!("Not a valid signature");
             return Err(anyhow!("Receiver could not be verified."));
         }
 
-        let chunk_path = get_chunk_file(&uuid, index).await?;
+        let receiver = receiver.unwrap();
+        touch_transfer(&uuid).await;
+
+        // Header of this receiver followed by the shared blob, the same layout the sender uploaded
+        let (header, blob_path) = read_chunk(&uuid, index, receiver).await?;
+
+        let size = blob_path.metadata()?;
+        let size = header.len() as u64 + size.len();
+
+        let blob_file = File::open(&blob_path).await?;
+        let header = stream::once(async move { Ok(Bytes::from(header)) });
 
-        let size = chunk_path.metadata()?;
-        let size = size.len();
+        let receiver_id = controller.receivers[receiver].id;
+        let reader = header.chain(ReaderStream::new(blob_file)).then(move |item: std::io::Result<Bytes>| async move {
+            if item.is_ok() {
+                throttle_download(&receiver_id, item.as_ref().unwrap().len() as u64).await;
+            }
 
-        let chunk_file = File::open(&chunk_path).await?;
-        let reader = ReaderStream::new(chunk_file);
+            return item;
+        });
 
         trace!("Returning with stream...");
         let body = warp::hyper::Body::wrap_stream(reader);
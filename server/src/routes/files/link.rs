@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use log::trace;
+use packets::{
+    consts::U64_SIZE,
+    encryption::sign::validate_signature,
+    file::{chunk::index::{is_valid_encrypted_size, ChunkHeaderInfo, ChunkLink}, processing::tools::get_chunk_size},
+    util::compression::Compression,
+};
+use warp::{hyper::StatusCode, reply, Buf};
+
+use crate::{
+    file::{blobs::link_chunk, journal::has_chunk, tools::get_uploading_controller},
+    queue::tools::get_user_fingerprint,
+    routes::files::upload::{get_target_position, on_chunk_stored},
+    utils::arcs::get_user,
+};
+
+// Stores a chunk by pointing it at a blob the sender uploaded before, so a file sent
+// to multiple receivers is only uploaded once. Answers 404 if the blob has to be uploaded.
+pub async fn on_link(param: HashMap<String, String>, mut body: impl Buf) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    // None if the blob is unknown, false if the receiver is not part of the transfer anymore
+    let res: anyhow::Result<Option<bool>> = async move {
+        let body = body.copy_to_bytes(body.remaining()).to_vec();
+        let link = ChunkLink::deserialize(&body)?;
+        let header = ChunkHeaderInfo::deserialize(&link.header)?;
+
+        let uuid = header.uuid;
+        let chunk_index = header.chunk_index;
+
+        trace!("Linking chunk {} of {}", chunk_index, uuid);
+        let controller = get_uploading_controller(&uuid).await?;
+        let receiver = get_target_position(&controller, &param);
+        if receiver.is_none() {
+            return Ok(Some(false));
+        }
+
+        let receiver = receiver.unwrap();
+        if has_chunk(&uuid, chunk_index, receiver).await {
+            trace!("Chunk {} of {} is already stored for receiver {}", chunk_index, uuid, receiver);
+            return Ok(Some(true));
+        }
+
+        let file = &controller.file;
+        let info = get_user(&file.sender).await?;
+
+        if header.compression != Compression::None && header.compression != file.compression {
+            return Err(anyhow!("Chunk {} is compressed with {:?}, but the file was offered with {:?}.", chunk_index, header.compression, file.compression));
+        }
+
+        let chunk_size = get_chunk_size(chunk_index, file.size, file.chunk_size)?;
+        if !is_valid_encrypted_size(header.encrypted_size, chunk_size, header.compression) {
+            return Err(anyhow!("Chunk {} has invalid size {} for {} bytes of data.", chunk_index, header.encrypted_size, chunk_size));
+        }
+
+        let pub_key = info.public_key;
+        if pub_key.is_none() {
+            return Err(anyhow!("Public key for user is none."));
+        }
+
+        let pub_key = pub_key.unwrap();
+        let is_valid = validate_signature(&link.get_auth_data(), &link.signature, &pub_key)?;
+        if !is_valid {
+            return Err(anyhow!("Link of chunk {} is not valid.", chunk_index));
+        }
+
+        let owner = get_user_fingerprint(&file.sender).await;
+        if owner.is_none() {
+            return Err(anyhow!("Sender of {} did not send a public key.", uuid));
+        }
+
+        // The blob has to be the encrypted data of this header followed by the signature
+        let blob_size = header.encrypted_size + (U64_SIZE + pub_key.size() as usize) as u64;
+        let linked = link_chunk(&uuid, chunk_index, receiver, &link.header, &link.blob_hash, &owner.unwrap(), blob_size).await?;
+        if !linked {
+            trace!("Blob {} of chunk {} is unknown", hex::encode(&link.blob_hash), chunk_index);
+            return Ok(None);
+        }
+
+        on_chunk_stored(&controller, &uuid, chunk_index, receiver).await;
+        Ok(Some(true))
+    }
+    .await;
+
+    if res.is_err() {
+        eprintln!("Link Error: {:?}", res.unwrap_err());
+        return Ok(Box::new(reply::with_status(
+            "Internal Server Error, (either user request was faulty or a serious bug)",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )));
+    }
+
+    let res = res.unwrap();
+    if res.is_none() {
+        return Ok(Box::new(reply::with_status("Unknown blob, upload the chunk instead.", StatusCode::NOT_FOUND)));
+    }
+
+    if !res.unwrap() {
+        return Ok(Box::new(reply::with_status("The receiver is not part of the transfer anymore.", StatusCode::GONE)));
+    }
+
+    return Ok(Box::new(warp::reply::html("linked.")));
+}
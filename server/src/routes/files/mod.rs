@@ -1,2 +1,3 @@
 pub mod upload;
-pub mod download;
\ No newline at end of file
+pub mod download;
+pub mod link;
\ No newline at end of file
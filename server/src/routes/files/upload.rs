@@ -1,41 +1,39 @@
+use std::{collections::HashMap, str::FromStr};
+
 use anyhow::anyhow;
-use futures_util::{Stream, StreamExt};
+use futures_util::Stream;
 use log::trace;
-use openssl::{pkey::PKey, sign::Verifier};
+use openssl::{hash::Hasher, pkey::PKey, sign::Verifier};
 use packets::{
     consts::{MSG_DIGEST, U64_SIZE, UUID_SIZE},
-    file::processing::ready::ChunkReadyMsg,
+    file::{chunk::index::is_valid_encrypted_size, processing::{ready::ChunkReadyMsg, tools::get_chunk_size}},
     types::ByteMessage,
-    util::tools::{u64_from_vec, uuid_from_vec, vec_to_usize},
+    util::{compression::Compression, tools::{u64_from_vec, uuid_from_vec, vec_to_usize}},
 };
 use tokio::{
     fs::{remove_file, File},
     io::AsyncWriteExt,
 };
+use uuid::Uuid;
 use warp::{hyper::StatusCode, reply, ws::Message, Buf};
 
 use crate::{
-    file::tools::{get_chunk_file, get_uploading_file},
+    file::{blobs::{get_temp_blob_file, store_chunk}, tools::{get_uploading_controller, touch_transfer}, controller::index::Controller, journal::{has_chunk, update_journal}, rate::throttle_upload},
+    queue::tools::get_user_fingerprint,
     utils::{
         arcs::get_user,
-        stream::s2vec,
+        stream::{s2piece, s2vec},
         tools::send_msg_specific,
     },
 };
 
-pub async fn on_upload<S, B>(mut body: S) -> Result<Box<dyn warp::Reply>, warp::Rejection>
+pub async fn on_upload<S, B>(param: HashMap<String, String>, mut body: S) -> Result<Box<dyn warp::Reply>, warp::Rejection>
 where
     S: Stream<Item = Result<B, warp::Error>> + Send + 'static + Unpin,
     B: Buf,
 {
-    let res: anyhow::Result<()> = async move {
+    let res: anyhow::Result<bool> = async move {
         let mut previous: Vec<u8> = Vec::new();
-        println!("Getting sig size...");
-        let b_signature_size = s2vec(&mut body, U64_SIZE, &mut previous).await?;
-        let signature_size = vec_to_usize(&mut b_signature_size.clone())?;
-
-        println!("Getting sig...");
-        let signature = s2vec(&mut body, signature_size, &mut previous).await?;
         println!("Getting uuid...");
         let b_uuid = s2vec(&mut body, UUID_SIZE, &mut previous).await?;
         let uuid = uuid_from_vec(&mut b_uuid.clone())?;
@@ -44,6 +42,9 @@ where
         let b_chunk_index = s2vec(&mut body, U64_SIZE, &mut previous).await?;
         let chunk_index = u64_from_vec(&mut b_chunk_index.clone())?;
 
+        let b_compression = s2vec(&mut body, 1, &mut previous).await?;
+        let compression = Compression::from_indicator(b_compression[0])?;
+
         println!("Getting key siz...");
         let b_key_size = s2vec(&mut body, U64_SIZE, &mut previous).await?;
         let key_size = vec_to_usize(&mut b_key_size.clone())?;
@@ -58,10 +59,35 @@ where
         println!("Getting iv...");
         let b_iv = s2vec(&mut body, iv_size, &mut previous).await?;
 
+        let b_encrypted_size = s2vec(&mut body, U64_SIZE, &mut previous).await?;
+        let encrypted_size = u64_from_vec(&mut b_encrypted_size.clone())?;
+
         trace!("Getting file in upload {}", uuid);
-        let file = get_uploading_file(&uuid).await?;
+        let controller = get_uploading_controller(&uuid).await?;
+        let receiver = get_target_position(&controller, &param);
+        if receiver.is_none() {
+            trace!("Receiver {:?} is not part of {}", param.get("receiver"), uuid);
+            return Ok(false);
+        }
+
+        let receiver = receiver.unwrap();
+        if has_chunk(&uuid, chunk_index, receiver).await {
+            trace!("Chunk {} of {} is already stored for receiver {}", chunk_index, uuid, receiver);
+            return Ok(true);
+        }
+
+        let file = &controller.file;
         let info = get_user(&file.sender).await?;
 
+        if compression != Compression::None && compression != file.compression {
+            return Err(anyhow!("Chunk {} is compressed with {:?}, but the file was offered with {:?}.", chunk_index, compression, file.compression));
+        }
+
+        let chunk_size = get_chunk_size(chunk_index, file.size, file.chunk_size)?;
+        if !is_valid_encrypted_size(encrypted_size, chunk_size, compression) {
+            return Err(anyhow!("Chunk {} has invalid size {} for {} bytes of data.", chunk_index, encrypted_size, chunk_size));
+        }
+
         let pub_key = info.public_key;
         if pub_key.is_none() {
             return Err(anyhow!("Public key for user is none."));
@@ -70,63 +96,74 @@ where
         let pub_key = pub_key.unwrap();
         let p_key = PKey::from_rsa(pub_key.clone())?;
 
-        let file_path = get_chunk_file(&uuid, chunk_index).await?;
-        let mut chunk_file = File::create(file_path.clone()).await?;
+        let owner = get_user_fingerprint(&file.sender).await;
+        if owner.is_none() {
+            return Err(anyhow!("Sender of {} did not send a public key.", uuid));
+        }
 
-        let e = std::env::current_dir()?;
-        let e = e.join(file_path.clone());
-        let e = e.to_str();
+        let owner = owner.unwrap();
+        let header = [b_uuid, b_chunk_index, b_compression, b_key_size, b_key, b_iv_size, b_iv, b_encrypted_size].concat();
 
-        if e.is_some() {
-            trace!("Writing chunk at {}", e.unwrap());
-        }
+        let blob_path = get_temp_blob_file(&uuid, chunk_index, receiver).await?;
+        let mut blob_file = File::create(blob_path.clone()).await?;
+        trace!("Writing blob of chunk {} at {:?}", chunk_index, blob_path);
 
         let inner = async {
-            chunk_file.write_all(&b_signature_size).await?;
-            chunk_file.write_all(&signature).await?;
-            chunk_file.write_all(&b_uuid).await?;
-            chunk_file.write_all(&b_chunk_index).await?;
-            chunk_file.write_all(&b_key_size).await?;
-            chunk_file.write_all(&b_key).await?;
-            chunk_file.write_all(&b_iv_size).await?;
-            chunk_file.write_all(&b_iv).await?;
-            chunk_file.write_all(&previous).await?;
-
             let mut verifier = Verifier::new(*MSG_DIGEST, &p_key)?;
-            verifier.update(&previous)?;
+            let mut hasher = Hasher::new(*MSG_DIGEST)?;
 
             trace!("Starting to store file {}...", uuid);
-            while let Some(item) = body.next().await {
-                let item = item?;
-                let item = item.chunk();
+            let mut left = encrypted_size as usize;
+            while left > 0 {
+                let piece = s2piece(&mut body, left, &mut previous).await?;
+                left -= piece.len();
+
+                throttle_upload(&file.sender, piece.len() as u64).await;
 
-                verifier.update(item)?;
-                chunk_file.write_all(item).await?;
+                verifier.update(&piece)?;
+                hasher.update(&piece)?;
+                blob_file.write_all(&piece).await?;
             }
 
+            let b_signature_size = s2vec(&mut body, U64_SIZE, &mut previous).await?;
+            let signature_size = vec_to_usize(&mut b_signature_size.clone())?;
+            if signature_size > p_key.size() {
+                return Err(anyhow!("Signature of chunk is too large."));
+            }
+
+            let signature = s2vec(&mut body, signature_size, &mut previous).await?;
+
             let is_valid = verifier.verify(&signature)?;
             if !is_valid {
                 return Err(anyhow!("Chunk is not valid."));
             }
 
-            send_msg_specific(
-                file.receiver,
-                Message::binary(ChunkReadyMsg { uuid, chunk_index }.serialize()),
-            )
-            .await?;
-            trace!("Sent ready msg to {}", file.receiver);
+            hasher.update(&b_signature_size)?;
+            hasher.update(&signature)?;
+
+            blob_file.write_all(&b_signature_size).await?;
+            blob_file.write_all(&signature).await?;
+            blob_file.shutdown().await?;
+
+            let hash = hasher.finish()?;
+            store_chunk(&uuid, chunk_index, receiver, &header, &blob_path, &hash, &owner).await?;
+
+            on_chunk_stored(&controller, &uuid, chunk_index, receiver).await;
             Ok(()) as anyhow::Result<()>
         };
 
         let res = inner.await;
         if res.is_err() {
-            let e = chunk_file.shutdown().await;
-            remove_file(file_path).await?;
+            let e = blob_file.shutdown().await;
+            if blob_path.is_file() {
+                remove_file(blob_path).await?;
+            }
+
             e?;
             res?;
         }
 
-        Ok(())
+        Ok(true)
     }
     .await;
 
@@ -138,5 +175,51 @@ where
         )));
     }
 
+    if !res.unwrap() {
+        return Ok(Box::new(reply::with_status("The receiver is not part of the transfer anymore.", StatusCode::GONE)));
+    }
+
     return Ok(Box::new(warp::reply::html("uploaded.")));
 }
+
+// Position of the receiver the chunk is stored for, the first one if the sender did not say
+pub fn get_target_position(controller: &Controller, param: &HashMap<String, String>) -> Option<usize> {
+    let receiver = param.get("receiver");
+    if receiver.is_none() {
+        return controller.get_accepted_position(&controller.file.receiver);
+    }
+
+    let receiver = Uuid::from_str(receiver.unwrap()).ok()?;
+    return controller.get_accepted_position(&receiver);
+}
+
+// Journals the chunk and tells the receiver that it can be downloaded
+pub async fn on_chunk_stored(controller: &Controller, uuid: &Uuid, chunk_index: u64, receiver: usize) {
+    touch_transfer(uuid).await;
+
+    let res = update_journal(uuid, |j| {
+        let is_valid = j.is_valid_chunk(chunk_index);
+        let entry = j.get_receiver_mut(receiver);
+        if is_valid && entry.is_some() {
+            entry.unwrap().uploaded.insert(chunk_index);
+        }
+    }).await;
+    if res.is_err() {
+        trace!("Could not journal chunk {} of {}: {}", chunk_index, uuid, res.unwrap_err());
+    }
+
+    let receiver = controller.receivers[receiver].id;
+    let res = send_msg_specific(
+        receiver,
+        Message::binary(ChunkReadyMsg { uuid: uuid.clone(), chunk_index }.serialize()),
+    )
+    .await;
+
+    if res.is_err() {
+        // The receiver gets the chunk once it resumes the transfer
+        trace!("Receiver {} is offline, keeping chunk {} of {}", receiver, chunk_index, uuid);
+        return;
+    }
+
+    trace!("Sent ready msg to {}", receiver);
+}
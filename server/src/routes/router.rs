@@ -1,17 +1,18 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::{collections::HashMap, net::SocketAddr, process::exit, sync::Arc};
 
 use crate::routes::{
     chat::connect::user_connected,
-    files::{download::on_download, upload::on_upload},
+    files::{download::on_download, link::on_link, upload::on_upload},
     index::get_index,
 };
 use colorize::AnsiColor;
-use packets::consts::{CHUNK_SIZE, ONE_MB_SIZE};
 use warp::Filter;
 
+use crate::{config::types::TlsConfig, file::chunks::{get_chunk_bounds, get_max_upload_size}, tls::{tools::{bind_tls, load_certified_key, reload_on_sighup, serve_redirect}, types::CertResolver}};
+
 use super::{info::on_info, list::on_list};
 
-pub async fn serve_routes(addr: impl Into<SocketAddr>) {
+pub async fn serve_routes(addr: impl Into<SocketAddr>, tls: Option<TlsConfig>) {
     // GET / -> index html
     let index = warp::path::end().and_then(get_index);
 
@@ -34,21 +35,56 @@ pub async fn serve_routes(addr: impl Into<SocketAddr>) {
             })
         });
 
+    let max_upload_size = get_max_upload_size(get_chunk_bounds().await.max);
     let upload_route = warp::path!("file" / "upload")
-        .and(warp::body::content_length_limit(CHUNK_SIZE + 3* ONE_MB_SIZE))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::content_length_limit(max_upload_size))
         .and(warp::body::stream())
         .and_then(on_upload);
 
+    // Only holds a chunk header and a signature
+    let link_route = warp::path!("file" / "link")
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::aggregate())
+        .and_then(on_link);
+
     let download_route = warp::path!("file" / "download")
         .and(warp::query::<HashMap<String, String>>())
         .and_then(on_download);
 
     let routes = warp::get()
         .and(index.or(chat).or(list_route).or(info_route).or(download_route))
-        .or(warp::post().and(upload_route));
+        .or(warp::post().and(upload_route.or(link_route)));
     let addr: SocketAddr = addr.into();
 
-    let url = format!("http://{}", addr).blue();
+    if tls.is_none() {
+        let url = format!("http://{}", addr).blue();
+        println!("{} {} !", "Listening on".b_black(), url);
+        warp::serve(routes).run(addr).await;
+        return;
+    }
+
+    let tls = tls.unwrap();
+    let key = load_certified_key(&tls);
+    if key.is_err() {
+        eprintln!("Could not load certificate: {}", key.err().unwrap());
+        exit(1);
+    }
+
+    let resolver = Arc::new(CertResolver::new(key.unwrap()));
+    let incoming = bind_tls(addr, resolver.clone()).await;
+    if incoming.is_err() {
+        eprintln!("Could not listen on {}: {}", addr, incoming.unwrap_err());
+        exit(1);
+    }
+
+    tokio::spawn(reload_on_sighup(tls.clone(), resolver));
+    if tls.redirect_port.is_some() {
+        tokio::spawn(serve_redirect(SocketAddr::new(addr.ip(), tls.redirect_port.unwrap()), addr.port()));
+    }
+
+    let url = format!("https://{}", addr).blue();
     println!("{} {} !", "Listening on".b_black(), url);
-    warp::serve(routes).run(addr).await;
+    warp::serve(routes).run_incoming(incoming.unwrap()).await;
 }
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+use tokio::sync::RwLock;
+
+use super::{memory::MemoryStorage, types::StorageArc};
+
+lazy_static! {
+    // Replaced by the backend given on the command line before anything is loaded
+    pub static ref STORAGE: StorageArc = Arc::new(RwLock::new(Box::new(MemoryStorage::default())));
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, path::Path};
+
+use packets::file::journal::TransferJournal;
+use sled::Tree;
+use uuid::Uuid;
+
+use crate::file::types::BlobInfo;
+
+use super::{index::Storage, types::Identity};
+
+// Stores everything in a sled database. sled writes changes to disk in the background every few hundred ms,
+// so a crash may lose the last updates. Chunks missing from the journal are uploaded again on resume.
+pub struct DiskStorage {
+    identities: Tree,
+    transfers: Tree,
+    blobs: Tree,
+}
+
+impl DiskStorage {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = sled::open(path)?;
+
+        return Ok(DiskStorage {
+            identities: db.open_tree("identities")?,
+            transfers: db.open_tree("transfers")?,
+            blobs: db.open_tree("blobs")?,
+        });
+    }
+}
+
+impl Storage for DiskStorage {
+    fn get_identity(&self, fingerprint: &[u8]) -> anyhow::Result<Option<Identity>> {
+        let data = self.identities.get(fingerprint)?;
+        if data.is_none() {
+            return Ok(None);
+        }
+
+        return Ok(Some(Identity::deserialize(&data.unwrap().to_vec())?));
+    }
+
+    fn put_identity(&self, fingerprint: &[u8], identity: &Identity) -> anyhow::Result<()> {
+        self.identities.insert(fingerprint, identity.serialize()?)?;
+        return Ok(());
+    }
+
+    fn get_transfers(&self) -> anyhow::Result<Vec<TransferJournal>> {
+        let mut transfers = Vec::new();
+        for entry in self.transfers.iter() {
+            let (uuid, data) = entry?;
+            let journal = TransferJournal::deserialize(&data.to_vec());
+            if journal.is_err() {
+                eprintln!("Could not read stored transfer {:?}: {}", Uuid::from_slice(&uuid), journal.unwrap_err());
+                continue;
+            }
+
+            transfers.push(journal.unwrap());
+        }
+
+        return Ok(transfers);
+    }
+
+    fn put_transfer(&self, journal: &TransferJournal) -> anyhow::Result<()> {
+        self.transfers.insert(journal.uuid.as_bytes(), journal.serialize())?;
+        return Ok(());
+    }
+
+    fn remove_transfer(&self, uuid: &Uuid) -> anyhow::Result<()> {
+        self.transfers.remove(uuid.as_bytes())?;
+        return Ok(());
+    }
+
+    fn get_blobs(&self) -> anyhow::Result<HashMap<String, BlobInfo>> {
+        let mut blobs = HashMap::new();
+        for entry in self.blobs.iter() {
+            let (hash, data) = entry?;
+            let hash = String::from_utf8(hash.to_vec())?;
+
+            blobs.insert(hash, BlobInfo::deserialize(&data.to_vec())?);
+        }
+
+        return Ok(blobs);
+    }
+
+    fn put_blob(&self, hash: &str, info: &BlobInfo) -> anyhow::Result<()> {
+        self.blobs.insert(hash.as_bytes(), info.serialize()?)?;
+        return Ok(());
+    }
+
+    fn remove_blob(&self, hash: &str) -> anyhow::Result<()> {
+        self.blobs.remove(hash.as_bytes())?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+
+use packets::file::journal::TransferJournal;
+use uuid::Uuid;
+
+use crate::file::types::BlobInfo;
+
+use super::types::Identity;
+
+// Everything the server needs to pick up accepted transfers after a restart.
+// Offers which have not been answered are not stored, the question is bound to the sessions of both clients
+// which do not survive a reconnect.
+pub trait Storage: Send + Sync {
+    // Identities are stored by the fingerprint of their public key
+    fn get_identity(&self, fingerprint: &[u8]) -> anyhow::Result<Option<Identity>>;
+    fn put_identity(&self, fingerprint: &[u8], identity: &Identity) -> anyhow::Result<()>;
+
+    fn get_transfers(&self) -> anyhow::Result<Vec<TransferJournal>>;
+    fn put_transfer(&self, journal: &TransferJournal) -> anyhow::Result<()>;
+    fn remove_transfer(&self, uuid: &Uuid) -> anyhow::Result<()>;
+
+    // Chunk index, key is the hex encoded sha256 of the blob
+    fn get_blobs(&self) -> anyhow::Result<HashMap<String, BlobInfo>>;
+    fn put_blob(&self, hash: &str, info: &BlobInfo) -> anyhow::Result<()>;
+    fn remove_blob(&self, hash: &str) -> anyhow::Result<()>;
+}
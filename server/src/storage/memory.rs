@@ -0,0 +1,60 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::anyhow;
+use packets::file::journal::TransferJournal;
+use uuid::Uuid;
+
+use crate::file::types::BlobInfo;
+
+use super::{index::Storage, types::Identity};
+
+// Keeps nothing after a restart, transfers can only be resumed as long as the server is running
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    identities: Mutex<HashMap<Vec<u8>, Identity>>,
+    transfers: Mutex<HashMap<Uuid, TransferJournal>>,
+    blobs: Mutex<HashMap<String, BlobInfo>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> anyhow::Result<std::sync::MutexGuard<'_, T>> {
+    return mutex.lock().map_err(|_| anyhow!("Storage lock is poisoned."));
+}
+
+impl Storage for MemoryStorage {
+    fn get_identity(&self, fingerprint: &[u8]) -> anyhow::Result<Option<Identity>> {
+        return Ok(lock(&self.identities)?.get(fingerprint).cloned());
+    }
+
+    fn put_identity(&self, fingerprint: &[u8], identity: &Identity) -> anyhow::Result<()> {
+        lock(&self.identities)?.insert(fingerprint.to_vec(), identity.clone());
+        return Ok(());
+    }
+
+    fn get_transfers(&self) -> anyhow::Result<Vec<TransferJournal>> {
+        return Ok(lock(&self.transfers)?.values().cloned().collect());
+    }
+
+    fn put_transfer(&self, journal: &TransferJournal) -> anyhow::Result<()> {
+        lock(&self.transfers)?.insert(journal.uuid, journal.clone());
+        return Ok(());
+    }
+
+    fn remove_transfer(&self, uuid: &Uuid) -> anyhow::Result<()> {
+        lock(&self.transfers)?.remove(uuid);
+        return Ok(());
+    }
+
+    fn get_blobs(&self) -> anyhow::Result<HashMap<String, BlobInfo>> {
+        return Ok(lock(&self.blobs)?.clone());
+    }
+
+    fn put_blob(&self, hash: &str, info: &BlobInfo) -> anyhow::Result<()> {
+        lock(&self.blobs)?.insert(hash.to_string(), info.clone());
+        return Ok(());
+    }
+
+    fn remove_blob(&self, hash: &str) -> anyhow::Result<()> {
+        lock(&self.blobs)?.remove(hash);
+        return Ok(());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod index;
+pub mod types;
+pub mod consts;
+pub mod tools;
+pub mod memory;
+pub mod disk;
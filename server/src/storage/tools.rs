@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use log::trace;
+use packets::encryption::fingerprint::format_fingerprint;
+use uuid::Uuid;
+
+use crate::{file::consts::USERS, queue::tools::get_user_fingerprint};
+
+use super::{consts::STORAGE, disk::DiskStorage, index::Storage, memory::MemoryStorage, types::Identity};
+
+// `memory` keeps everything in memory, anything else is the directory of the database
+pub fn open_storage(location: &str) -> anyhow::Result<Box<dyn Storage>> {
+    if location == "memory" {
+        return Ok(Box::new(MemoryStorage::default()));
+    }
+
+    return Ok(Box::new(DiskStorage::open(Path::new(location))?));
+}
+
+pub async fn set_storage(storage: Box<dyn Storage>) {
+    let mut state = STORAGE.write().await;
+    *state = storage;
+
+    drop(state);
+}
+
+// Stores name and key of the user, called whenever one of them changes
+pub async fn save_identity(user: &Uuid) -> anyhow::Result<()> {
+    let fingerprint = get_user_fingerprint(user).await;
+    if fingerprint.is_none() {
+        return Ok(());
+    }
+
+    let state = USERS.read().await;
+    let info = state.get(user);
+    let identity = info.and_then(|e| e.public_key.clone().map(|key| Identity {
+        name: e.name.clone(),
+        public_key: key
+    }));
+
+    drop(state);
+    if identity.is_none() {
+        return Ok(());
+    }
+
+    trace!("Saving identity of {}", user);
+    let storage = STORAGE.read().await;
+    let res = storage.put_identity(&fingerprint.unwrap(), &identity.unwrap());
+
+    drop(storage);
+    return res;
+}
+
+// Name of the identity with this fingerprint, the fingerprint itself if it is unknown
+pub async fn get_identity_name(fingerprint: &[u8]) -> String {
+    let storage = STORAGE.read().await;
+    let identity = storage.get_identity(fingerprint);
+
+    drop(storage);
+    let name = identity.ok().flatten().and_then(|e| e.name);
+    return name.unwrap_or_else(|| format_fingerprint(fingerprint));
+}
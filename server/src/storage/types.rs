@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use openssl::{pkey::Public, rsa::Rsa};
+use packets::util::{tools::{usize_to_vec, vec_to_usize}, vec::extract_vec};
+use tokio::sync::RwLock;
+
+use super::index::Storage;
+
+// Last known name and key of a user
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub public_key: Rsa<Public>,
+}
+
+impl Identity {
+    // Layout: `key size | key (der) | name`, users without a name have an empty one
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let mut key = self.public_key.public_key_to_der()?;
+        let mut merged = usize_to_vec(key.len())?;
+
+        merged.append(&mut key);
+        merged.append(&mut self.name.clone().unwrap_or_default().as_bytes().to_vec());
+
+        return Ok(merged);
+    }
+
+    pub fn deserialize(data: &Vec<u8>) -> anyhow::Result<Self> {
+        let mut data = data.clone();
+
+        let key_size = vec_to_usize(&mut data)?;
+        let key = extract_vec(0..key_size, &mut data)?;
+        let public_key = Rsa::public_key_from_der(&key)?;
+
+        let name = String::from_utf8(data)?;
+        let name = if name.is_empty() { None } else { Some(name) };
+
+        return Ok(Identity { name, public_key });
+    }
+}
+
+pub type StorageArc = Arc<RwLock<Box<dyn Storage>>>;
@@ -0,0 +1,169 @@
+use std::{fs::File, io::BufReader, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use colorize::AnsiColor;
+use log::{debug, trace};
+use rustls_pemfile::Item;
+use tokio::{net::{TcpListener, TcpStream}, sync::mpsc, time::timeout};
+use tokio_rustls::{rustls::{sign::{any_supported_type, CertifiedKey}, Certificate, PrivateKey, ServerConfig}, server::TlsStream, TlsAcceptor};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use warp::{filters::path::FullPath, http::{StatusCode, Uri}, Filter, Reply};
+
+use crate::config::types::TlsConfig;
+
+use super::types::CertResolver;
+
+// Clients which have not finished their handshake by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn open_pem(path: &Path) -> anyhow::Result<BufReader<File>> {
+    let file = File::open(path);
+    if file.is_err() {
+        return Err(anyhow!("Could not open '{}': {}", path.display(), file.unwrap_err()));
+    }
+
+    return Ok(BufReader::new(file.unwrap()));
+}
+
+// Reads the certificate chain and the private key (pkcs8 or rsa) of the config
+pub fn load_certified_key(tls: &TlsConfig) -> anyhow::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut open_pem(&tls.cert)?);
+    if certs.is_err() || certs.as_ref().unwrap().is_empty() {
+        return Err(anyhow!("No certificate found in '{}'.", tls.cert.display()));
+    }
+
+    let items = rustls_pemfile::read_all(&mut open_pem(&tls.key)?);
+    if items.is_err() {
+        return Err(anyhow!("Could not read private key '{}': {}", tls.key.display(), items.unwrap_err()));
+    }
+
+    let key = items.unwrap().into_iter().find_map(|e| match e {
+        Item::PKCS8Key(key) | Item::RSAKey(key) => Some(key),
+        _ => None
+    });
+
+    if key.is_none() {
+        return Err(anyhow!("No private key found in '{}'.", tls.key.display()));
+    }
+
+    let signing_key = any_supported_type(&PrivateKey(key.unwrap()));
+    if signing_key.is_err() {
+        return Err(anyhow!("Unsupported private key in '{}'.", tls.key.display()));
+    }
+
+    let certs = certs.unwrap().into_iter().map(Certificate).collect();
+    return Ok(CertifiedKey::new(certs, signing_key.unwrap()));
+}
+
+// Accepts connections on `addr` and yields them once their tls handshake is done
+pub async fn bind_tls(addr: SocketAddr, resolver: Arc<CertResolver>) -> anyhow::Result<UnboundedReceiverStream<std::io::Result<TlsStream<TcpStream>>>> {
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+
+    // Websockets are upgraded from http/1.1
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind(addr).await?;
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let conn = listener.accept().await;
+            if conn.is_err() {
+                debug!("Could not accept connection: {}", conn.unwrap_err());
+                continue;
+            }
+
+            let (stream, remote) = conn.unwrap();
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+
+            // Handshakes run on their own, so slow clients do not hold up others
+            tokio::spawn(async move {
+                let stream = timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await;
+                if stream.is_err() {
+                    trace!("Tls handshake with {} timed out", remote);
+                    return;
+                }
+
+                let stream = stream.unwrap();
+                if stream.is_err() {
+                    trace!("Tls handshake with {} failed: {}", remote, stream.unwrap_err());
+                    return;
+                }
+
+                let _ = tx.send(stream);
+            });
+        }
+    });
+
+    return Ok(UnboundedReceiverStream::new(rx));
+}
+
+// Loads the certificate again whenever the server receives SIGHUP, e.g. after it has been renewed.
+// The old one is kept if the new one can not be read.
+#[cfg(unix)]
+pub async fn reload_on_sighup(tls: TlsConfig, resolver: Arc<CertResolver>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let hangups = signal(SignalKind::hangup());
+    if hangups.is_err() {
+        eprintln!("Could not listen for SIGHUP, certificates will not be reloaded: {}", hangups.unwrap_err());
+        return;
+    }
+
+    let mut hangups = hangups.unwrap();
+    while hangups.recv().await.is_some() {
+        let key = load_certified_key(&tls);
+        if key.is_err() {
+            eprintln!("Could not reload certificate: {}", key.err().unwrap());
+            continue;
+        }
+
+        resolver.set_key(key.unwrap());
+        println!("Reloaded certificate '{}'", tls.cert.display());
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_tls: TlsConfig, _resolver: Arc<CertResolver>) {}
+
+// Host of the `Host` header without its port, ipv6 addresses keep their brackets
+fn get_host_name(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host.split_inclusive(']').next().unwrap_or(host);
+    }
+
+    return host.split(':').next().unwrap_or(host);
+}
+
+fn get_redirect(path: FullPath, query: String, host: Option<String>, fallback: &str, https_port: u16) -> Box<dyn Reply> {
+    let host = host.unwrap_or(fallback.to_string());
+    let port = if https_port == 443 { String::new() } else { format!(":{}", https_port) };
+    let query = if query.is_empty() { query } else { format!("?{}", query) };
+
+    let uri = format!("https://{}{}{}{}", get_host_name(&host), port, path.as_str(), query).parse::<Uri>();
+    if uri.is_err() {
+        return Box::new(StatusCode::BAD_REQUEST);
+    }
+
+    // Unlike 301, 308 keeps the method and body of uploads
+    return Box::new(warp::redirect::permanent(uri.unwrap()));
+}
+
+// Plain http server on `addr` which sends every request to the https server on `https_port`
+pub async fn serve_redirect(addr: SocketAddr, https_port: u16) {
+    // Used for clients which do not send a `Host` header
+    let fallback = if addr.is_ipv6() { format!("[{}]", addr.ip()) } else { addr.ip().to_string() };
+    let redirect = warp::path::full()
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::optional::<String>("host"))
+        .map(move |path, query, host| get_redirect(path, query, host, &fallback, https_port));
+
+    let url = format!("http://{}", addr).blue();
+    println!("{} {} !", "Redirecting to https from".b_black(), url);
+    warp::serve(redirect).run(addr).await;
+}
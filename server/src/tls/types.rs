@@ -0,0 +1,28 @@
+use std::sync::{Arc, RwLock};
+
+use tokio_rustls::rustls::{server::{ClientHello, ResolvesServerCert}, sign::CertifiedKey};
+
+// Hands the current certificate to every handshake, so it can be swapped (e.g. on SIGHUP) without restarting the server
+pub struct CertResolver {
+    pub key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertResolver {
+    pub fn new(key: CertifiedKey) -> Self {
+        return CertResolver { key: RwLock::new(Arc::new(key)) };
+    }
+
+    pub fn set_key(&self, key: CertifiedKey) {
+        let state = self.key.write();
+        if state.is_ok() {
+            *state.unwrap() = Arc::new(key);
+        }
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let state = self.key.read().ok()?;
+        return Some(state.clone());
+    }
+}
@@ -1,5 +1,4 @@
 pub mod types;
 pub mod tools;
-pub mod vec;
 pub mod stream;
 pub mod arcs;
\ No newline at end of file
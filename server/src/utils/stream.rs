@@ -10,6 +10,29 @@ B: Buf {
     return vec_from_stream(stream, size, previous).await;
 }
 
+// Returns the next piece of the stream with at most `max` bytes, the rest is kept in `previous`
+pub async fn s2piece<S, B>(stream: &mut S, max: usize, previous: &mut Vec<u8>) -> anyhow::Result<Vec<u8>>
+where
+S: Stream<Item = Result<B, warp::Error>> + Send + 'static + Unpin,
+B: Buf {
+    if previous.is_empty() {
+        let item = stream.next().await;
+        if item.is_none() {
+            return Err(anyhow!("Error, stream was not long enough."));
+        }
+
+        let mut item = item.unwrap()?;
+        while item.has_remaining() {
+            let len = item.chunk().len();
+            previous.extend_from_slice(item.chunk());
+            item.advance(len);
+        }
+    }
+
+    let len = previous.len().min(max);
+    return Ok(previous.drain(0..len).collect());
+}
+
 
 pub async fn vec_from_stream<S, B>(stream: &mut S, size: usize, previous: &mut Vec<u8>) -> anyhow::Result<Vec<u8>>
 where
@@ -1,4 +1,5 @@
 use anyhow::anyhow;
+use packets::{communication::error::{ErrorCode, ErrorMsg}, types::ByteMessage};
 use uuid::Uuid;
 use warp::ws::Message;
 
@@ -17,6 +18,15 @@ pub fn send_msg(tx: &TXChannel, msg: Message) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn send_error(id: Uuid, error: &str) -> anyhow::Result<()> {
+    return send_error_code(id, ErrorCode::Generic, error).await;
+}
+
+pub async fn send_error_code(id: Uuid, code: ErrorCode, error: &str) -> anyhow::Result<()> {
+    let packet = ErrorMsg { code, error: error.to_string() }.serialize();
+    return send_msg_specific(id, Message::binary(packet)).await;
+}
+
 pub async fn send_msg_specific(id: Uuid, msg: Message) -> anyhow::Result<()> {
     let mut found = false;
 
@@ -1,12 +1,13 @@
-use std::{sync::Arc, collections::HashMap};
+use std::{sync::Arc, collections::HashMap, path::PathBuf};
 
 use clap::Parser;
 use openssl::{pkey::Public, rsa::Rsa};
-use packets::other::info::UserInfoBasic;
+use packets::{other::info::UserInfoBasic, util::rate::{parse_rate, parse_size}};
 use tokio::sync::{mpsc::{self, UnboundedSender}, RwLock};
 use uuid::Uuid;
 use warp::ws::Message;
 
+
 pub struct UserInfo {
     pub sender: mpsc::UnboundedSender<Message>,
     pub name: Option<String>,
@@ -24,19 +25,97 @@ pub type UsersList = Arc<RwLock<Vec<Uuid>>>;
 
 pub type TXChannel = UnboundedSender<Message>;
 
-/// A server to host rsa-encrypted messaging between clients
+/// A server to host rsa-encrypted messaging between clients.
+/// Every option can be set in the config file or as `RSA_MSG_<OPTION>` environment variable, the command line wins over both.
 #[derive(Parser, Debug)]
 #[command(author="sshcrack", about="A server to host rsa-encrypted messaging between clients", long_about = None)]
 pub struct Args {
-    /// Address to bind to
-    #[arg(long, short='b')]
+    /// TOML file with the server configuration
+    #[arg(long, short='c', env = "RSA_MSG_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to bind to, 127.0.0.1 by default
+    #[arg(long, short='b', env = "RSA_MSG_BIND")]
     pub bind: Option<std::net::IpAddr>,
 
-    /// Specifies on which port server should listen to
-    #[arg(
-        short='p',
-        long,
-        default_value_t = 3030
-    )]
-    pub port: u16,
-}
\ No newline at end of file
+    /// Specifies on which port server should listen to, 3030 by default
+    #[arg(short='p', long, env = "RSA_MSG_PORT")]
+    pub port: Option<u16>,
+
+    /// Directory uploaded chunks are stored in, `chunks` by default
+    #[arg(long, env = "RSA_MSG_CHUNK_DIR")]
+    pub chunk_dir: Option<PathBuf>,
+
+    /// Directory of the database transfers are stored in, so they can be resumed after a restart. `memory` keeps them in memory only, `state` by default
+    #[arg(long, env = "RSA_MSG_STORAGE")]
+    pub storage: Option<String>,
+
+    /// PEM certificate (chain) to serve https and wss with, needs --tls-key. Reloaded on SIGHUP
+    #[arg(long, env = "RSA_MSG_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the certificate, needs --tls-cert
+    #[arg(long, env = "RSA_MSG_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Port to redirect plain http requests to https from (e.g. 80), off by default
+    #[arg(long, env = "RSA_MSG_TLS_REDIRECT_PORT")]
+    pub tls_redirect_port: Option<u16>,
+
+    /// Log filter like `info` or `rsa_msg_server=trace`, RUST_LOG is used if not given
+    #[arg(long, env = "RSA_MSG_LOG")]
+    pub log: Option<String>,
+
+    /// How long messages to offline users are kept (in seconds), 7 days by default
+    #[arg(long, env = "RSA_MSG_QUEUE_TTL")]
+    pub queue_ttl: Option<u64>,
+
+    /// Max size of messages queued for one offline user (e.g. 4M), 4M by default
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_QUEUE_MAX_SIZE")]
+    pub queue_max_size: Option<u64>,
+
+    /// Max upload rate of each user (e.g. 500K or 2M), unlimited by default
+    #[arg(long, value_parser = parse_rate, env = "RSA_MSG_MAX_USER_UPLOAD_RATE")]
+    pub max_user_upload_rate: Option<u64>,
+
+    /// Max download rate of each user (e.g. 500K or 2M), unlimited by default
+    #[arg(long, value_parser = parse_rate, env = "RSA_MSG_MAX_USER_DOWNLOAD_RATE")]
+    pub max_user_download_rate: Option<u64>,
+
+    /// Smallest chunk size clients may use for file transfers (e.g. 512K), 64K by default
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_MIN_CHUNK_SIZE")]
+    pub min_chunk_size: Option<u64>,
+
+    /// Largest chunk size clients may use for file transfers (e.g. 50M), 100M by default
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_MAX_CHUNK_SIZE")]
+    pub max_chunk_size: Option<u64>,
+
+    /// Largest file users may send (e.g. 2G), unlimited by default
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_MAX_FILE_SIZE")]
+    pub max_file_size: Option<u64>,
+
+    /// How many files each user may send at the same time, unlimited by default
+    #[arg(long, env = "RSA_MSG_MAX_TRANSFERS_PER_USER")]
+    pub max_transfers_per_user: Option<usize>,
+
+    /// Sum of the file sizes each user may send at the same time (e.g. 10G), unlimited by default
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_USER_QUOTA")]
+    pub user_quota: Option<u64>,
+
+    /// Sum of the file sizes of all pending and running transfers (e.g. 100G), unlimited by default.
+    /// Files are reserved at their full size, so this is an upper bound rather than the disk usage
+    #[arg(long, value_parser = parse_size, env = "RSA_MSG_RESERVED_QUOTA")]
+    pub reserved_quota: Option<u64>,
+
+    /// How long offers nobody answered are kept (in seconds, 0 keeps them forever), 10 minutes by default
+    #[arg(long, env = "RSA_MSG_PENDING_TTL")]
+    pub pending_ttl: Option<u64>,
+
+    /// How long accepted transfers without any up- or downloads are kept (in seconds, 0 keeps them forever), 1 day by default
+    #[arg(long, env = "RSA_MSG_IDLE_TTL")]
+    pub idle_ttl: Option<u64>,
+
+    /// How long chunks every receiver has downloaded are kept (in seconds, 0 keeps them until the transfer is done), 5 minutes by default
+    #[arg(long, env = "RSA_MSG_DOWNLOADED_TTL")]
+    pub downloaded_ttl: Option<u64>,
+}